@@ -0,0 +1,142 @@
+//! 资源文件的内容指纹：导入全局库时找出字节完全相同的已有文件。
+//!
+//! - 指纹是流式 FNV-1a 64（十六进制 16 位），只用来快速挑候选；判定重复前总会再逐字节比较，
+//!   所以指纹碰撞不会造成误判。
+//! - 先按文件大小分桶，只有大小相同的已有文件才需要算指纹，导入大库时不必把整个库读一遍。
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv_update(mut h: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        h ^= *b as u64;
+        h = h.wrapping_mul(FNV_PRIME);
+    }
+    h
+}
+
+/// 文件内容的指纹。
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buf = [0u8; 64 * 1024];
+    let mut h = FNV_OFFSET;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        h = fnv_update(h, &buf[..n]);
+    }
+    Ok(format!("{h:016x}"))
+}
+
+/// 已读进内存的内容的指纹，与 [`hash_file`] 结果一致。
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv_update(FNV_OFFSET, bytes))
+}
+
+/// 两个文件字节是否完全相同；任一方读不了时视为不同。
+pub fn same_content(a: &Path, b: &Path) -> bool {
+    let (Ok(ma), Ok(mb)) = (a.metadata(), b.metadata()) else {
+        return false;
+    };
+    if ma.len() != mb.len() {
+        return false;
+    }
+    let (Ok(mut fa), Ok(mut fb)) = (File::open(a), File::open(b)) else {
+        return false;
+    };
+    let mut ba = [0u8; 64 * 1024];
+    let mut bb = [0u8; 64 * 1024];
+    loop {
+        let Ok(n) = fa.read(&mut ba) else { return false };
+        if n == 0 {
+            return true;
+        }
+        if fb.read_exact(&mut bb[..n]).is_err() || ba[..n] != bb[..n] {
+            return false;
+        }
+    }
+}
+
+/// 库内文件的内容索引：大小 -> 文件，指纹按需计算并缓存。
+#[derive(Debug, Default)]
+pub struct ContentIndex {
+    by_size: HashMap<u64, Vec<PathBuf>>,
+    hashes: HashMap<PathBuf, String>,
+}
+
+impl ContentIndex {
+    pub fn insert(&mut self, path: PathBuf, size: u64) {
+        let bucket = self.by_size.entry(size).or_default();
+        if !bucket.contains(&path) {
+            bucket.push(path);
+        }
+    }
+
+    /// 登记一个指纹已知的文件（例如取自库索引），省掉之后的重算。
+    pub fn insert_hashed(&mut self, path: PathBuf, size: u64, hash: String) {
+        self.hashes.insert(path.clone(), hash);
+        self.insert(path, size);
+    }
+
+    fn hash_of(&mut self, path: &Path) -> Option<String> {
+        if let Some(h) = self.hashes.get(path) {
+            return Some(h.clone());
+        }
+        let h = hash_file(path).ok()?;
+        self.hashes.insert(path.to_path_buf(), h.clone());
+        Some(h)
+    }
+
+    /// 找一个与 `src`（指纹为 `src_hash`）内容完全相同的已登记文件，跳过 `src` 自身。
+    pub fn find(&mut self, src: &Path, src_hash: &str) -> Option<PathBuf> {
+        let size = src.metadata().ok()?.len();
+        let candidates = self.by_size.get(&size)?.clone();
+        candidates
+            .into_iter()
+            .find(|c| c != src && self.hash_of(c).as_deref() == Some(src_hash) && same_content(src, c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn hash_and_index_find_identical_files() {
+        let dir = std::env::temp_dir().join(format!("ui-designer-hash-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let (a, b, c, src) = (dir.join("a.blp"), dir.join("b.blp"), dir.join("c.blp"), dir.join("src.blp"));
+        fs::write(&a, b"abcd").unwrap();
+        fs::write(&b, b"abce").unwrap();
+        fs::write(&c, b"abcd").unwrap();
+        fs::write(&src, b"abcd").unwrap();
+
+        let h = hash_file(&src).unwrap();
+        assert_eq!(h, hash_bytes(b"abcd"));
+        assert_eq!(h.len(), 16);
+        assert_ne!(h, hash_bytes(b"abce"));
+        assert!(same_content(&a, &src));
+        assert!(!same_content(&b, &src));
+        assert!(!same_content(&dir.join("missing.blp"), &src));
+
+        let mut index = ContentIndex::default();
+        index.insert(src.clone(), 4);
+        index.insert(b.clone(), 4);
+        assert_eq!(index.find(&src, &h), None, "不能把源文件自己当成重复");
+        // 记录的指纹与实际内容不符（碰撞 / 过期）时，逐字节比较兜底
+        index.insert_hashed(c.clone(), 4, hash_bytes(b"abce"));
+        index.insert_hashed(a.clone(), 4, h.clone());
+        index.insert_hashed(b.clone(), 4, h.clone());
+        assert_eq!(index.find(&src, &h), Some(a));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
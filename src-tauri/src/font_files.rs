@@ -0,0 +1,159 @@
+//! 字体文件（.ttf / .otf）：内置字体表与 `name` 表的最小解析。
+//!
+//! - widget 的 `font` 要么是游戏 / 模板仓内置字体（`Fonts\FZXH1JW.TTF` 这类 MPQ 内路径，
+//!   不是磁盘文件，不参与拷贝与路径换算），要么是全局库里字体文件的绝对路径，空串为默认字体。
+//! - `BUILTIN_FONTS` 只是常用的几款；打开项目时在库里找不到的相对路径（如 `Fonts\DFHeiMd.ttf`）
+//!   同样按游戏内字体保留原样，不拼成库路径，也不报未知字体。
+//! - 只读 sfnt 的 `name` 表取 family / style（优先 nameID 16 / 17 的排版名，其次 1 / 2），
+//!   字符串优先取 Windows 平台英文记录。TTC 合集不解析。
+
+use serde::Serialize;
+use std::path::Path;
+
+pub const FONT_EXTS: &[&str] = &["ttf", "otf"];
+
+/// 游戏客户端与模板仓自带的字体（与前端 `TextFonts` 预设一致）。
+pub const BUILTIN_FONTS: &[(&str, &str)] = &[
+    ("Fonts\\FRIZQT__.TTF", "Friz Quadrata（英文客户端默认）"),
+    ("Fonts\\FZXH1JW.TTF", "方正细黑（中文客户端默认）"),
+    ("Fonts\\MORPHEUS.TTF", "Morpheus（标题）"),
+    ("UI\\hpbar\\ZiTi.ttf", "模板仓自带字体"),
+];
+
+pub fn is_font_ext(ext: &str) -> bool {
+    FONT_EXTS.contains(&ext.to_ascii_lowercase().as_str())
+}
+
+/// 是否为内置字体；大小写与正反斜杠不敏感。
+pub fn is_builtin_font(value: &str) -> bool {
+    let norm = value.trim().replace('/', "\\");
+    BUILTIN_FONTS.iter().any(|(p, _)| p.eq_ignore_ascii_case(&norm))
+}
+
+/// 全局库里的一个字体文件。
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryFont {
+    /// 绝对路径，即 widget.font 应写入的值。
+    pub value: String,
+    /// 相对库根的路径（反斜杠）。
+    pub rel_path: String,
+    pub family: Option<String>,
+    pub style: Option<String>,
+}
+
+fn be_u16(b: &[u8], at: usize) -> Option<usize> {
+    b.get(at..at + 2).map(|s| u16::from_be_bytes([s[0], s[1]]) as usize)
+}
+
+fn be_u32(b: &[u8], at: usize) -> Option<usize> {
+    b.get(at..at + 4).map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]) as usize)
+}
+
+/// 从 sfnt 字节里取 (family, style)。不是 TrueType / OpenType 或没有 `name` 表时返回 `None`。
+pub fn font_names(bytes: &[u8]) -> Option<(Option<String>, Option<String>)> {
+    let version = bytes.get(0..4)?;
+    if version != [0, 1, 0, 0] && version != b"OTTO" && version != b"true" {
+        return None;
+    }
+    let num_tables = be_u16(bytes, 4)?;
+    let name_off = (0..num_tables).find_map(|i| {
+        let rec = 12 + i * 16;
+        if bytes.get(rec..rec + 4)? == b"name" {
+            be_u32(bytes, rec + 8)
+        } else {
+            None
+        }
+    })?;
+    let count = be_u16(bytes, name_off + 2)?;
+    let storage = name_off + be_u16(bytes, name_off + 4)?;
+
+    // (nameID, 优先级, 文本)；优先级越小越好。
+    let mut found: Vec<(usize, u8, String)> = Vec::new();
+    for i in 0..count {
+        let rec = name_off + 6 + i * 12;
+        let (Some(platform), Some(lang), Some(name_id), Some(len), Some(off)) = (
+            be_u16(bytes, rec),
+            be_u16(bytes, rec + 4),
+            be_u16(bytes, rec + 6),
+            be_u16(bytes, rec + 8),
+            be_u16(bytes, rec + 10),
+        ) else {
+            break;
+        };
+        if !matches!(name_id, 1 | 2 | 16 | 17) {
+            continue;
+        }
+        let Some(raw) = bytes.get(storage + off..storage + off + len) else {
+            continue;
+        };
+        let (rank, text) = match platform {
+            0 | 3 => {
+                let units: Vec<u16> = raw.chunks_exact(2).map(|c| u16::from_be_bytes([c[0], c[1]])).collect();
+                let rank = if platform == 3 && lang == 0x409 { 0 } else if platform == 3 { 1 } else { 2 };
+                (rank, String::from_utf16_lossy(&units))
+            }
+            1 => (3, raw.iter().map(|b| *b as char).collect()),
+            _ => continue,
+        };
+        let text = text.trim().to_string();
+        if !text.is_empty() {
+            found.push((name_id, rank, text));
+        }
+    }
+    let pick = |ids: [usize; 2]| {
+        ids.iter().find_map(|id| {
+            found
+                .iter()
+                .filter(|(n, _, _)| n == id)
+                .min_by_key(|(_, rank, _)| *rank)
+                .map(|(_, _, t)| t.clone())
+        })
+    };
+    Some((pick([16, 1]), pick([17, 2])))
+}
+
+/// 读取字体文件的 (family, style)；读不了或解析失败时两项都为 `None`。
+pub fn read_font_names(path: &Path) -> (Option<String>, Option<String>) {
+    std::fs::read(path)
+        .ok()
+        .and_then(|b| font_names(&b))
+        .unwrap_or((None, None))
+}
+
+/// 递归列出全局库里的字体文件（跳过点开头的隐藏目录 / 文件），按相对路径排序。
+pub fn scan_library_fonts(root: &Path) -> Vec<LibraryFont> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<LibraryFont>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            if path.is_dir() {
+                walk(root, &path, out);
+                continue;
+            }
+            let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+            if !is_font_ext(ext) {
+                continue;
+            }
+            let (family, style) = read_font_names(&path);
+            out.push(LibraryFont {
+                value: path.to_string_lossy().to_string(),
+                rel_path: path
+                    .strip_prefix(root)
+                    .map(|p| p.to_string_lossy().replace('/', "\\"))
+                    .unwrap_or_default(),
+                family,
+                style,
+            });
+        }
+    }
+    let mut out = Vec::new();
+    walk(root, root, &mut out);
+    out.sort_by(|a, b| a.rel_path.cmp(&b.rel_path));
+    out
+}
@@ -9,10 +9,26 @@
 //! - 导入时支持把 jpg/png/jpeg 转成 tga（由 `convertToBlp` 开关触发，历史字段名保留），
 //!   其余格式（blp/tga/bmp）保持原样拷贝。
 //! - BLP 编解码仅用于预览解码（blp -> png data url）。
-
+//! - 模型（mdx/mdl）也可以进库：原样拷贝，并把模型目录下能找到的贴图一起带进来。
+//! - 字体（ttf/otf）原样拷贝，列表条目附带从 `name` 表读出的 family / style。
+//! - 导入时按内容去重：源文件与库里已有文件字节相同时，按 `onDuplicate` 复用 / 跳过 / 照常导入，
+//!   决定写进 `ImportResult.warnings`。整个目录导入的文件与本批模型引用的贴图不去重，保持原有层级。
+//! - 可以配多个库根（见 `library_roots`）：`global_resource_list_roots` 按顺序合并列出，
+//!   靠前的根遮蔽靠后根里相对路径相同的文件；导入 / 删除仍只针对传入的可写根。
+//!   写入类命令同时收到完整的库根列表，目标不是其中的可写根（例如是共享根）时直接拒绝。
+//! - 列表走库根下的持久化索引（见 `library_index`）：条目带内容指纹与图片元数据
+//!   （尺寸 / 通道 / alpha / BLP 版本与压缩 / mip 数），只重读大小或修改时间变了的文件。
+//! - 条目可以带用户写的标签与描述（见 `library_meta`）；`global_resource_search` 按名称 / 路径 /
+//!   标签 / 扩展名 / 尺寸范围过滤并分页，MCP 的 `ui_search_library` 也走它。搜的是最近一次
+//!   合并列出的结果，显式刷新（重新列出）或根目录 / 标签文件变了才重新扫描。
+//! - 库内改名 / 移动（`global_resource_rename` / `global_resource_move`）会顺带改写调用方给出的
+//!   `.uiproj` 里指向旧路径的资源引用（只替换这些字符串值，文件其余内容原样保留），标签与索引记录也跟着挪。
+
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use std::time::UNIX_EPOCH;
 
 use base64::Engine as _;
@@ -23,6 +39,15 @@ use image_blp::parser::load_blp;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter};
 
+use crate::content_hash::{self, ContentIndex};
+use crate::font_files;
+use crate::image_meta::ImageMeta;
+use crate::library_index::{self, LibraryIndex};
+use crate::library_meta::{self, LibraryMeta, ResourceMeta};
+use crate::library_roots::{LibraryRoot, LibraryRoots};
+use crate::model_files;
+use crate::project_engine;
+
 // ----------------------------- Data types -----------------------------
 
 /// 对应前端 ImageResource 的子集；前端拿到后自行拼成完整条目。
@@ -41,6 +66,33 @@ pub struct GlobalResourceEntry {
     pub mtime_ms: i64,
     /// 小写扩展名，不含点。
     pub ext: String,
+    /// `image` / `model` / `font`。
+    pub kind: String,
+    /// 字体的 family（仅 `kind=font`，读不出时缺省）。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_family: Option<String>,
+    /// 字体的 style，如 `Regular` / `Bold`（仅 `kind=font`）。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub font_style: Option<String>,
+    /// 条目所在的库根。
+    pub root: String,
+    /// 来自只读的共享根：不能删除。
+    pub read_only: bool,
+    /// 被本条目遮蔽的、靠后库根里同一相对路径的文件（绝对路径）。
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub shadowed: Vec<String>,
+    /// 内容指纹（来自库索引；导入结果里的条目没有）。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// 图片元数据（仅 `kind=image` 且文件头可识别时）。
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageMeta>,
+    /// 用户标签。
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// 用户描述。
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
 }
 
 #[derive(Debug, Serialize)]
@@ -57,6 +109,8 @@ pub struct SetRootResult {
 #[serde(rename_all = "camelCase")]
 pub struct ImportRequest {
     pub root: String,
+    /// 当前配置的全部库根（含只读标记），`root` 必须是其中的可写根。
+    pub roots: Vec<LibraryRoot>,
     pub sources: Vec<String>,
     #[serde(default)]
     pub sub_dir: String,
@@ -64,6 +118,21 @@ pub struct ImportRequest {
     pub convert_to_blp: bool,
     #[serde(default)]
     pub overwrite: bool,
+    /// 源文件与库里已有文件内容相同时怎么办，默认复用。
+    #[serde(default)]
+    pub on_duplicate: DuplicatePolicy,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicatePolicy {
+    /// 不拷贝，返回库里已有的那条（出现在 `entries` 里）。
+    #[default]
+    Reuse,
+    /// 不拷贝也不返回条目。
+    Skip,
+    /// 照常导入（按 `overwrite` 覆盖或加后缀）。
+    Import,
 }
 
 #[derive(Debug, Serialize)]
@@ -105,6 +174,29 @@ fn emit_progress(app: &AppHandle, p: ImportProgress) {
     let _ = app.emit(IMPORT_PROGRESS_EVENT, p);
 }
 
+/// 改名 / 移动的结果。
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RelocateReport {
+    /// 旧 relPath（反斜杠）。
+    pub from: String,
+    /// 新 relPath。
+    pub to: String,
+    /// 随之移动的文件数（文件夹时为其下的库内文件数）。
+    pub moved_files: usize,
+    /// 被改写的项目（没有引用的项目不列出）。
+    pub projects: Vec<ProjectRewrite>,
+    pub warnings: Vec<ImportWarning>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRewrite {
+    pub path: String,
+    /// 改写的引用个数。
+    pub rewritten: usize,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MigrateResult {
@@ -121,10 +213,27 @@ fn is_image_ext(ext: &str) -> bool {
     ALLOWED_EXTS.contains(&ext)
 }
 
+/// 能进全局库的文件：图片、模型或字体。
+fn is_library_ext(ext: &str) -> bool {
+    is_image_ext(ext) || model_files::is_model_ext(ext) || font_files::is_font_ext(ext)
+}
+
 fn normalize_root(root: &str) -> PathBuf {
     PathBuf::from(root.trim())
 }
 
+/// 写入类命令的前置检查。`roots` 是调用方当前配置的全部库根（含只读标记）：
+/// 目标 `root` 必须是其中的可写根；共享根（及其下的目录）只读，不能导入 / 删除 / 改名 / 改标签。
+fn ensure_writable_root(roots: &LibraryRoots, root: &str) -> Result<(), String> {
+    if roots.is_read_only_path(root) {
+        return Err(format!("{} 是只读的共享库，不能写入", root.trim()));
+    }
+    if !roots.is_writable_root(root) {
+        return Err(format!("{} 不是当前配置的可写库根", root.trim()));
+    }
+    Ok(())
+}
+
 fn mtime_millis(meta: &fs::Metadata) -> i64 {
     meta.modified()
         .ok()
@@ -183,9 +292,21 @@ fn to_entry(root: &Path, abs: &Path) -> Option<GlobalResourceEntry> {
     }
     let name = abs.file_name()?.to_str()?.to_string();
     let ext = ext_of(abs);
-    if !is_image_ext(&ext) {
+    if !is_library_ext(&ext) {
         return None;
     }
+    let kind = if model_files::is_model_ext(&ext) {
+        "model"
+    } else if font_files::is_font_ext(&ext) {
+        "font"
+    } else {
+        "image"
+    };
+    let (font_family, font_style) = if kind == "font" {
+        font_files::read_font_names(abs)
+    } else {
+        (None, None)
+    };
     Some(GlobalResourceEntry {
         name,
         rel_path: rel_from_root(root, abs),
@@ -193,6 +314,16 @@ fn to_entry(root: &Path, abs: &Path) -> Option<GlobalResourceEntry> {
         size: meta.len(),
         mtime_ms: mtime_millis(&meta),
         ext,
+        kind: kind.to_string(),
+        font_family,
+        font_style,
+        root: root.to_string_lossy().to_string(),
+        read_only: false,
+        shadowed: Vec::new(),
+        hash: None,
+        image: None,
+        tags: Vec::new(),
+        description: String::new(),
     })
 }
 
@@ -456,18 +587,315 @@ pub fn global_resource_set_root(root: String) -> SetRootResult {
     }
 }
 
-#[tauri::command]
+/// 列出单个可写根。首次列出要把每个文件读一遍建索引，放到后台线程跑，不阻塞 IPC。
+#[tauri::command(async)]
 pub fn global_resource_list(root: String) -> Vec<GlobalResourceEntry> {
-    let path = normalize_root(&root);
+    list_root(&normalize_root(&root), false)
+}
+
+fn list_root(path: &Path, read_only: bool) -> Vec<GlobalResourceEntry> {
     if path.as_os_str().is_empty() || !path.is_dir() {
         return Vec::new();
     }
     let mut out: Vec<GlobalResourceEntry> = Vec::new();
-    let _ = walk_collect(&path, &path, &mut out);
+    let _ = walk_collect(path, path, &mut out);
+    fill_from_index(path, &mut out, read_only);
+    if let Ok(meta) = LibraryMeta::load(path) {
+        for e in out.iter_mut() {
+            if let Some(m) = meta.get(&e.rel_path) {
+                e.tags = m.tags.clone();
+                e.description = m.description.clone();
+            }
+        }
+    }
     out.sort_by(|a, b| a.rel_path.to_lowercase().cmp(&b.rel_path.to_lowercase()));
     out
 }
 
+/// 只读（共享）根的索引写不回根目录，按根留在内存里复用，免得每次列出都把整个共享库重读一遍。
+fn shared_indexes() -> &'static Mutex<HashMap<String, LibraryIndex>> {
+    static INDEXES: OnceLock<Mutex<HashMap<String, LibraryIndex>>> = OnceLock::new();
+    INDEXES.get_or_init(Default::default)
+}
+
+/// 用库索引补上指纹与图片元数据，顺带把索引刷新到与磁盘一致。
+/// 可写根把索引存回根目录，存不进去时记一条警告；只读根从不写入，索引放进 `shared_indexes`。
+fn fill_from_index(root: &Path, entries: &mut [GlobalResourceEntry], read_only: bool) {
+    let key = root_key(root);
+    let cached = if read_only {
+        shared_indexes().lock().ok().and_then(|mut m| m.remove(&key))
+    } else {
+        None
+    };
+    let mut index = cached.unwrap_or_else(|| LibraryIndex::load(root));
+    for e in entries.iter_mut() {
+        let record = index.refresh(&e.rel_path, Path::new(&e.abs_path), e.size, e.mtime_ms);
+        e.hash = record.hash;
+        e.image = record.image;
+    }
+    index.retain(&entries.iter().map(|e| e.rel_path.clone()).collect());
+    if read_only {
+        if let Ok(mut m) = shared_indexes().lock() {
+            m.insert(key, index);
+        }
+    } else if let Err(e) = index.save() {
+        log::warn!(
+            "[ui-designer] 库索引写入失败，下次列出会重读文件: {} ; err={}",
+            root.join(library_index::INDEX_FILE).display(),
+            e
+        );
+    }
+}
+
+/// 按顺序合并多个库根的条目。相对路径相同（大小写不敏感）时靠前的根胜出，
+/// 靠后根里的那份记进胜出条目的 `shadowed`。不存在的根跳过。同样在后台线程跑。
+/// 每次调用都重新扫描，即显式刷新；结果留给后续搜索复用。
+#[tauri::command(async)]
+pub fn global_resource_list_roots(roots: Vec<LibraryRoot>) -> Vec<GlobalResourceEntry> {
+    list_roots(&LibraryRoots::new(roots))
+}
+
+/// 最近一次合并列出的结果，搜索直接在它上面过滤。
+struct CachedListing {
+    stamps: Vec<Option<(u64, i64)>>,
+    entries: Vec<GlobalResourceEntry>,
+}
+
+/// 按库根列表（路径 + 只读标记）缓存的合并列表。
+fn listings() -> &'static Mutex<HashMap<String, CachedListing>> {
+    static LISTINGS: OnceLock<Mutex<HashMap<String, CachedListing>>> = OnceLock::new();
+    LISTINGS.get_or_init(Default::default)
+}
+
+fn root_key(root: &Path) -> String {
+    root.to_string_lossy().to_lowercase()
+}
+
+fn listing_key(roots: &LibraryRoots) -> String {
+    roots
+        .roots()
+        .iter()
+        .map(|r| format!("{}|{}", root_key(&normalize_root(&r.path)), r.read_only))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 各根目录与其标签文件的（大小, 修改时间）。根下增删文件、标签被改过都会让它变化；
+/// 子目录里的外部改动不会，要靠显式刷新。
+fn listing_stamps(roots: &LibraryRoots) -> Vec<Option<(u64, i64)>> {
+    let stamp = |p: &Path| fs::metadata(p).ok().map(|m| (m.len(), mtime_millis(&m)));
+    roots
+        .roots()
+        .iter()
+        .flat_map(|r| {
+            let root = normalize_root(&r.path);
+            [stamp(&root), stamp(&root.join(library_meta::META_FILE))]
+        })
+        .collect()
+}
+
+/// 库里的文件被本模块改过（导入 / 删除 / 改名 / 改标签）后丢掉含这个根的缓存列表，下次搜索重新扫描。
+fn forget_listings(root: &Path) {
+    let prefix = format!("{}|", root_key(root));
+    if let Ok(mut m) = listings().lock() {
+        m.retain(|key, _| !key.lines().any(|l| l.starts_with(&prefix)));
+    }
+}
+
+/// 重新扫描并缓存。stamp 在扫描之后取：扫描时写回的索引文件也会改动根目录。
+fn list_roots(roots: &LibraryRoots) -> Vec<GlobalResourceEntry> {
+    let entries = scan_roots(roots);
+    let stamps = listing_stamps(roots);
+    if let Ok(mut m) = listings().lock() {
+        m.insert(listing_key(roots), CachedListing { stamps, entries: entries.clone() });
+    }
+    entries
+}
+
+/// 有缓存且各根的 stamp 没变时直接用缓存，否则重新扫描。
+fn cached_listing(roots: &LibraryRoots) -> Vec<GlobalResourceEntry> {
+    let stamps = listing_stamps(roots);
+    let cached = listings().lock().ok().and_then(|m| {
+        m.get(&listing_key(roots))
+            .filter(|c| c.stamps == stamps)
+            .map(|c| c.entries.clone())
+    });
+    cached.unwrap_or_else(|| list_roots(roots))
+}
+
+fn scan_roots(roots: &LibraryRoots) -> Vec<GlobalResourceEntry> {
+    let mut out: Vec<GlobalResourceEntry> = Vec::new();
+    // 小写 relPath -> out 下标
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for root in roots.roots() {
+        for mut e in list_root(&normalize_root(&root.path), root.read_only) {
+            match seen.get(&e.rel_path.to_lowercase()) {
+                Some(&i) => out[i].shadowed.push(e.abs_path),
+                None => {
+                    e.read_only = root.read_only;
+                    seen.insert(e.rel_path.to_lowercase(), out.len());
+                    out.push(e);
+                }
+            }
+        }
+    }
+    out.sort_by_key(|e| e.rel_path.to_lowercase());
+    out
+}
+
+/// `global_resource_search` 的条件；各项之间是"且"，全部缺省时返回整个库（分页）。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchQuery {
+    /// 空白分隔的关键词，每个都要命中文件名 / relPath / 标签 / 描述之一（不区分大小写）。
+    pub text: String,
+    /// 必须全部带上的标签（不区分大小写）。
+    pub tags: Vec<String>,
+    /// 扩展名（不含点），命中其一即可；空表示不限。
+    pub exts: Vec<String>,
+    /// `image` / `model` / `font`。
+    pub kind: Option<String>,
+    /// 尺寸范围（含端点）；给了任一项时，读不出尺寸的条目不会命中。
+    pub min_width: Option<u32>,
+    pub max_width: Option<u32>,
+    pub min_height: Option<u32>,
+    pub max_height: Option<u32>,
+    /// 是否带 alpha 通道；同样只对读得出元数据的图片生效。
+    pub has_alpha: Option<bool>,
+    /// 先重新扫描各根再搜；缺省时复用最近一次列出的结果。
+    pub refresh: bool,
+    pub offset: usize,
+    /// 每页条数，默认 50，最多 500。
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchPage {
+    /// 命中总数（分页前）。
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub items: Vec<GlobalResourceEntry>,
+}
+
+const SEARCH_DEFAULT_LIMIT: usize = 50;
+const SEARCH_MAX_LIMIT: usize = 500;
+
+fn in_range(v: u32, min: Option<u32>, max: Option<u32>) -> bool {
+    (min.unwrap_or(0)..=max.unwrap_or(u32::MAX)).contains(&v)
+}
+
+fn matches_query(e: &GlobalResourceEntry, q: &SearchQuery, terms: &[String]) -> bool {
+    if q.kind.as_deref().is_some_and(|k| !k.eq_ignore_ascii_case(&e.kind)) {
+        return false;
+    }
+    if !q.exts.is_empty()
+        && !q.exts.iter().any(|x| x.trim().trim_start_matches('.').eq_ignore_ascii_case(&e.ext))
+    {
+        return false;
+    }
+    let tags_lower: Vec<String> = e.tags.iter().map(|t| t.to_lowercase()).collect();
+    if !q.tags.iter().all(|t| tags_lower.contains(&t.trim().to_lowercase())) {
+        return false;
+    }
+    let wants_image = q.min_width.is_some()
+        || q.max_width.is_some()
+        || q.min_height.is_some()
+        || q.max_height.is_some()
+        || q.has_alpha.is_some();
+    if wants_image {
+        let Some(img) = &e.image else { return false };
+        if !in_range(img.width, q.min_width, q.max_width)
+            || !in_range(img.height, q.min_height, q.max_height)
+            || q.has_alpha.is_some_and(|a| a != img.has_alpha)
+        {
+            return false;
+        }
+    }
+    let haystack = format!(
+        "{}\n{}\n{}\n{}",
+        e.name.to_lowercase(),
+        e.rel_path.to_lowercase(),
+        tags_lower.join("\n"),
+        e.description.to_lowercase()
+    );
+    terms.iter().all(|t| haystack.contains(t.as_str()))
+}
+
+/// 在（多根合并后的）全局库里搜索，结果按 relPath 排序后分页。
+/// 搜的是缓存的合并列表（见 `cached_listing`），不是每次都重新扫描。
+pub fn search_library(roots: &LibraryRoots, q: &SearchQuery) -> SearchPage {
+    let terms: Vec<String> = q.text.split_whitespace().map(|t| t.to_lowercase()).collect();
+    let limit = q.limit.unwrap_or(SEARCH_DEFAULT_LIMIT).clamp(1, SEARCH_MAX_LIMIT);
+    let listing = if q.refresh { list_roots(roots) } else { cached_listing(roots) };
+    let hits: Vec<GlobalResourceEntry> = listing
+        .into_iter()
+        .filter(|e| matches_query(e, q, &terms))
+        .collect();
+    SearchPage {
+        total: hits.len(),
+        offset: q.offset,
+        limit,
+        items: hits.into_iter().skip(q.offset).take(limit).collect(),
+    }
+}
+
+/// 缓存失效时要重新列出各根，放到后台线程跑；搜索框按键触发时不阻塞 IPC。
+#[tauri::command(async)]
+pub fn global_resource_search(roots: Vec<LibraryRoot>, query: SearchQuery) -> SearchPage {
+    search_library(&LibraryRoots::new(roots), &query)
+}
+
+/// 元数据命令的公共前置：库根有效且可写、文件在库里。返回规范化后的 relPath。
+fn meta_target(roots: Vec<LibraryRoot>, root: &Path, rel_path: &str) -> Result<String, String> {
+    if root.as_os_str().is_empty() || !root.is_dir() {
+        return Err("全局资源库根路径无效".to_string());
+    }
+    ensure_writable_root(&LibraryRoots::new(roots), &root.to_string_lossy())?;
+    let rel = normalize_rel(rel_path);
+    if rel.is_empty() || !root.join(rel.replace('\\', std::path::MAIN_SEPARATOR_STR)).is_file() {
+        return Err(format!("全局资源库里没有 {rel_path}"));
+    }
+    Ok(rel)
+}
+
+/// 设置标签 / 描述：传入的字段整体替换（`tags` 为完整的新标签集，`description` 空串即清空），
+/// 未传的保留。返回设置后的元数据。
+#[tauri::command]
+pub fn global_resource_set_meta(
+    root: String,
+    roots: Vec<LibraryRoot>,
+    rel_path: String,
+    tags: Option<Vec<String>>,
+    description: Option<String>,
+) -> Result<ResourceMeta, String> {
+    let root = normalize_root(&root);
+    let rel = meta_target(roots, &root, &rel_path)?;
+    let mut meta = LibraryMeta::load(&root)?;
+    let out = meta.set(&rel, tags, description);
+    meta.save().map_err(|e| format!("写入 {} 失败: {e}", library_meta::META_FILE))?;
+    forget_listings(&root);
+    Ok(out)
+}
+
+/// 去掉指定标签；不传 `tags` 时清掉该文件的全部标签与描述。返回剩下的元数据。
+#[tauri::command]
+pub fn global_resource_remove_meta(
+    root: String,
+    roots: Vec<LibraryRoot>,
+    rel_path: String,
+    tags: Option<Vec<String>>,
+) -> Result<ResourceMeta, String> {
+    let root = normalize_root(&root);
+    let rel = meta_target(roots, &root, &rel_path)?;
+    let mut meta = LibraryMeta::load(&root)?;
+    let out = meta.remove(&rel, tags.as_deref());
+    meta.save().map_err(|e| format!("写入 {} 失败: {e}", library_meta::META_FILE))?;
+    forget_listings(&root);
+    Ok(out)
+}
+
 /// 哪些源扩展可以转成 TGA？
 /// 仅转 png/jpg/jpeg；blp/tga/bmp 保持原样。
 fn is_convertible_to_tga(ext: &str) -> bool {
@@ -479,7 +907,8 @@ pub async fn global_resource_import(app: AppHandle, req: ImportRequest) -> Impor
     // 全部重活扔到 blocking 线程：`image::open` + BLP 编码是 CPU 密集型，
     // 放在 async 事件循环里会阻塞 IPC，前端表现为 UI 卡死。
     let handle = app.clone();
-    tauri::async_runtime::spawn_blocking(move || do_import(handle, req))
+    let root = normalize_root(&req.root);
+    let result = tauri::async_runtime::spawn_blocking(move || do_import(handle, req))
         .await
         .unwrap_or_else(|e| ImportResult {
             entries: Vec::new(),
@@ -487,7 +916,9 @@ pub async fn global_resource_import(app: AppHandle, req: ImportRequest) -> Impor
                 source: String::new(),
                 message: format!("导入线程异常退出: {e}"),
             }],
-        })
+        });
+    forget_listings(&root);
+    result
 }
 
 fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
@@ -504,6 +935,10 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
         });
         return result;
     }
+    if let Err(message) = ensure_writable_root(&LibraryRoots::new(req.roots.clone()), &req.root) {
+        result.warnings.push(ImportWarning { source: req.root.clone(), message });
+        return result;
+    }
 
     let sub_dir = normalize_rel(&req.sub_dir);
     let dst_dir = if sub_dir.is_empty() {
@@ -521,7 +956,7 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
         }
     }
 
-    // 把入参里混进来的"目录"展开成其中所有图片文件（保留相对子目录结构）。
+    // 把入参里混进来的"目录"展开成其中所有可导入文件（保留相对子目录结构）。
     // 这样用户从全局库 Tab 直接拖一个 `ui` 文件夹进来也能用。
     // 元素是 (src_abs, extra_sub_dir_relative_to_dst_dir)。
     let mut expanded: Vec<(PathBuf, String)> = Vec::new();
@@ -533,7 +968,7 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
             if found.is_empty() {
                 result.warnings.push(ImportWarning {
                     source: src_raw.clone(),
-                    message: "目录里没有可导入的图片文件（支持 blp/png/tga/bmp/jpg/jpeg）"
+                    message: "目录里没有可导入的文件（支持 blp/png/tga/bmp/jpg/jpeg/mdx/mdl/ttf/otf）"
                         .to_string(),
                 });
                 continue;
@@ -573,6 +1008,8 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
         }
     }
 
+    let mut dedup = ImportDedup::new(&root, &expanded);
+
     let total = expanded.len();
     emit_progress(
         &app,
@@ -599,7 +1036,7 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
         );
 
         let src_ext = ext_of(src);
-        if !is_image_ext(&src_ext) {
+        if !is_library_ext(&src_ext) {
             let msg = format!("不支持的扩展名: .{src_ext}");
             result.warnings.push(ImportWarning {
                 source: src_raw_display.clone(),
@@ -618,6 +1055,38 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
             continue;
         }
 
+        let src_hash = content_hash::hash_file(src).ok();
+        let duplicate = src_hash.as_deref().and_then(|h| dedup.find(src, extra_sub, h));
+        if let Some(dup) = &duplicate {
+            let decision = match req.on_duplicate {
+                DuplicatePolicy::Reuse => Some("已复用，未拷贝"),
+                DuplicatePolicy::Skip => Some("已跳过"),
+                DuplicatePolicy::Import => None,
+            };
+            if let Some(decision) = decision {
+                if req.on_duplicate == DuplicatePolicy::Reuse {
+                    if let Some(entry) = to_entry(&root, &dup.path) {
+                        result.entries.push(entry);
+                    }
+                }
+                result.warnings.push(ImportWarning {
+                    source: src_raw_display.clone(),
+                    message: format!("{}，{decision}", dup.describe(&root)),
+                });
+                emit_progress(
+                    &app,
+                    ImportProgress {
+                        phase: "item-done".into(),
+                        index: idx,
+                        total,
+                        source: src_raw_display.clone(),
+                        message: None,
+                    },
+                );
+                continue;
+            }
+        }
+
         let stem = src
             .file_stem()
             .and_then(|s| s.to_str())
@@ -716,8 +1185,19 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
         match outcome {
             Some(abs) => {
                 if let Some(entry) = to_entry(&root, &abs) {
+                    dedup.record(src_hash, &abs, entry.size);
                     result.entries.push(entry);
                 }
+                if let Some(dup) = &duplicate {
+                    result.warnings.push(ImportWarning {
+                        source: src_raw_display.clone(),
+                        message: format!("{}，仍按要求导入", dup.describe(&root)),
+                    });
+                }
+                // 整个目录导入时贴图本来就随目录进库，只对单独拖入的模型补拷贴图。
+                if model_files::is_model_ext(&src_ext) && extra_sub.is_empty() {
+                    import_model_textures(&root, src, &abs, req.overwrite, &mut result);
+                }
                 emit_progress(
                     &app,
                     ImportProgress {
@@ -758,6 +1238,125 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
     result
 }
 
+/// 导入时的去重状态。只对单独拖入的文件去重：整个目录导入的文件、以及本批模型引用的贴图
+/// 要按原有相对层级落盘（模型靠相对路径找贴图），换成库里别处的同内容文件会让引用失效。
+struct ImportDedup {
+    /// 库里已有文件的内容索引；本批导入的结果也会登记进来，批内的重复同样能识别。
+    index: ContentIndex,
+    /// 源指纹 -> 本批产物；转成 tga 的文件与源不再字节相同，靠它识别同一张图的重复导入。
+    imported_from: HashMap<String, PathBuf>,
+    /// 本批单独拖入的模型引用到的贴图（规范化后的路径）。
+    model_textures: HashSet<PathBuf>,
+}
+
+/// 找到的重复。`converted` 表示它是本批由相同源内容转换出的产物，与源并非字节相同。
+struct Duplicate {
+    path: PathBuf,
+    converted: bool,
+}
+
+impl Duplicate {
+    fn describe(&self, root: &Path) -> String {
+        let rel = rel_from_root(root, &self.path);
+        if self.converted {
+            format!("与本批已由相同内容转换导入的 {rel} 重复")
+        } else {
+            format!("内容与库中已有的 {rel} 相同")
+        }
+    }
+}
+
+impl ImportDedup {
+    fn new(root: &Path, batch: &[(PathBuf, String)]) -> Self {
+        let mut index = ContentIndex::default();
+        let mut existing: Vec<GlobalResourceEntry> = Vec::new();
+        let _ = walk_collect(root, root, &mut existing);
+        let lib_index = LibraryIndex::load(root);
+        for e in existing {
+            let known = lib_index
+                .fresh(&e.rel_path, e.size, e.mtime_ms)
+                .and_then(|r| r.hash.clone());
+            match known {
+                Some(h) => index.insert_hashed(PathBuf::from(e.abs_path), e.size, h),
+                None => index.insert(PathBuf::from(e.abs_path), e.size),
+            }
+        }
+        let model_textures = batch
+            .iter()
+            .filter(|(src, sub)| sub.is_empty() && model_files::is_model_ext(&ext_of(src)))
+            .flat_map(|(src, _)| model_files::resolve_textures(src))
+            .filter_map(|t| t.source?.canonicalize().ok())
+            .collect();
+        Self { index, imported_from: HashMap::new(), model_textures }
+    }
+
+    fn find(&mut self, src: &Path, extra_sub: &str, src_hash: &str) -> Option<Duplicate> {
+        if !extra_sub.is_empty() || src.canonicalize().is_ok_and(|p| self.model_textures.contains(&p)) {
+            return None;
+        }
+        if let Some(path) = self.index.find(src, src_hash) {
+            return Some(Duplicate { path, converted: false });
+        }
+        self.imported_from
+            .get(src_hash)
+            .map(|path| Duplicate { path: path.clone(), converted: true })
+    }
+
+    /// 登记本批的一个产物。
+    fn record(&mut self, src_hash: Option<String>, product: &Path, size: u64) {
+        self.index.insert(product.to_path_buf(), size);
+        if let Some(h) = src_hash {
+            self.imported_from.entry(h).or_insert_with(|| product.to_path_buf());
+        }
+    }
+}
+
+/// 把模型引用、且在源模型目录下找得到的贴图拷到库里的模型旁边，保持相对层级，
+/// 这样模型里写的贴图路径在库里仍然有效。库里已有同名贴图时（`overwrite=false`）保留原文件。
+fn import_model_textures(root: &Path, src_model: &Path, dst_model: &Path, overwrite: bool, result: &mut ImportResult) {
+    let dst_dir = dst_model.parent().unwrap_or(root);
+    for tex in model_files::resolve_textures(src_model) {
+        if tex.rejected {
+            result.warnings.push(ImportWarning {
+                source: src_model.to_string_lossy().to_string(),
+                message: format!("模型引用的贴图路径越出模型目录，已拒绝: {}", tex.reference),
+            });
+            continue;
+        }
+        let Some(tex_src) = tex.source else {
+            result.warnings.push(ImportWarning {
+                source: src_model.to_string_lossy().to_string(),
+                message: format!("模型引用的贴图未找到（游戏内置贴图可忽略）: {}", tex.reference),
+            });
+            continue;
+        };
+        let Some(tex_dst) = model_files::join_within(dst_dir, &tex.rel) else {
+            result.warnings.push(ImportWarning {
+                source: src_model.to_string_lossy().to_string(),
+                message: format!("模型引用的贴图路径越出模型目录，已拒绝: {}", tex.reference),
+            });
+            continue;
+        };
+        if tex_dst.exists() && !overwrite {
+            continue;
+        }
+        if let Some(parent) = tex_dst.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::copy(&tex_src, &tex_dst) {
+            Ok(_) => {
+                if let Some(entry) = to_entry(root, &tex_dst) {
+                    result.entries.push(entry);
+                }
+            }
+            Err(e) => result.warnings.push(ImportWarning {
+                source: tex_src.to_string_lossy().to_string(),
+                message: format!("拷贝模型贴图失败: {e}"),
+            }),
+        }
+    }
+}
+
 /// 从全局库彻底删除一条路径（文件或目录）。
 ///
 /// 策略：**硬删除**——直接走 `fs::remove_file` / `fs::remove_dir_all`，不再挪去 `.trash`。
@@ -767,7 +1366,8 @@ fn do_import(app: AppHandle, req: ImportRequest) -> ImportResult {
 /// 兼容清理：如果根目录下还残留着老版本写入的 `.trash` 目录，这个命令也会顺手把它
 /// 整个干掉（只在它看起来确实是老遗留时才动——顶层只能含 "数字时间戳命名" 的子目录）。
 #[tauri::command]
-pub fn global_resource_delete(root: String, rel_path: String) -> Result<(), String> {
+pub fn global_resource_delete(root: String, roots: Vec<LibraryRoot>, rel_path: String) -> Result<(), String> {
+    ensure_writable_root(&LibraryRoots::new(roots), &root)?;
     let root = normalize_root(&root);
     if root.as_os_str().is_empty() || !root.is_dir() {
         return Err("全局资源库根路径无效".to_string());
@@ -792,13 +1392,228 @@ pub fn global_resource_delete(root: String, rel_path: String) -> Result<(), Stri
         fs::remove_file(&abs).map_err(|e| format!("删除文件失败: {e}"))
     };
     let result = removed.map(|_| ());
+    if result.is_ok() {
+        if let Ok(mut meta) = LibraryMeta::load(&root) {
+            if meta.remove_under(&rel) {
+                let _ = meta.save();
+            }
+        }
+    }
 
     // 即便本次删除没事，也顺手把残留的老 .trash 清掉，保证迁移到新策略后全局库干净。
     let _ = purge_legacy_trash(&root);
+    forget_listings(&root);
 
     result
 }
 
+/// 在库内给文件或文件夹改名（同一目录下，`new_name` 不含路径分隔符）。
+/// `projects` 是要一并改写引用的 `.uiproj` 文件或目录（目录递归查找）。
+#[tauri::command]
+pub fn global_resource_rename(
+    root: String,
+    roots: Vec<LibraryRoot>,
+    rel_path: String,
+    new_name: String,
+    projects: Vec<String>,
+) -> Result<RelocateReport, String> {
+    let name = new_name.trim();
+    if name.is_empty() || name.contains(['\\', '/']) || name == "." || name == ".." {
+        return Err(format!("新名称不合法: {new_name}"));
+    }
+    let rel = normalize_rel(&rel_path);
+    let to = match rel.rfind('\\') {
+        Some(i) => format!("{}\\{name}", &rel[..i]),
+        None => name.to_string(),
+    };
+    let report = relocate(&root, &LibraryRoots::new(roots), &rel, &to, &projects);
+    forget_listings(&normalize_root(&root));
+    report
+}
+
+/// 把库内的文件或文件夹移到另一个子目录下（`target_dir` 为相对库根的目录，空串为根），名称不变。
+/// `projects` 同 [`global_resource_rename`]。
+#[tauri::command]
+pub fn global_resource_move(
+    root: String,
+    roots: Vec<LibraryRoot>,
+    rel_path: String,
+    target_dir: String,
+    projects: Vec<String>,
+) -> Result<RelocateReport, String> {
+    let rel = normalize_rel(&rel_path);
+    let name = rel.rsplit('\\').next().unwrap_or("").to_string();
+    let dir = normalize_rel(&target_dir);
+    let to = if dir.is_empty() { name } else { format!("{dir}\\{name}") };
+    let report = relocate(&root, &LibraryRoots::new(roots), &rel, &to, &projects);
+    forget_listings(&normalize_root(&root));
+    report
+}
+
+/// 展开调用方给的项目路径：文件原样收下，目录递归找 `.uiproj`（跳过点目录与 node_modules）。
+fn collect_project_files(paths: &[String], warnings: &mut Vec<ImportWarning>) -> Vec<PathBuf> {
+    fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+        let Ok(entries) = fs::read_dir(dir) else { return };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || name == "node_modules" {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(t) if t.is_dir() => walk(&path, out),
+                Ok(t) if t.is_file() && ext_of(&path) == "uiproj" => out.push(path),
+                _ => {}
+            }
+        }
+    }
+    let mut out: Vec<PathBuf> = Vec::new();
+    for raw in paths {
+        let p = PathBuf::from(raw.trim());
+        if p.is_dir() {
+            walk(&p, &mut out);
+        } else if p.is_file() {
+            out.push(p);
+        } else {
+            warnings.push(ImportWarning {
+                source: raw.clone(),
+                message: "项目路径不存在".to_string(),
+            });
+        }
+    }
+    out.sort();
+    out.dedup();
+    out
+}
+
+fn relocate(root_raw: &str, roots: &LibraryRoots, from: &str, to: &str, projects: &[String]) -> Result<RelocateReport, String> {
+    ensure_writable_root(roots, root_raw)?;
+    let root = normalize_root(root_raw);
+    if root.as_os_str().is_empty() || !root.is_dir() {
+        return Err("全局资源库根路径无效".to_string());
+    }
+    if from.is_empty() || to.is_empty() {
+        return Err("relPath 为空".to_string());
+    }
+    if from == to {
+        return Err("新旧路径相同".to_string());
+    }
+    for rel in [from, to] {
+        if rel.split('\\').any(|seg| seg.is_empty() || seg == "." || seg == ".." || seg.contains(':')) {
+            return Err(format!("路径不合法: {rel}"));
+        }
+        let name = rel.rsplit('\\').next().unwrap_or(rel);
+        if is_library_data_file(name) {
+            return Err(format!("{name} 是全局库自己的数据文件，不能改名或移动"));
+        }
+    }
+    // 按真实路径再确认一遍仍在库根之下（防符号链接 / 目录联接把路径带出去）。
+    let (Some(src), Some(dst)) = (model_files::join_within(&root, from), model_files::join_within(&root, to)) else {
+        return Err("目标路径越界".to_string());
+    };
+    if !src.exists() {
+        return Err(format!("全局资源库里没有 {from}"));
+    }
+    // 只改大小写时目标"已存在"的就是它自己
+    if !from.eq_ignore_ascii_case(to) && dst.exists() {
+        return Err(format!("目标已存在: {to}"));
+    }
+    if to.to_lowercase().starts_with(&format!("{}\\", from.to_lowercase())) {
+        return Err("不能把文件夹移动到它自己里面".to_string());
+    }
+
+    let mut report = RelocateReport {
+        from: from.to_string(),
+        to: to.to_string(),
+        moved_files: 1,
+        projects: Vec::new(),
+        warnings: Vec::new(),
+    };
+    if src.is_dir() {
+        let mut files: Vec<GlobalResourceEntry> = Vec::new();
+        let _ = walk_collect(&src, &src, &mut files);
+        report.moved_files = files.len();
+    } else if model_files::is_model_ext(&ext_of(&src)) && src.parent() != dst.parent() {
+        // 模型按相对自身目录的路径引用贴图；单独挪走模型，贴图留在原处。
+        for tex in model_files::resolve_textures(&src) {
+            if tex.source.is_some() {
+                report.warnings.push(ImportWarning {
+                    source: tex.reference,
+                    message: "贴图没有随模型移动，模型里的这条引用可能失效".to_string(),
+                });
+            }
+        }
+    }
+
+    // 先把项目都读好并算出改写结果；读不了的只告警、不影响移动。
+    let mut rewrites: Vec<(PathBuf, String, usize)> = Vec::new();
+    for path in collect_project_files(projects, &mut report.warnings) {
+        let rewritten = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|s| project_engine::rewrite_library_refs_in_text(&s, roots, root_raw.trim(), from, to));
+        match rewritten {
+            Ok((text, n)) => {
+                if n > 0 {
+                    rewrites.push((path, text, n));
+                }
+            }
+            Err(e) => report.warnings.push(ImportWarning {
+                source: path.to_string_lossy().to_string(),
+                message: format!("读取项目文件失败，未改写: {e}"),
+            }),
+        }
+    }
+
+    if let Some(parent) = dst.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("创建目标目录失败: {e}"))?;
+    }
+    fs::rename(&src, &dst).map_err(|e| format!("移动失败: {e}"))?;
+
+    match LibraryMeta::load(&root) {
+        Ok(mut meta) => {
+            if meta.rename_under(from, to) {
+                if let Err(e) = meta.save() {
+                    report.warnings.push(ImportWarning {
+                        source: library_meta::META_FILE.to_string(),
+                        message: format!("标签与描述未能跟着移动: {e}"),
+                    });
+                }
+            }
+        }
+        Err(e) => report.warnings.push(ImportWarning {
+            source: library_meta::META_FILE.to_string(),
+            message: format!("标签与描述未能跟着移动: {e}"),
+        }),
+    }
+    let mut index = LibraryIndex::load(&root);
+    index.rename_under(from, to);
+    if let Err(e) = index.save() {
+        report.warnings.push(ImportWarning {
+            source: library_index::INDEX_FILE.to_string(),
+            message: format!("库索引未能跟着移动，下次列出会重读这些文件: {e}"),
+        });
+    }
+
+    for (path, text, n) in rewrites {
+        let written = fs::write(&path, text).map_err(|e| e.to_string());
+        let display = path.to_string_lossy().to_string();
+        match written {
+            Ok(()) => report.projects.push(ProjectRewrite { path: display, rewritten: n }),
+            Err(e) => report.warnings.push(ImportWarning {
+                source: display,
+                message: format!("写回项目文件失败: {e}"),
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// 库根下的索引 / 元数据文件（`.ui-designer-*.json`）。
+fn is_library_data_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.starts_with(".ui-designer-") && name.ends_with(".json")
+}
+
 /// 扫一眼根目录下的 `.trash`：如果存在且仅由"老版本的时间戳子目录"组成就整个删掉。
 /// 保守起见，遇到无法识别的内容就停手不动，避免误删用户自己放在同名目录里的东西。
 fn purge_legacy_trash(root: &Path) -> std::io::Result<()> {
@@ -904,6 +1719,31 @@ pub fn global_resource_migrate(
             }
         }
     }
+    if is_move {
+        // 索引在新库首次列出时重建；旧库里的留着只会过期。
+        let _ = fs::remove_file(old_path.join(library_index::INDEX_FILE));
+    }
+    // 标签与描述跟着走；新库里已有的记录优先。
+    match (LibraryMeta::load(&old_path), LibraryMeta::load(&new_path)) {
+        (Ok(old_meta), _) if old_meta.is_empty() => {}
+        (Ok(old_meta), Ok(mut new_meta)) => {
+            new_meta.absorb(old_meta);
+            match new_meta.save() {
+                Ok(()) if is_move => {
+                    let _ = fs::remove_file(old_path.join(library_meta::META_FILE));
+                }
+                Ok(()) => {}
+                Err(e) => out.warnings.push(ImportWarning {
+                    source: library_meta::META_FILE.to_string(),
+                    message: format!("迁移标签与描述失败: {e}"),
+                }),
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => out.warnings.push(ImportWarning {
+            source: library_meta::META_FILE.to_string(),
+            message: format!("迁移标签与描述失败: {e}"),
+        }),
+    }
     out
 }
 
@@ -1029,3 +1869,260 @@ pub fn copy_file_abs(src: String, dst: String, overwrite: bool) -> Result<u64, S
     }
     fs::copy(&src_p, &dst_p).map_err(|e| format!("拷贝失败 {} -> {}: {e}", src, dst))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("ui-designer-gr-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn entry(rel: &str, kind: &str, tags: &[&str], image: Option<(u32, u32, bool)>) -> GlobalResourceEntry {
+        let name = rel.rsplit('\\').next().unwrap().to_string();
+        GlobalResourceEntry {
+            ext: ext_of(Path::new(&name)),
+            name,
+            rel_path: rel.to_string(),
+            abs_path: String::new(),
+            size: 0,
+            mtime_ms: 0,
+            kind: kind.to_string(),
+            font_family: None,
+            font_style: None,
+            root: String::new(),
+            read_only: false,
+            shadowed: Vec::new(),
+            hash: None,
+            image: image.map(|(width, height, has_alpha)| ImageMeta {
+                width,
+                height,
+                channels: if has_alpha { 4 } else { 3 },
+                alpha_bits: if has_alpha { 8 } else { 0 },
+                has_alpha,
+                blp: None,
+            }),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            description: "金色按钮".to_string(),
+        }
+    }
+
+    #[test]
+    fn search_query_matching() {
+        let button = entry("ui\\Button.blp", "image", &["Menu", "gold"], Some((256, 64, true)));
+        let model = entry("fx\\glow.mdx", "model", &[], None);
+        let hit = |e: &GlobalResourceEntry, q: SearchQuery| {
+            let terms: Vec<String> = q.text.split_whitespace().map(|t| t.to_lowercase()).collect();
+            matches_query(e, &q, &terms)
+        };
+        assert!(hit(&button, SearchQuery::default()));
+        assert!(hit(&button, SearchQuery { text: "BUTTON ui".into(), ..Default::default() }));
+        assert!(hit(&button, SearchQuery { text: "gold".into(), ..Default::default() }));
+        assert!(hit(&model, SearchQuery { text: "金色".into(), ..Default::default() }));
+        assert!(!hit(&button, SearchQuery { text: "button glow".into(), ..Default::default() }));
+        assert!(hit(&button, SearchQuery { tags: vec![" menu ".into()], ..Default::default() }));
+        assert!(!hit(&button, SearchQuery { tags: vec!["menu".into(), "red".into()], ..Default::default() }));
+        assert!(hit(&button, SearchQuery { exts: vec![".BLP".into(), "tga".into()], ..Default::default() }));
+        assert!(!hit(&model, SearchQuery { kind: Some("image".into()), ..Default::default() }));
+        assert!(hit(&model, SearchQuery { kind: Some("Model".into()), ..Default::default() }));
+        let sized = SearchQuery { min_width: Some(128), max_height: Some(64), has_alpha: Some(true), ..Default::default() };
+        assert!(hit(&button, sized.clone()));
+        // 给了尺寸 / alpha 条件时，没有图片元数据的条目不命中
+        assert!(!hit(&model, sized));
+        assert!(!hit(&button, SearchQuery { max_width: Some(255), ..Default::default() }));
+        assert!(!hit(&button, SearchQuery { has_alpha: Some(false), ..Default::default() }));
+    }
+
+    #[test]
+    fn relocate_folder_carries_meta_and_index() {
+        let root = temp_root("move");
+        fs::create_dir_all(root.join("ui")).unwrap();
+        fs::write(root.join("ui").join("a.blp"), b"BLP1").unwrap();
+        let mut meta = LibraryMeta::load(&root).unwrap();
+        meta.set("ui\\a.blp", Some(vec!["button".into()]), None);
+        meta.save().unwrap();
+        let fs_meta = fs::metadata(root.join("ui").join("a.blp")).unwrap();
+        let (size, mtime) = (fs_meta.len(), mtime_millis(&fs_meta));
+        let mut index = LibraryIndex::load(&root);
+        index.refresh("ui\\a.blp", &root.join("ui").join("a.blp"), size, mtime);
+        index.save().unwrap();
+
+        let root_str = root.to_string_lossy().to_string();
+        let roots = vec![LibraryRoot { path: root_str.clone(), read_only: false }];
+        let report = global_resource_move(root_str, roots, "ui".into(), "skins".into(), Vec::new()).unwrap();
+        assert_eq!(report.to, "skins\\ui");
+        assert_eq!(report.moved_files, 1);
+        assert!(root.join("skins").join("ui").join("a.blp").is_file());
+        assert!(!root.join("ui").exists());
+        let meta = LibraryMeta::load(&root).unwrap();
+        assert!(meta.get("ui\\a.blp").is_none());
+        assert_eq!(meta.get("skins\\ui\\a.blp").unwrap().tags, vec!["button"]);
+        assert!(LibraryIndex::load(&root).fresh("skins\\ui\\a.blp", size, mtime).is_some());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn import_dedup_only_applies_to_loose_files() {
+        let root = temp_root("dedup");
+        let ext = root.join("ext");
+        fs::create_dir_all(root.join("lib")).unwrap();
+        fs::create_dir_all(&ext).unwrap();
+        let lib = root.join("lib");
+        fs::write(lib.join("a.png"), b"same bytes").unwrap();
+        fs::write(ext.join("x.png"), b"same bytes").unwrap();
+        fs::write(ext.join("y.png"), b"to be converted").unwrap();
+        fs::write(ext.join("z.png"), b"to be converted").unwrap();
+        let hash = |p: &Path| content_hash::hash_file(p).unwrap();
+
+        let loose = vec![(ext.join("x.png"), String::new())];
+        let mut dedup = ImportDedup::new(&lib, &loose);
+        let dup = dedup.find(&ext.join("x.png"), "", &hash(&ext.join("x.png"))).unwrap();
+        assert_eq!(dup.path, lib.join("a.png"));
+        assert_eq!(dup.describe(&lib), "内容与库中已有的 a.png 相同");
+        // 整个目录导入的文件保持原有层级，不去重
+        assert!(dedup.find(&ext.join("x.png"), "ext", &hash(&ext.join("x.png"))).is_none());
+
+        // 本批转换出的产物与源不再字节相同，只能按源指纹认出来，提示里要说明是转换产物
+        fs::write(lib.join("y.tga"), b"TGA").unwrap();
+        dedup.record(Some(hash(&ext.join("y.png"))), &lib.join("y.tga"), 3);
+        let dup = dedup.find(&ext.join("z.png"), "", &hash(&ext.join("z.png"))).unwrap();
+        assert!(dup.converted);
+        assert_eq!(dup.describe(&lib), "与本批已由相同内容转换导入的 y.tga 重复");
+
+        // 本批模型引用的贴图也不去重
+        fs::write(ext.join("m.mdl"), "Textures 1 {\n\tBitmap {\n\t\tImage \"x.png\",\n\t}\n}").unwrap();
+        let with_model = vec![(ext.join("m.mdl"), String::new()), (ext.join("x.png"), String::new())];
+        let mut dedup = ImportDedup::new(&lib, &with_model);
+        assert!(dedup.find(&ext.join("x.png"), "", &hash(&ext.join("x.png"))).is_none());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn listing_never_writes_index_into_read_only_roots() {
+        let root = temp_root("shared");
+        fs::write(root.join("a.tga"), [0u8; 18]).unwrap();
+        assert_eq!(list_root(&root, true).len(), 1);
+        assert!(!root.join(library_index::INDEX_FILE).exists());
+        // 第二次列出沿用内存里的索引：大小与修改时间没变的文件不重读。
+        let before = fs::metadata(root.join("a.tga")).unwrap().modified().unwrap();
+        let listed = list_root(&root, true);
+        fs::write(root.join("a.tga"), [1u8; 18]).unwrap();
+        fs::File::options().write(true).open(root.join("a.tga")).unwrap().set_modified(before).unwrap();
+        assert_eq!(list_root(&root, true)[0].hash, listed[0].hash);
+        assert!(!root.join(library_index::INDEX_FILE).exists());
+        assert_eq!(list_root(&root, false).len(), 1);
+        assert!(root.join(library_index::INDEX_FILE).is_file());
+        let names: Vec<String> = fs::read_dir(&root)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        assert!(!names.iter().any(|n| n.ends_with(".tmp")), "{names:?}");
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn search_reuses_the_listing_until_refreshed() {
+        let root = temp_root("search-cache");
+        fs::create_dir_all(root.join("ui")).unwrap();
+        fs::write(root.join("ui").join("a.blp"), b"BLP1").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+        let roots = LibraryRoots::new(vec![LibraryRoot { path: root_str.clone(), read_only: false }]);
+        let q = SearchQuery { text: "ui".into(), ..Default::default() };
+        assert_eq!(search_library(&roots, &q).total, 1);
+
+        // 子目录里的外部改动不会让缓存失效，显式刷新后才看得到。
+        fs::write(root.join("ui").join("b.blp"), b"BLP1").unwrap();
+        assert_eq!(search_library(&roots, &q).total, 1);
+        assert_eq!(search_library(&roots, &SearchQuery { refresh: true, ..q.clone() }).total, 2);
+
+        // 本模块自己的写入会丢掉缓存。
+        let owned = vec![LibraryRoot { path: root_str.clone(), read_only: false }];
+        global_resource_set_meta(root_str, owned, "ui\\b.blp".into(), Some(vec!["panel".into()]), None).unwrap();
+        let tagged = search_library(&roots, &SearchQuery { tags: vec!["panel".into()], ..Default::default() });
+        assert_eq!(tagged.total, 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn writes_to_shared_roots_are_rejected() {
+        let shared = temp_root("ro-shared");
+        let writable = temp_root("ro-writable");
+        for root in [&shared, &writable] {
+            fs::create_dir_all(root.join("ui")).unwrap();
+            fs::write(root.join("ui").join("a.blp"), b"BLP1").unwrap();
+        }
+        let (shared_str, writable_str) = (shared.to_string_lossy().to_string(), writable.to_string_lossy().to_string());
+        let roots = vec![
+            LibraryRoot { path: writable_str.clone(), read_only: false },
+            LibraryRoot { path: format!("{shared_str}/"), read_only: true },
+        ];
+
+        assert!(global_resource_delete(shared_str.clone(), roots.clone(), "ui\\a.blp".into()).is_err());
+        assert!(global_resource_delete(shared.join("ui").to_string_lossy().to_string(), roots.clone(), "a.blp".into()).is_err());
+        assert!(global_resource_delete(format!("{writable_str}/../{}", shared.file_name().unwrap().to_string_lossy()), roots.clone(), "ui\\a.blp".into()).is_err());
+        assert!(global_resource_rename(shared_str.clone(), roots.clone(), "ui\\a.blp".into(), "b.blp".into(), Vec::new()).is_err());
+        assert!(global_resource_set_meta(shared_str.clone(), roots.clone(), "ui\\a.blp".into(), Some(vec!["x".into()]), None).is_err());
+        // 调用方没有声明为可写根的目录同样拒绝。
+        assert!(global_resource_delete(writable_str.clone(), Vec::new(), "ui\\a.blp".into()).is_err());
+        assert!(shared.join("ui").join("a.blp").is_file());
+        assert!(!shared.join(library_meta::META_FILE).exists());
+
+        assert!(global_resource_set_meta(writable_str.clone(), roots.clone(), "ui\\a.blp".into(), Some(vec!["x".into()]), None).is_ok());
+        assert!(global_resource_delete(writable_str, roots, "ui\\a.blp".into()).is_ok());
+        assert!(!writable.join("ui").join("a.blp").exists());
+        let _ = fs::remove_dir_all(&shared);
+        let _ = fs::remove_dir_all(&writable);
+    }
+
+    #[test]
+    fn relocate_rejects_traversal_and_library_data_files() {
+        let root = temp_root("traversal");
+        fs::write(root.join("a.blp"), b"BLP1").unwrap();
+        let mut meta = LibraryMeta::load(&root).unwrap();
+        meta.set("a.blp", None, Some("x".into()));
+        meta.save().unwrap();
+        let root_str = root.to_string_lossy().to_string();
+
+        let roots = vec![LibraryRoot { path: root_str.clone(), read_only: false }];
+        assert!(global_resource_move(root_str.clone(), roots.clone(), "a.blp".into(), "..".into(), Vec::new()).is_err());
+        assert!(global_resource_move(root_str.clone(), roots.clone(), "a.blp".into(), "sub\\..\\..".into(), Vec::new()).is_err());
+        assert!(global_resource_move(root_str.clone(), roots.clone(), "..\\a.blp".into(), "sub".into(), Vec::new()).is_err());
+        assert!(global_resource_rename(root_str.clone(), roots.clone(), "a.blp".into(), "..".into(), Vec::new()).is_err());
+        assert!(relocate(&root_str, &LibraryRoots::new(roots.clone()), "a.blp", "..\\evil.blp", &[]).is_err());
+        assert!(relocate(&root_str, &LibraryRoots::new(roots.clone()), "a.blp", "C:\\evil.blp", &[]).is_err());
+        assert!(global_resource_rename(root_str.clone(), roots.clone(), library_meta::META_FILE.into(), "m.json".into(), Vec::new()).is_err());
+        assert!(global_resource_rename(root_str.clone(), roots.clone(), "a.blp".into(), library_index::INDEX_FILE.into(), Vec::new()).is_err());
+        assert!(root.join("a.blp").is_file());
+        assert!(root.join(library_meta::META_FILE).is_file());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn library_roots_reject_traversal_out_of_the_root() {
+        let parent = temp_root("roots-traversal");
+        let root = parent.join("lib");
+        fs::create_dir_all(root.join("ui")).unwrap();
+        fs::write(parent.join("secret.blp"), b"BLP1").unwrap();
+        fs::write(root.join("ui").join("a.blp"), b"BLP1").unwrap();
+        let root_str = root.to_string_lossy().to_string();
+        let roots = LibraryRoots::new(vec![
+            LibraryRoot { path: root_str.clone(), read_only: false },
+            LibraryRoot { path: parent.join("shared").to_string_lossy().to_string(), read_only: true },
+        ]);
+
+        assert!(crate::library_roots::join(&root_str, "..\\secret.blp").is_none());
+        assert!(crate::library_roots::join(&root_str, "ui\\..\\..\\secret.blp").is_none());
+        assert!(crate::library_roots::join(&root_str, ".\\ui\\a.blp").is_none());
+        assert!(crate::library_roots::join(&root_str, "ui\\a.blp").is_some_and(|p| p.is_file()));
+        assert!(roots.resolve("..\\secret.blp").is_none());
+        assert!(roots.root_for("..\\secret.blp").is_none());
+        assert!(roots.is_read_only_path(&format!("{root_str}/../shared/ui")));
+        assert!(!roots.is_read_only_path(&format!("{root_str}/../shared-other")));
+        assert!(roots.is_writable_root(&format!("{root_str}/ui/..")));
+        assert!(!roots.is_writable_root(&format!("{root_str}/ui")));
+        let _ = fs::remove_dir_all(&parent);
+    }
+}
@@ -0,0 +1,293 @@
+//! 图片文件头的最小解析：尺寸、通道、alpha，BLP 另有版本 / 压缩方式 / mip 数。
+//!
+//! - 只读文件头，不解码像素，给全局库索引用；按魔数识别格式，TGA 没有魔数才看扩展名。
+//! - alpha 指"文件带 alpha 通道"，不代表真有透明像素：32 位 BMP 只有带 alpha 掩码时才算。
+//! - PNG 的调色板 / 灰度图带 `tRNS` 块时视为有 alpha。
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageMeta {
+    pub width: u32,
+    pub height: u32,
+    /// 通道数（含 alpha）：灰度 1、灰度+alpha 2、RGB 3、RGBA 4。
+    pub channels: u8,
+    /// alpha 位深，0 表示没有 alpha 通道。
+    pub alpha_bits: u8,
+    pub has_alpha: bool,
+    /// 仅 BLP。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blp: Option<BlpMeta>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlpMeta {
+    /// 0 / 1 / 2，对应 `BLP0` / `BLP1` / `BLP2`。
+    pub version: u8,
+    /// `jpeg` / `palette` / `dxt1` / `dxt3` / `dxt5` / `bgra`。
+    pub compression: String,
+    pub mip_count: u32,
+}
+
+fn le_u16(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 2).map(|s| u16::from_le_bytes([s[0], s[1]]) as u32)
+}
+
+fn le_u32(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4).map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn be_u16(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 2).map(|s| u16::from_be_bytes([s[0], s[1]]) as u32)
+}
+
+fn be_u32(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4).map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn meta(width: u32, height: u32, channels: u8, alpha_bits: u8) -> Option<ImageMeta> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some(ImageMeta { width, height, channels, alpha_bits, has_alpha: alpha_bits > 0, blp: None })
+}
+
+/// 解析图片文件头；不是可识别的图片时返回 `None`。`ext` 为小写扩展名，只用来认 TGA。
+pub fn probe(bytes: &[u8], ext: &str) -> Option<ImageMeta> {
+    match bytes.get(0..4)? {
+        b"BLP0" | b"BLP1" | b"BLP2" => probe_blp(bytes),
+        [0x89, b'P', b'N', b'G'] => probe_png(bytes),
+        [0xFF, 0xD8, ..] => probe_jpeg(bytes),
+        [b'B', b'M', ..] => probe_bmp(bytes),
+        _ if ext == "tga" => probe_tga(bytes),
+        _ => None,
+    }
+}
+
+/// mip 表里从 0 级起连续非空的层数（至少 1）。
+fn count_mips(b: &[u8], sizes_at: usize) -> u32 {
+    let n = (0..16).take_while(|i| le_u32(b, sizes_at + i * 4).unwrap_or(0) != 0).count();
+    n.max(1) as u32
+}
+
+fn probe_blp(b: &[u8]) -> Option<ImageMeta> {
+    let version = b[3] - b'0';
+    let (width, height, alpha_bits, compression, mip_count) = if version == 2 {
+        let content = le_u32(b, 4)?;
+        let encoding = *b.get(8)?;
+        let alpha_bits = *b.get(9)?;
+        let alpha_type = *b.get(10)?;
+        let has_mips = *b.get(11)? != 0;
+        let compression = match (content, encoding, alpha_type) {
+            (0, _, _) => "jpeg",
+            (_, 1, _) => "palette",
+            (_, 2, 0) => "dxt1",
+            (_, 2, 1) => "dxt3",
+            (_, 2, _) => "dxt5",
+            _ => "bgra",
+        };
+        let mips = if has_mips { count_mips(b, 84) } else { 1 };
+        (le_u32(b, 12)?, le_u32(b, 16)?, alpha_bits, compression, mips)
+    } else {
+        let content = le_u32(b, 4)?;
+        let alpha_bits = le_u32(b, 8)?.min(8) as u8;
+        let (width, height) = (le_u32(b, 12)?, le_u32(b, 16)?);
+        let has_mips = le_u32(b, 24)? != 0;
+        let compression = if content == 0 { "jpeg" } else { "palette" };
+        let mips = match (has_mips, version) {
+            (false, _) => 1,
+            // BLP0 的各级 mip 在外部文件里，按尺寸推算完整链的层数。
+            (true, 0) => (32 - width.max(height).max(1).leading_zeros()).min(16),
+            (true, _) => count_mips(b, 92),
+        };
+        (width, height, alpha_bits, compression, mips)
+    };
+    let channels = if alpha_bits > 0 { 4 } else { 3 };
+    let mut m = meta(width, height, channels, alpha_bits)?;
+    m.blp = Some(BlpMeta { version, compression: compression.to_string(), mip_count });
+    Some(m)
+}
+
+fn probe_png(b: &[u8]) -> Option<ImageMeta> {
+    if b.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let (width, height) = (be_u32(b, 16)?, be_u32(b, 20)?);
+    let depth = (*b.get(24)?).min(8);
+    let color_type = *b.get(25)?;
+    // tRNS 只能出现在 IDAT 之前。
+    let mut has_trns = false;
+    let mut at = 8usize;
+    while let (Some(len), Some(kind)) = (be_u32(b, at), b.get(at + 4..at + 8)) {
+        if kind == b"tRNS" {
+            has_trns = true;
+            break;
+        }
+        if kind == b"IDAT" || kind == b"IEND" {
+            break;
+        }
+        at = at.checked_add(12 + len as usize)?;
+    }
+    let (channels, alpha_bits) = match (color_type, has_trns) {
+        (0, false) => (1, 0),
+        (0, true) => (2, 1),
+        (4, _) => (2, depth),
+        (2, false) | (3, false) => (3, 0),
+        (2, true) => (4, 1),
+        (3, true) => (4, 8),
+        (6, _) => (4, depth),
+        _ => return None,
+    };
+    meta(width, height, channels, alpha_bits)
+}
+
+fn probe_jpeg(b: &[u8]) -> Option<ImageMeta> {
+    let mut at = 2usize;
+    loop {
+        if *b.get(at)? != 0xFF {
+            return None;
+        }
+        let marker = *b.get(at + 1)?;
+        // 填充字节 / 无长度的标记。
+        if marker == 0xFF || (0xD0..=0xD9).contains(&marker) || marker == 0x01 {
+            at += if marker == 0xFF { 1 } else { 2 };
+            continue;
+        }
+        let len = be_u16(b, at + 2)? as usize;
+        let is_sof = (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker);
+        if is_sof {
+            let height = be_u16(b, at + 5)?;
+            let width = be_u16(b, at + 7)?;
+            let components = *b.get(at + 9)?;
+            return meta(width, height, components, 0);
+        }
+        at = at.checked_add(2 + len)?;
+    }
+}
+
+fn probe_bmp(b: &[u8]) -> Option<ImageMeta> {
+    let dib = le_u32(b, 14)?;
+    let (width, height, bpp) = if dib == 12 {
+        (le_u16(b, 18)?, le_u16(b, 20)?, le_u16(b, 24)?)
+    } else {
+        let w = le_u32(b, 18)? as i32;
+        let h = le_u32(b, 22)? as i32;
+        (w.unsigned_abs(), h.unsigned_abs(), le_u16(b, 28)?)
+    };
+    // BITMAPV3INFOHEADER 起才有 alpha 掩码（文件偏移 54）。
+    let alpha_mask = if dib >= 56 { le_u32(b, 54).unwrap_or(0) } else { 0 };
+    if bpp == 32 && alpha_mask != 0 {
+        meta(width, height, 4, alpha_mask.count_ones().min(8) as u8)
+    } else {
+        meta(width, height, 3, 0)
+    }
+}
+
+fn probe_tga(b: &[u8]) -> Option<ImageMeta> {
+    let image_type = *b.get(2)?;
+    let width = le_u16(b, 12)?;
+    let height = le_u16(b, 14)?;
+    let depth = *b.get(16)?;
+    let alpha_bits = *b.get(17)? & 0x0f;
+    let (channels, alpha_bits) = match image_type {
+        // 调色板：看调色板条目位深。
+        1 | 9 => {
+            let entry = *b.get(7)?;
+            if entry == 32 { (4, 8) } else { (3, 0) }
+        }
+        2 | 10 => match depth {
+            // 不少工具写 32 位 TGA 时不填描述符里的 alpha 位数，按 8 位算。
+            32 => (4, if alpha_bits == 0 { 8 } else { alpha_bits }),
+            24 => (3, 0),
+            15 | 16 => if alpha_bits > 0 { (4, 1) } else { (3, 0) },
+            _ => return None,
+        },
+        3 | 11 => if alpha_bits > 0 { (2, alpha_bits) } else { (1, 0) },
+        _ => return None,
+    };
+    meta(width, height, channels, alpha_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blp2(encoding: u8, alpha_bits: u8, alpha_type: u8, mips: usize) -> Vec<u8> {
+        let mut b = b"BLP2".to_vec();
+        b.extend(1u32.to_le_bytes());
+        b.extend([encoding, alpha_bits, alpha_type, (mips > 1) as u8]);
+        b.extend(256u32.to_le_bytes());
+        b.extend(128u32.to_le_bytes());
+        b.extend([0u8; 64]);
+        for i in 0..16 {
+            b.extend((if i < mips { 64u32 } else { 0 }).to_le_bytes());
+        }
+        b
+    }
+
+    fn png(color_type: u8, chunks: &[&[u8; 4]]) -> Vec<u8> {
+        let mut b = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+        b.extend(13u32.to_be_bytes());
+        b.extend(b"IHDR");
+        b.extend(32u32.to_be_bytes());
+        b.extend(16u32.to_be_bytes());
+        b.extend([8, color_type, 0, 0, 0]);
+        b.extend([0u8; 4]);
+        for kind in chunks {
+            b.extend(1u32.to_be_bytes());
+            b.extend(*kind);
+            b.extend([0u8; 5]);
+        }
+        b
+    }
+
+    #[test]
+    fn blp2_compression_and_mips() {
+        let m = probe(&blp2(2, 0, 0, 9), "blp").unwrap();
+        assert_eq!((m.width, m.height, m.channels, m.has_alpha), (256, 128, 3, false));
+        assert_eq!(m.blp, Some(BlpMeta { version: 2, compression: "dxt1".into(), mip_count: 9 }));
+        let m = probe(&blp2(2, 8, 1, 1), "blp").unwrap();
+        assert_eq!((m.channels, m.alpha_bits, m.has_alpha), (4, 8, true));
+        assert_eq!(m.blp.unwrap().compression, "dxt3");
+        let m = probe(&blp2(2, 8, 7, 1), "blp").unwrap();
+        assert_eq!(m.blp.as_ref().map(|b| (b.compression.as_str(), b.mip_count)), Some(("dxt5", 1)));
+        assert_eq!(probe(&blp2(1, 8, 8, 1), "blp").unwrap().blp.unwrap().compression, "palette");
+    }
+
+    #[test]
+    fn png_color_types_and_trns() {
+        let m = probe(&png(6, &[b"IDAT"]), "png").unwrap();
+        assert_eq!((m.width, m.height, m.channels, m.alpha_bits), (32, 16, 4, 8));
+        assert!(!probe(&png(2, &[b"IDAT"]), "png").unwrap().has_alpha);
+        assert!(!probe(&png(3, &[b"PLTE", b"IDAT"]), "png").unwrap().has_alpha);
+        let m = probe(&png(3, &[b"PLTE", b"tRNS", b"IDAT"]), "png").unwrap();
+        assert_eq!((m.channels, m.alpha_bits, m.has_alpha), (4, 8, true));
+        assert_eq!(probe(&png(0, &[b"tRNS"]), "png").unwrap().channels, 2);
+        // IDAT 之后的 tRNS 不算
+        assert!(!probe(&png(2, &[b"IDAT", b"tRNS"]), "png").unwrap().has_alpha);
+    }
+
+    #[test]
+    fn truncated_headers_are_rejected() {
+        let blp = blp2(2, 8, 7, 9);
+        for len in [4, 8, 11, 15, 19] {
+            assert_eq!(probe(&blp[..len], "blp"), None, "blp2 truncated at {len}");
+        }
+        // mip 表被截断时按读到的层数算，不越界
+        assert_eq!(probe(&blp[..92], "blp").unwrap().blp.unwrap().mip_count, 2);
+        assert_eq!(probe(&b"BLP1\0\0\0\0"[..], "blp"), None);
+        let p = png(6, &[]);
+        for len in [4, 16, 20, 25] {
+            assert_eq!(probe(&p[..len], "png"), None, "png truncated at {len}");
+        }
+        // 块链在中途截断时只是找不到 tRNS
+        let p = png(3, &[b"PLTE", b"tRNS"]);
+        assert!(!probe(&p[..40], "png").unwrap().has_alpha);
+        assert_eq!(probe(&[0xFF, 0xD8, 0xFF], "jpg"), None);
+        assert_eq!(probe(b"BM\0\0", "bmp"), None);
+        assert_eq!(probe(&[0u8; 10], "tga"), None);
+        assert_eq!(probe(&[], "png"), None);
+    }
+}
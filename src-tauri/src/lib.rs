@@ -1,6 +1,15 @@
+mod content_hash;
+mod font_files;
 mod global_resources;
+mod image_meta;
+mod library_index;
+mod library_meta;
+mod library_roots;
 mod mcp_http;
+mod model_files;
 mod project_engine;
+mod resource_fields;
+mod widget_types;
 
 use std::sync::Mutex;
 
@@ -255,8 +264,14 @@ pub fn run() {
             // 全局资源库（跨项目共享，路径由用户在设置里自行配置）
             global_resources::global_resource_set_root,
             global_resources::global_resource_list,
+            global_resources::global_resource_list_roots,
+            global_resources::global_resource_search,
+            global_resources::global_resource_set_meta,
+            global_resources::global_resource_remove_meta,
             global_resources::global_resource_import,
             global_resources::global_resource_delete,
+            global_resources::global_resource_rename,
+            global_resources::global_resource_move,
             global_resources::global_resource_migrate,
             global_resources::blp_decode_to_png_base64,
             global_resources::tga_decode_to_png_base64,
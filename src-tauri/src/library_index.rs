@@ -0,0 +1,167 @@
+//! 全局库的持久化索引：每个库根下一份 `.ui-designer-index.json`。
+//!
+//! - 按相对路径记录文件的大小、修改时间、内容指纹，图片另记尺寸 / 通道 / alpha / BLP 信息。
+//! - 刷新是增量的：大小与修改时间都没变的文件沿用旧记录，变了才重读；已经不在库里的记录丢掉。
+//! - 索引文件以点开头，列目录时会被跳过，不会出现在资源列表里。
+//! - 只读的共享根从不写入，索引由调用方留在内存里复用；写不进去时由调用方记警告。版本不符或损坏的索引当作空索引重建。
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::content_hash;
+use crate::image_meta::{self, ImageMeta};
+
+pub const INDEX_FILE: &str = ".ui-designer-index.json";
+const INDEX_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexRecord {
+    pub size: u64,
+    pub mtime_ms: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<ImageMeta>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct IndexFile {
+    version: u32,
+    /// 相对路径（反斜杠） -> 记录。
+    entries: BTreeMap<String, IndexRecord>,
+}
+
+#[derive(Debug)]
+pub struct LibraryIndex {
+    path: PathBuf,
+    entries: BTreeMap<String, IndexRecord>,
+    dirty: bool,
+}
+
+impl LibraryIndex {
+    pub fn load(root: &Path) -> Self {
+        let path = root.join(INDEX_FILE);
+        let entries = fs::read(&path)
+            .ok()
+            .and_then(|b| serde_json::from_slice::<IndexFile>(&b).ok())
+            .filter(|f| f.version == INDEX_VERSION)
+            .map(|f| f.entries)
+            .unwrap_or_default();
+        Self { path, entries, dirty: false }
+    }
+
+    /// 记录仍然有效（大小与修改时间都对得上）时返回它。
+    pub fn fresh(&self, rel: &str, size: u64, mtime_ms: i64) -> Option<&IndexRecord> {
+        self.entries
+            .get(rel)
+            .filter(|r| r.size == size && r.mtime_ms == mtime_ms)
+    }
+
+    /// 取 `rel` 的记录，过期或没有时重读 `abs` 并更新索引。
+    pub fn refresh(&mut self, rel: &str, abs: &Path, size: u64, mtime_ms: i64) -> IndexRecord {
+        if let Some(r) = self.fresh(rel, size, mtime_ms) {
+            return r.clone();
+        }
+        let record = read_record(abs, size, mtime_ms);
+        self.entries.insert(rel.to_string(), record.clone());
+        self.dirty = true;
+        record
+    }
+
+    /// 文件或文件夹改名 / 移动后沿用旧记录（改名不改变大小与修改时间），省掉重读。
+    pub fn rename_under(&mut self, from: &str, to: &str) {
+        let moved: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|k| {
+                k.get(..from.len()).is_some_and(|h| h.eq_ignore_ascii_case(from))
+                    && (k.len() == from.len() || k[from.len()..].starts_with('\\'))
+            })
+            .cloned()
+            .collect();
+        for k in moved {
+            if let Some(v) = self.entries.remove(&k) {
+                self.entries.insert(format!("{to}{}", &k[from.len()..]), v);
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// 丢掉不在 `keep` 里的记录。
+    pub fn retain(&mut self, keep: &HashSet<String>) {
+        let before = self.entries.len();
+        self.entries.retain(|rel, _| keep.contains(rel));
+        self.dirty |= self.entries.len() != before;
+    }
+
+    /// 有改动时写回；先写临时文件再改名，避免中途失败留下半截索引。
+    pub fn save(&mut self) -> io::Result<()> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let json = serde_json::to_vec(&serde_json::json!({
+            "version": INDEX_VERSION,
+            "entries": &self.entries,
+        }))
+        .map_err(io::Error::other)?;
+        write_replace(&self.path, &json)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+/// 先写同目录下的临时文件再改名替换 `path`，避免中途失败留下半截文件。临时文件名带进程号与序号，
+/// 同时保存的两个进程 / 线程不会互相踩到对方的临时文件。
+pub fn write_replace(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!("{name}.{}-{seq}.tmp", std::process::id()));
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+fn read_record(abs: &Path, size: u64, mtime_ms: i64) -> IndexRecord {
+    let ext = abs
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    let bytes = fs::read(abs).ok();
+    IndexRecord {
+        size,
+        mtime_ms,
+        hash: bytes.as_deref().map(content_hash::hash_bytes),
+        image: bytes.as_deref().and_then(|b| image_meta::probe(b, &ext)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(size: u64) -> IndexRecord {
+        IndexRecord { size, mtime_ms: 1, hash: None, image: None }
+    }
+
+    #[test]
+    fn rename_under_moves_folder_records() {
+        let mut index = LibraryIndex { path: PathBuf::from(INDEX_FILE), entries: BTreeMap::new(), dirty: false };
+        index.entries.insert("UI\\a.blp".into(), record(1));
+        index.entries.insert("UI\\sub\\b.blp".into(), record(2));
+        index.entries.insert("UI2\\c.blp".into(), record(3));
+        index.rename_under("ui", "skins\\ui");
+        assert!(index.dirty);
+        assert!(index.fresh("skins\\ui\\a.blp", 1, 1).is_some());
+        assert!(index.fresh("skins\\ui\\sub\\b.blp", 2, 1).is_some());
+        assert!(index.fresh("UI\\a.blp", 1, 1).is_none());
+        assert!(index.fresh("UI2\\c.blp", 3, 1).is_some());
+    }
+}
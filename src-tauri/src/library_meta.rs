@@ -0,0 +1,239 @@
+//! 全局库条目的用户元数据：标签与描述，每个库根下一份 `.ui-designer-meta.json`。
+//!
+//! - 与 `library_index` 不同，这是用户写的数据：不会因为文件暂时找不到就丢弃，只在删除库内文件时一并清掉；
+//!   迁移全局库时随文件一起搬走。
+//! - 键是相对库根的路径（反斜杠、小写），与 Windows 下文件名大小写不敏感保持一致。
+//! - 标签去掉首尾空白、丢弃空串，大小写不敏感去重并保留先出现的写法。
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::library_index::write_replace;
+
+pub const META_FILE: &str = ".ui-designer-meta.json";
+const META_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceMeta {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl ResourceMeta {
+    fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.description.is_empty()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaFile {
+    version: u32,
+    entries: BTreeMap<String, ResourceMeta>,
+}
+
+#[derive(Debug)]
+pub struct LibraryMeta {
+    path: PathBuf,
+    entries: BTreeMap<String, ResourceMeta>,
+}
+
+fn key(rel: &str) -> String {
+    rel.trim()
+        .replace('/', "\\")
+        .trim_matches('\\')
+        .to_lowercase()
+}
+
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut out: Vec<String> = Vec::new();
+    for t in tags {
+        let t = t.trim().to_string();
+        if !t.is_empty() && !out.iter().any(|o| o.to_lowercase() == t.to_lowercase()) {
+            out.push(t);
+        }
+    }
+    out
+}
+
+impl LibraryMeta {
+    /// 文件不存在时为空。读不了、解析失败或版本不认识时报错——这是用户数据，
+    /// 不能当成空的再写回去把它覆盖掉。
+    pub fn load(root: &Path) -> Result<Self, String> {
+        let path = root.join(META_FILE);
+        let bytes = match fs::read(&path) {
+            Ok(b) => b,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(Self { path, entries: BTreeMap::new() });
+            }
+            Err(e) => return Err(format!("读取 {META_FILE} 失败: {e}")),
+        };
+        let file: MetaFile = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("{META_FILE} 无法解析（请先修复或移走）: {e}"))?;
+        if file.version != META_VERSION {
+            return Err(format!("{META_FILE} 的版本 {} 不受支持", file.version));
+        }
+        Ok(Self { path, entries: file.entries })
+    }
+
+    pub fn get(&self, rel: &str) -> Option<&ResourceMeta> {
+        self.entries.get(&key(rel))
+    }
+
+    /// 传入的字段整体替换（`tags` 为新的完整标签集，`description` 空串即清空），未传的保留。
+    pub fn set(&mut self, rel: &str, tags: Option<Vec<String>>, description: Option<String>) -> ResourceMeta {
+        let k = key(rel);
+        let mut meta = self.entries.remove(&k).unwrap_or_default();
+        if let Some(tags) = tags {
+            meta.tags = normalize_tags(tags);
+        }
+        if let Some(d) = description {
+            meta.description = d.trim().to_string();
+        }
+        if !meta.is_empty() {
+            self.entries.insert(k, meta.clone());
+        }
+        meta
+    }
+
+    /// 去掉指定标签（大小写不敏感）；`tags` 为 `None` 时清掉该条目的全部标签与描述。
+    pub fn remove(&mut self, rel: &str, tags: Option<&[String]>) -> ResourceMeta {
+        let k = key(rel);
+        let Some(tags) = tags else {
+            self.entries.remove(&k);
+            return ResourceMeta::default();
+        };
+        let mut meta = self.entries.remove(&k).unwrap_or_default();
+        meta.tags
+            .retain(|t| !tags.iter().any(|x| x.trim().to_lowercase() == t.to_lowercase()));
+        if !meta.is_empty() {
+            self.entries.insert(k, meta.clone());
+        }
+        meta
+    }
+
+    /// 清掉 `rel` 本身及其下所有路径的记录（删除文件或整个文件夹时用）。返回是否有改动。
+    pub fn remove_under(&mut self, rel: &str) -> bool {
+        let k = key(rel);
+        let prefix = format!("{k}\\");
+        let before = self.entries.len();
+        self.entries.retain(|e, _| *e != k && !e.starts_with(&prefix));
+        self.entries.len() != before
+    }
+
+    /// 文件或文件夹改名 / 移动后，把 `from` 本身及其下的记录挪到 `to` 下。返回是否有改动。
+    pub fn rename_under(&mut self, from: &str, to: &str) -> bool {
+        let (from, to) = (key(from), key(to));
+        let prefix = format!("{from}\\");
+        let moved: Vec<String> = self
+            .entries
+            .keys()
+            .filter(|k| **k == from || k.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for k in &moved {
+            if let Some(v) = self.entries.remove(k) {
+                self.entries.insert(format!("{to}{}", &k[from.len()..]), v);
+            }
+        }
+        !moved.is_empty()
+    }
+
+    /// 并入另一份元数据（迁移全局库时用）；本方已有的记录优先。返回是否有改动。
+    pub fn absorb(&mut self, other: LibraryMeta) -> bool {
+        let before = self.entries.len();
+        for (k, v) in other.entries {
+            self.entries.entry(k).or_insert(v);
+        }
+        self.entries.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 先写临时文件再改名（见 [`write_replace`]）；没有任何记录时删掉元数据文件。
+    pub fn save(&self) -> io::Result<()> {
+        if self.entries.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_vec_pretty(&serde_json::json!({
+            "version": META_VERSION,
+            "entries": &self.entries,
+        }))
+        .map_err(io::Error::other)?;
+        write_replace(&self.path, &json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty() -> LibraryMeta {
+        LibraryMeta { path: PathBuf::from(META_FILE), entries: BTreeMap::new() }
+    }
+
+    #[test]
+    fn set_and_remove_tags_and_description() {
+        let mut meta = empty();
+        let m = meta.set("UI/a.blp", Some(vec![" gold ".into(), "".into(), "Gold".into(), "menu".into()]), None);
+        assert_eq!(m.tags, vec!["gold", "menu"]);
+        // 未传的字段保留
+        let m = meta.set("ui\\a.blp", None, Some("  按钮  ".into()));
+        assert_eq!((m.tags.len(), m.description.as_str()), (2, "按钮"));
+        let m = meta.remove("ui\\a.blp", Some(&["GOLD".into()]));
+        assert_eq!(m.tags, vec!["menu"]);
+        assert_eq!(meta.get("ui/a.blp").unwrap().description, "按钮");
+        // 标签与描述都清空后整条记录消失
+        meta.set("ui\\a.blp", Some(Vec::new()), Some(String::new()));
+        assert!(meta.get("ui\\a.blp").is_none());
+        meta.set("ui\\b.blp", Some(vec!["x".into()]), Some("y".into()));
+        assert_eq!(meta.remove("ui\\b.blp", None), ResourceMeta::default());
+        assert!(meta.is_empty());
+    }
+
+    #[test]
+    fn remove_under_and_absorb() {
+        let mut meta = empty();
+        meta.set("ui\\a.blp", Some(vec!["a".into()]), None);
+        meta.set("ui\\sub\\b.blp", Some(vec!["b".into()]), None);
+        meta.set("ui2\\c.blp", Some(vec!["c".into()]), None);
+        assert!(meta.remove_under("UI"));
+        assert!(meta.get("ui\\a.blp").is_none() && meta.get("ui\\sub\\b.blp").is_none());
+        assert!(meta.get("ui2\\c.blp").is_some());
+        assert!(!meta.remove_under("ui"));
+
+        let mut other = empty();
+        other.set("ui2\\c.blp", Some(vec!["other".into()]), None);
+        other.set("d.blp", None, Some("d".into()));
+        assert!(meta.absorb(other));
+        // 本方已有的记录优先
+        assert_eq!(meta.get("ui2\\c.blp").unwrap().tags, vec!["c"]);
+        assert_eq!(meta.get("d.blp").unwrap().description, "d");
+        assert!(!meta.absorb(empty()));
+    }
+
+    #[test]
+    fn rename_under_moves_folder_records() {
+        let mut meta = empty();
+        meta.set("UI\\a.blp", Some(vec!["a".into()]), None);
+        meta.set("ui\\sub\\b.blp", None, Some("b".into()));
+        meta.set("ui2\\c.blp", Some(vec!["c".into()]), None);
+        assert!(meta.rename_under("ui", "skins\\UI"));
+        assert!(meta.get("ui\\a.blp").is_none());
+        assert_eq!(meta.get("skins\\ui\\a.blp").unwrap().tags, vec!["a"]);
+        assert_eq!(meta.get("skins/ui/sub/b.blp").unwrap().description, "b");
+        // 只是前缀相同的兄弟目录不受影响
+        assert!(meta.get("ui2\\c.blp").is_some());
+        assert!(!meta.rename_under("missing", "x"));
+    }
+}
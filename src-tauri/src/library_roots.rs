@@ -0,0 +1,149 @@
+//! 全局资源库的多个根：有序列表，靠前的优先。
+//!
+//! - 典型组合：个人可写库在前，团队共享的网络库（只读）在后。导入与 normalize 只写可写根。
+//! - `.uiproj` 里的相对路径按顺序在各根下查找，第一个存在的胜出；都不存在时落到首个根
+//!   （画布显示"预览缺失"，等文件补进库里再打开即可）。
+//! - 同一相对路径在多个根里都有时，靠前的遮蔽（shadow）靠后的：列表只展示胜出的那份。
+//! - 老的单根入参（`globalResourceRoot`）等价于只含一个可写根的列表。
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryRoot {
+    pub path: String,
+    /// 只读根不接收导入 / 删除 / normalize 拷贝。
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LibraryRoots(Vec<LibraryRoot>);
+
+/// 统一成正斜杠、去掉末尾分隔符，并按字面折叠 `.` / `..` 段（不访问磁盘），
+/// 这样 `D:/shared/../lib` 与 `D:/lib` 比较前缀时结果一致。
+fn norm(p: &str) -> String {
+    let p = p.trim().replace('\\', "/");
+    let mut out: Vec<&str> = Vec::new();
+    for seg in p.split('/') {
+        match seg {
+            "." => {}
+            // 开头的空段（`/abs`、`//server/share`）保留，中间与末尾的去掉
+            "" if out.iter().any(|s| !s.is_empty()) => {}
+            ".." if out.last().is_some_and(|s| !s.is_empty() && *s != ".." && !s.ends_with(':')) => {
+                out.pop();
+            }
+            _ => out.push(seg),
+        }
+    }
+    out.join("/").trim_end_matches('/').to_string()
+}
+
+impl LibraryRoots {
+    /// 去掉空路径与重复路径（大小写、正反斜杠不敏感，保留先出现的）。
+    pub fn new(roots: Vec<LibraryRoot>) -> Self {
+        let mut out: Vec<LibraryRoot> = Vec::new();
+        for r in roots {
+            let path = r.path.trim().to_string();
+            if path.is_empty() || out.iter().any(|o| norm(&o.path).eq_ignore_ascii_case(&norm(&path))) {
+                continue;
+            }
+            out.push(LibraryRoot { path, read_only: r.read_only });
+        }
+        Self(out)
+    }
+
+    /// 设计器设置的形态：可写根在前，共享只读根按配置顺序在后。
+    pub fn from_settings(writable: Option<String>, shared: Vec<String>) -> Self {
+        let writable = writable.map(|path| LibraryRoot { path, read_only: false });
+        let shared = shared.into_iter().map(|path| LibraryRoot { path, read_only: true });
+        Self::new(writable.into_iter().chain(shared).collect())
+    }
+
+    pub fn roots(&self) -> &[LibraryRoot] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 接收写入的根：第一个非只读根。
+    pub fn writable(&self) -> Option<&LibraryRoot> {
+        self.0.iter().find(|r| !r.read_only)
+    }
+
+    /// 相对路径应落到哪个根：第一个包含该文件的根，都没有时为首个根。
+    /// 会越出库根的相对路径（见 [`join`]）不落到任何根。
+    pub fn root_for(&self, rel: &str) -> Option<&LibraryRoot> {
+        if !is_safe_rel(rel) {
+            return None;
+        }
+        self.0
+            .iter()
+            .find(|r| join(&r.path, rel).is_some_and(|p| p.is_file()))
+            .or_else(|| self.0.first())
+    }
+
+    /// 在各根下按顺序查找相对路径，返回第一个存在的文件。
+    pub fn resolve(&self, rel: &str) -> Option<PathBuf> {
+        self.0.iter().filter_map(|r| join(&r.path, rel)).find(|p| p.is_file())
+    }
+
+    /// `path` 是某个只读根本身，或落在只读根之下。
+    pub fn is_read_only_path(&self, path: &str) -> bool {
+        let p = norm(path).to_lowercase();
+        self.0.iter().filter(|r| r.read_only).any(|r| {
+            let root = norm(&r.path).to_lowercase();
+            p == root || p.starts_with(&format!("{root}/"))
+        })
+    }
+
+    /// `path` 正是列表里的某个可写根（且不在任何只读根之下）。
+    pub fn is_writable_root(&self, path: &str) -> bool {
+        let p = norm(path);
+        !self.is_read_only_path(path)
+            && self.0.iter().any(|r| !r.read_only && norm(&r.path).eq_ignore_ascii_case(&p))
+    }
+
+    /// 绝对路径落在哪个根下；返回该根与相对路径（反斜杠分隔）。
+    pub fn locate(&self, abs: &str) -> Option<(&LibraryRoot, String)> {
+        let abs_n = norm(abs);
+        self.0.iter().find_map(|r| {
+            let root_n = norm(&r.path);
+            let head = abs_n.get(..root_n.len())?;
+            let rest = abs_n.get(root_n.len()..)?;
+            if !head.eq_ignore_ascii_case(&root_n) || !rest.starts_with('/') {
+                return None;
+            }
+            let rel = rest.trim_start_matches('/');
+            (!rel.is_empty()).then(|| (r, rel.replace('/', "\\")))
+        })
+    }
+}
+
+impl From<Option<String>> for LibraryRoots {
+    fn from(root: Option<String>) -> Self {
+        Self::from_settings(root, Vec::new())
+    }
+}
+
+/// 相对路径是否一定落在库根之下：不含 `.` / `..` 段，也不含 `:`（盘符 / 流名）。
+/// 这些值来自 `.uiproj`，不可信。
+pub fn is_safe_rel(rel: &str) -> bool {
+    rel.split(['\\', '/'])
+        .all(|p| p != "." && p != ".." && !p.contains(':'))
+}
+
+/// 根 + 相对路径（正反斜杠都接受）拼成本机路径；相对路径可能越出库根时（见 [`is_safe_rel`]）为 `None`。
+pub fn join(root: &str, rel: &str) -> Option<PathBuf> {
+    if !is_safe_rel(rel) {
+        return None;
+    }
+    Some(
+        rel.split(['\\', '/'])
+            .filter(|p| !p.is_empty())
+            .fold(Path::new(root.trim()).to_path_buf(), |acc, p| acc.join(p)),
+    )
+}
@@ -21,9 +21,12 @@ use tauri::{AppHandle, Manager};
 use tokio::sync::{Mutex, oneshot};
 use tokio_util::sync::CancellationToken;
 
+use crate::global_resources::{self, SearchQuery};
+use crate::library_roots::LibraryRoots;
 use crate::project_engine::{
-    ApplyOptions, ProjectEngine, TransactionAuditEvent,
+    ApplyOptions, ProjectEngine, TransactionAuditEvent, WidgetQuery,
 };
+use crate::widget_types;
 
 fn protocol_meta() -> &'static serde_json::Value {
     static PROTOCOL: OnceLock<serde_json::Value> = OnceLock::new();
@@ -69,39 +72,41 @@ fn non_empty_trimmed(value: Option<String>) -> Option<String> {
         .filter(|s| !s.is_empty())
 }
 
-/// 解析全局资源库根：优先使用调用方显式传入，其次尝试从运行态设置读取。
-async fn resolve_global_resource_root(
+/// 解析全局资源库根：优先使用调用方显式传入（单个可写根），其次从运行态设置读取
+/// `globalResourceRootPath`（可写根）与 `globalResourceSharedRoots`（共享只读根，按顺序）。
+async fn resolve_library_roots(
     runtime: &Arc<RuntimeBridge>,
     provided: Option<String>,
-) -> Option<String> {
+) -> LibraryRoots {
     if let Some(v) = non_empty_trimmed(provided) {
-        return Some(v);
+        return LibraryRoots::from(Some(v));
     }
 
-    let snap = runtime
-        .dispatch("getProjectSnapshot", json!({}), 3_000)
-        .await
-        .ok()?;
+    let Ok(snap) = runtime.dispatch("getProjectSnapshot", json!({}), 3_000).await else {
+        return LibraryRoots::default();
+    };
 
     // 兼容两种返回形态：
     // 1) { settings: { globalResourceRootPath: "..." } }
     // 2) { data: { settings: { globalResourceRootPath: "..." } } }
-    let from_direct = snap
+    let Some(settings) = snap
         .get("settings")
-        .and_then(|s| s.get("globalResourceRootPath"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    if let Some(v) = non_empty_trimmed(from_direct) {
-        return Some(v);
-    }
-
-    let from_wrapped = snap
-        .get("data")
-        .and_then(|d| d.get("settings"))
-        .and_then(|s| s.get("globalResourceRootPath"))
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-    non_empty_trimmed(from_wrapped)
+        .or_else(|| snap.get("data").and_then(|d| d.get("settings")))
+    else {
+        return LibraryRoots::default();
+    };
+    let writable = non_empty_trimmed(
+        settings
+            .get("globalResourceRootPath")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string()),
+    );
+    let shared: Vec<String> = settings
+        .get("globalResourceSharedRoots")
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|x| x.as_str()).map(|s| s.to_string()).collect())
+        .unwrap_or_default();
+    LibraryRoots::from_settings(writable, shared)
 }
 
 /// 若 Rust 引擎尚未加载任何项目，尝试从前端运行态读取当前已打开的项目路径并自动 open。
@@ -150,13 +155,13 @@ async fn ensure_engine_project_loaded(
     };
 
     // 3. 顺便读取全局资源库根（前端设置），用于水合图片绝对路径。
-    let global_root = resolve_global_resource_root(runtime, None).await;
+    let roots = resolve_library_roots(runtime, None).await;
 
     // 4. 打开项目并水合路径。
     let mut eng = engine.lock().await;
     match eng.open_project(project_path.clone()).await {
         Ok(_) => {
-            eng.hydrate_runtime_image_paths(global_root);
+            eng.hydrate_runtime_image_paths(roots);
             vec![format!(
                 "auto-sync success: loaded frontend project \"{}\"",
                 project_path
@@ -308,6 +313,13 @@ fn json_value_array_schema(_gen: &mut SchemaGenerator) -> Schema {
     .expect("static json_value_array_schema is valid")
 }
 
+/// widget 引用：数字 id 或名字路径（如 `shopDialog/shopDialog_btn_1`）。
+fn widget_ref_schema(_gen: &mut SchemaGenerator) -> Schema {
+    schemars::json_schema!({
+        "type": ["integer", "string"]
+    })
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct UiOpenProjectArgs {
     #[serde(rename = "projectPath")]
@@ -318,6 +330,9 @@ struct UiOpenProjectArgs {
 struct UiSaveProjectArgs {
     #[serde(rename = "projectPath")]
     project_path: Option<String>,
+    /// 全局资源库根；库内的绝对路径按它换算成 relPath 落盘。留空时从设计器设置自动读取。
+    #[serde(default, rename = "globalResourceRoot")]
+    global_resource_root: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
@@ -377,6 +392,53 @@ struct UiListResourcesArgs {
     global_resource_root: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UiListFontsArgs {
+    /// 全局资源库根；用来扫描库里的 .ttf / .otf。
+    #[serde(default, rename = "globalResourceRoot")]
+    global_resource_root: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UiSearchLibraryArgs {
+    /// 空白分隔的关键词，每个都要命中文件名 / relPath / 标签 / 描述之一（不区分大小写）。
+    #[serde(default)]
+    text: Option<String>,
+    /// 必须全部带上的标签。
+    #[serde(default)]
+    tags: Option<Vec<String>>,
+    /// 扩展名（不含点），如 `["blp","tga"]`；命中其一即可。
+    #[serde(default)]
+    exts: Option<Vec<String>>,
+    /// `image` / `model` / `font`。
+    #[serde(default)]
+    kind: Option<String>,
+    /// 尺寸范围（像素，含端点）；给了任一项时只返回读得出尺寸的图片。
+    #[serde(default, rename = "minWidth")]
+    min_width: Option<u32>,
+    #[serde(default, rename = "maxWidth")]
+    max_width: Option<u32>,
+    #[serde(default, rename = "minHeight")]
+    min_height: Option<u32>,
+    #[serde(default, rename = "maxHeight")]
+    max_height: Option<u32>,
+    /// 只要带 / 不带 alpha 通道的图片。
+    #[serde(default, rename = "hasAlpha")]
+    has_alpha: Option<bool>,
+    /// 先重新扫描全局库再搜（库在设计器外被改过时用）；缺省复用最近一次的列表。
+    #[serde(default)]
+    refresh: Option<bool>,
+    /// 分页起点，默认 0。
+    #[serde(default)]
+    offset: Option<usize>,
+    /// 每页条数，默认 50，最多 500。
+    #[serde(default)]
+    limit: Option<usize>,
+    /// 全局资源库根；缺省时按设计器设置读取全部库根（含共享只读库）。
+    #[serde(default, rename = "globalResourceRoot")]
+    global_resource_root: Option<String>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct UiCopyResourcesArgs {
     /// 目标目录。例如 wc3-map-ts-template 的 `resource/`；每个资源按"相对全局库根"的子路径
@@ -406,6 +468,55 @@ struct UiNormalizeResourcePathsArgs {
     global_resource_root: Option<String>,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UiQueryWidgetsArgs {
+    /// 精确匹配 widget `type`（panel / button / text ...）。
+    #[serde(default, rename = "type")]
+    widget_type: Option<String>,
+    /// `name` 通配：`*` 任意串、`?` 单字符，大小写不敏感。
+    #[serde(default)]
+    name: Option<String>,
+    /// 只在该 widget 及其后代中查找：数字 id 或名字路径。
+    #[serde(default)]
+    #[schemars(schema_with = "widget_ref_schema")]
+    root: Option<serde_json::Value>,
+    #[serde(default, rename = "templateKind")]
+    template_kind: Option<String>,
+    /// 与矩形 `{ x, y, w, h }` 相交的 widget。
+    #[serde(default)]
+    #[schemars(schema_with = "schema_loose_object")]
+    intersects: Option<serde_json::Value>,
+    /// 字段谓词列表 `{ field, op, value? }`，op 取 eq / ne / gt / gte / lt / lte / empty / notEmpty / contains；
+    /// `field` 支持 `padding.top` 这类点路径。
+    #[serde(default, rename = "where")]
+    #[schemars(schema_with = "json_value_array_schema")]
+    predicates: Option<Vec<serde_json::Value>>,
+    /// 字段投影：只返回这些字段（总会带上 `id`）。
+    #[serde(default)]
+    fields: Option<Vec<String>>,
+    /// 最多返回条数，默认 50。
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UiGetOutlineArgs {
+    /// 只输出该 widget 的子树：数字 id 或名字路径；缺省输出全部顶层节点。
+    #[serde(default)]
+    #[schemars(schema_with = "widget_ref_schema")]
+    root: Option<serde_json::Value>,
+    /// 最大展开深度（起点为 0）；缺省不限。被截断的节点以 `(+N children)` 标注。
+    #[serde(default, rename = "maxDepth")]
+    max_depth: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct UiDescribeWidgetTypesArgs {
+    /// 只返回这些类型（如 `["button","panel"]`）；缺省返回全部。通用属性总是返回。
+    #[serde(default)]
+    types: Option<Vec<String>>,
+}
+
 #[derive(Debug, Deserialize, JsonSchema)]
 struct UiRuntimeCallArgs {
     method: String,
@@ -435,30 +546,36 @@ impl UiDesignerMcp {
         &self,
         Parameters(args): Parameters<UiOpenProjectArgs>,
     ) -> Result<Json<UiDesignerEnvelope>, McpError> {
-        let global_root = resolve_global_resource_root(&self.runtime, None).await;
+        let roots = resolve_library_roots(&self.runtime, None).await;
         let mut eng = self.engine.lock().await;
         eng
             .open_project(args.project_path)
             .await
             .map_err(|e| McpError::internal_error(e, None))?;
-        eng.hydrate_runtime_image_paths(global_root);
+        eng.hydrate_runtime_image_paths(roots);
         let snap = eng.get_snapshot();
         let diags: Vec<String> = snap.diagnostics.clone();
         let v = serde_json::to_value(&snap).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         Ok(Json(ok_envelope(v, diags)))
     }
 
-    #[tool(description = "保存当前项目到磁盘")]
+    #[tool(description = "保存当前项目到磁盘。资源字段里全局库下的绝对路径按库根换算成相对路径落盘（内存里仍是绝对路径），与设计器保存的 .uiproj 一致；库外的绝对路径原样写出并在 diagnostics 里列出。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。")]
     async fn ui_save_project(
         &self,
         Parameters(args): Parameters<UiSaveProjectArgs>,
     ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
         let mut eng = self.engine.lock().await;
         let r = eng
-            .save_project(args.project_path)
+            .save_project(args.project_path, roots)
             .await
             .map_err(|e| McpError::internal_error(e, None))?;
-        Ok(Json(ok_envelope(r, vec![])))
+        let diags: Vec<String> = r
+            .get("diagnostics")
+            .and_then(|d| d.as_array())
+            .map(|a| a.iter().filter_map(|x| x.as_str()).map(|s| s.to_string()).collect())
+            .unwrap_or_default();
+        Ok(Json(ok_envelope(r, diags)))
     }
 
     #[tool(description = "从 wc3-template-export 生成的 *.ui.json sidecar 反向导入项目快照（用于 yarn ui:push）")]
@@ -501,6 +618,72 @@ impl UiDesignerMcp {
         Ok(Json(ok_envelope(v, auto_diags)))
     }
 
+    #[tool(description = "按条件查询 widgets（type / name 通配 / 子树 root / templateKind / 矩形相交 / 字段谓词），支持字段投影与 limit；返回 total / returned / truncated / widgets。大项目里代替 ui_get_snapshot 定位控件。若引擎尚未加载项目，会自动尝试从前端读取当前打开的项目路径并同步加载。")]
+    async fn ui_query_widgets(
+        &self,
+        Parameters(args): Parameters<UiQueryWidgetsArgs>,
+    ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let auto_diags = ensure_engine_project_loaded(&self.engine, &self.runtime).await;
+        let intersects = args
+            .intersects
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| McpError::invalid_params(format!("intersects: {e}"), None))?;
+        let predicates = args
+            .predicates
+            .unwrap_or_default()
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| McpError::invalid_params(format!("where: {e}"), None))?;
+        let query = WidgetQuery {
+            widget_type: args.widget_type,
+            name: args.name,
+            root: args.root,
+            template_kind: args.template_kind,
+            intersects,
+            r#where: predicates,
+            fields: args.fields,
+            limit: args.limit,
+        };
+        let eng = self.engine.lock().await;
+        let data = eng
+            .query(&query)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(Json(ok_envelope(data, auto_diags)))
+    }
+
+    #[tool(description = "以缩进文本输出 widget 层级大纲（data.outline），每行：#id type \"name\" [x,y wxh] + text / image 文件名 + 与默认值不同的字段。支持 root（id 或名字路径）与 maxDepth。用几百 token 看懂大项目，再按需 ui_query_widgets。若引擎尚未加载项目，会自动尝试从前端读取当前打开的项目路径并同步加载。")]
+    async fn ui_get_outline(
+        &self,
+        Parameters(args): Parameters<UiGetOutlineArgs>,
+    ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let auto_diags = ensure_engine_project_loaded(&self.engine, &self.runtime).await;
+        let eng = self.engine.lock().await;
+        let data = eng
+            .outline(args.root.as_ref(), args.max_depth)
+            .map_err(|e| McpError::invalid_params(e, None))?;
+        Ok(Json(ok_envelope(data, auto_diags)))
+    }
+
+    #[tool(description = "发布 widget 类型注册表：data.commonProps 为所有类型共享的属性，data.types[] 为各类型的中文标签与专属属性。每个属性给出 type（integer/number/boolean/string/enum/color/object）、min/max、values、nullable、readOnly、default。createWidget / updateWidgetProps / patchWidget / updateWhere 写入的字段按它校验，已登记类型拒绝未知属性。不需要打开项目。")]
+    async fn ui_describe_widget_types(
+        &self,
+        Parameters(args): Parameters<UiDescribeWidgetTypesArgs>,
+    ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let reg = widget_types::registry();
+        let types: Vec<&widget_types::WidgetTypeSpec> = reg
+            .types
+            .iter()
+            .filter(|t| match &args.types {
+                Some(want) => want.iter().any(|w| w == t.widget_type),
+                None => true,
+            })
+            .collect();
+        let data = json!({ "commonProps": reg.common_props, "types": types });
+        Ok(Json(ok_envelope(data, vec![])))
+    }
+
     #[tool(description = "批量应用动作（create/update/delete/setParent）")]
     async fn ui_apply_actions(
         &self,
@@ -543,8 +726,9 @@ impl UiDesignerMcp {
 
     #[tool(description = "校验当前项目")]
     async fn ui_validate(&self) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let roots = resolve_library_roots(&self.runtime, None).await;
         let eng = self.engine.lock().await;
-        let r = eng.validate();
+        let r = eng.validate(roots);
         let diags = r.diagnostics.clone();
         let v = serde_json::to_value(&r).map_err(|e| McpError::internal_error(e.to_string(), None))?;
         Ok(Json(ok_envelope(v, diags)))
@@ -584,17 +768,17 @@ impl UiDesignerMcp {
         Ok(Json(ok_envelope(json!({ "events": events }), vec![])))
     }
 
-    #[tool(description = "列出当前项目中被 widget 引用的图片资源（含 localPath / exists / usedByWidgetIds）。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。若引擎尚未加载项目，会自动尝试从前端读取当前打开的项目路径并同步加载。")]
+    #[tool(description = "列出当前项目中被 widget 引用的资源文件（图片 / 模型 / 字体，含 localPath / exists / usedByWidgetIds）。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。若引擎尚未加载项目，会自动尝试从前端读取当前打开的项目路径并同步加载。")]
     async fn ui_list_resources(
         &self,
         Parameters(args): Parameters<UiListResourcesArgs>,
     ) -> Result<Json<UiDesignerEnvelope>, McpError> {
         let auto_diags = ensure_engine_project_loaded(&self.engine, &self.runtime).await;
-        let global_root = resolve_global_resource_root(&self.runtime, args.global_resource_root).await;
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
         let eng = self.engine.lock().await;
         let mut data = eng.list_resources(
             args.include_unused.unwrap_or(false),
-            global_root,
+            roots,
         );
         // 把 auto_diags 注入到 data.diagnostics 中（list_resources 返回的是普通 JSON value）
         if !auto_diags.is_empty() {
@@ -612,19 +796,64 @@ impl UiDesignerMcp {
         Ok(Json(ok_envelope(data, auto_diags)))
     }
 
+    #[tool(description = "列出 widget `font` 可用的字体：内置字体（MPQ 内路径）与全局库里的 .ttf / .otf（含 family / style），以及项目里每个 font 值的使用情况（`known=false` 表示既非内置也不在库里）。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。")]
+    async fn ui_list_fonts(
+        &self,
+        Parameters(args): Parameters<UiListFontsArgs>,
+    ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let auto_diags = ensure_engine_project_loaded(&self.engine, &self.runtime).await;
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
+        let eng = self.engine.lock().await;
+        Ok(Json(ok_envelope(eng.list_fonts(roots), auto_diags)))
+    }
+
+    #[tool(description = "在全局资源库里搜索文件：按关键词（文件名 / relPath / 标签 / 描述）、标签、扩展名、类型、尺寸范围与是否带 alpha 过滤，结果按 relPath 排序并分页（`total` / `offset` / `limit` / `items`）。条目的 `absPath` 即 widget 资源字段应填的值，`image` 里有宽高 / 通道 / BLP 信息。默认搜最近一次列出的库，库在设计器外改过时传 `refresh: true`。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。")]
+    async fn ui_search_library(
+        &self,
+        Parameters(args): Parameters<UiSearchLibraryArgs>,
+    ) -> Result<Json<UiDesignerEnvelope>, McpError> {
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
+        if roots.is_empty() {
+            return Ok(Json(ok_envelope(
+                json!({ "total": 0, "offset": 0, "limit": 0, "items": [] }),
+                vec!["no globalResourceRoot: pass it or configure the library in the designer settings".to_string()],
+            )));
+        }
+        let query = SearchQuery {
+            text: args.text.unwrap_or_default(),
+            tags: args.tags.unwrap_or_default(),
+            exts: args.exts.unwrap_or_default(),
+            kind: non_empty_trimmed(args.kind),
+            min_width: args.min_width,
+            max_width: args.max_width,
+            min_height: args.min_height,
+            max_height: args.max_height,
+            has_alpha: args.has_alpha,
+            refresh: args.refresh.unwrap_or(false),
+            offset: args.offset.unwrap_or(0),
+            limit: args.limit,
+        };
+        // 首次搜索或刷新大库时要建索引（读文件算指纹），放到阻塞线程里做。
+        let page = tokio::task::spawn_blocking(move || global_resources::search_library(&roots, &query))
+            .await
+            .map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        let v = serde_json::to_value(&page).map_err(|e| McpError::internal_error(e.to_string(), None))?;
+        Ok(Json(ok_envelope(v, vec![])))
+    }
+
     #[tool(description = "把资源文件从各自的 localPath 拷贝到 targetDir 下的 <war3 相对路径>（典型用法：把资源落到模板仓 `resource/`）。未指定 values 时默认拷贝所有被 widget 引用的资源。未显式传 `globalResourceRoot` 时会尝试从设计器设置自动读取。")]
     async fn ui_copy_resources(
         &self,
         Parameters(args): Parameters<UiCopyResourcesArgs>,
     ) -> Result<Json<UiDesignerEnvelope>, McpError> {
-        let global_root = resolve_global_resource_root(&self.runtime, args.global_resource_root).await;
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
         let eng = self.engine.lock().await;
         let data = eng
             .copy_resources(
                 args.target_dir,
                 args.values,
                 args.overwrite.unwrap_or(true),
-                global_root,
+                roots,
             )
             .await
             .map_err(|e| McpError::internal_error(e, None))?;
@@ -646,10 +875,10 @@ impl UiDesignerMcp {
         &self,
         Parameters(args): Parameters<UiNormalizeResourcePathsArgs>,
     ) -> Result<Json<UiDesignerEnvelope>, McpError> {
-        let global_root = resolve_global_resource_root(&self.runtime, args.global_resource_root).await;
+        let roots = resolve_library_roots(&self.runtime, args.global_resource_root).await;
         let (data, snap_value) = {
             let mut eng = self.engine.lock().await;
-            let data = eng.normalize_resource_paths(args.prefix, global_root);
+            let data = eng.normalize_resource_paths(args.prefix, roots);
             let snap = eng.get_snapshot();
             let sv = serde_json::to_value(&snap)
                 .map_err(|e| McpError::internal_error(e.to_string(), None))?;
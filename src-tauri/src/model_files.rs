@@ -0,0 +1,153 @@
+//! 模型文件（.mdx / .mdl）的最小解析：只关心模型引用了哪些贴图。
+//!
+//! - MDX：`MDLX` 魔数后是 `tag(4) + size(u32 LE) + data` 的 chunk 序列；`TEXS` chunk 由
+//!   268 字节的记录组成：`replaceableId(u32) + fileName([u8; 260]) + flags(u32)`。
+//! - MDL：文本格式，`Textures` 块里的 `Image "path",` 行。
+//! - `fileName` 为空的记录是可替换贴图（队伍颜色等），由游戏提供，不算外部引用。
+
+use std::path::{Path, PathBuf};
+
+pub const MODEL_EXTS: &[&str] = &["mdx", "mdl"];
+
+const TEXS_RECORD_LEN: usize = 268;
+const TEXS_PATH_LEN: usize = 260;
+
+pub fn is_model_ext(ext: &str) -> bool {
+    MODEL_EXTS.contains(&ext.to_ascii_lowercase().as_str())
+}
+
+/// 一条贴图引用及其在本地的解析结果。
+#[derive(Debug, Clone)]
+pub struct ModelTexture {
+    /// 模型里写的原始路径（如 `war3mapImported\hero.blp`）。
+    pub reference: String,
+    /// 在模型所在目录下找到的文件：先按原路径，找不到再退化为同目录下的同名文件；
+    /// 游戏内置贴图通常找不到，为 `None`。
+    pub source: Option<PathBuf>,
+    /// 模型引用的、相对模型所在目录的子路径（`/` 分隔）。拷贝时总按它摆放——源是同目录下的
+    /// 同名文件时也一样——保证模型里的引用仍然有效。
+    pub rel: String,
+    /// 引用越出模型目录（`..`、盘符、绝对路径），已拒绝；此时 `source` 为 `None`、`rel` 为空。
+    pub rejected: bool,
+}
+
+/// 从 MDX 字节里取出 `TEXS` chunk 的贴图路径。不是 MDX 时返回空。
+pub fn mdx_texture_paths(bytes: &[u8]) -> Vec<String> {
+    let mut out = Vec::new();
+    if bytes.get(0..4) != Some(b"MDLX") {
+        return out;
+    }
+    let mut pos = 4usize;
+    while pos + 8 <= bytes.len() {
+        let tag = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let start = pos + 8;
+        let end = start.saturating_add(size).min(bytes.len());
+        if tag == b"TEXS" {
+            for rec in bytes[start..end].chunks_exact(TEXS_RECORD_LEN) {
+                let raw = &rec[4..4 + TEXS_PATH_LEN];
+                let len = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+                let path = String::from_utf8_lossy(&raw[..len]).trim().to_string();
+                if !path.is_empty() {
+                    out.push(path);
+                }
+            }
+        }
+        pos = end;
+    }
+    out
+}
+
+/// 从 MDL 文本里取出 `Image "..."` 的贴图路径。
+pub fn mdl_texture_paths(text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    for line in text.lines() {
+        let Some(rest) = line.trim_start().strip_prefix("Image") else {
+            continue;
+        };
+        let Some(open) = rest.find('"') else {
+            continue;
+        };
+        let Some(len) = rest[open + 1..].find('"') else {
+            continue;
+        };
+        let path = rest[open + 1..open + 1 + len].trim();
+        if !path.is_empty() {
+            out.push(path.to_string());
+        }
+    }
+    out
+}
+
+/// 读取模型文件，返回去重后的贴图引用（按出现顺序）。读不了或格式不认识时返回空。
+pub fn texture_paths(model: &Path) -> Vec<String> {
+    let Ok(bytes) = std::fs::read(model) else {
+        return Vec::new();
+    };
+    let ext = model
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .unwrap_or_default();
+    let found = if ext == "mdl" {
+        mdl_texture_paths(&String::from_utf8_lossy(&bytes))
+    } else {
+        mdx_texture_paths(&bytes)
+    };
+    let mut out: Vec<String> = Vec::new();
+    for p in found {
+        if !out.iter().any(|q| q.eq_ignore_ascii_case(&p)) {
+            out.push(p);
+        }
+    }
+    out
+}
+
+/// 把模型里的贴图引用转成相对模型目录的子路径（`/` 分隔）。
+///
+/// 模型文件不可信：含 `..` 段、`:`（盘符 / 流名）或以分隔符开头（绝对路径 / UNC）的引用
+/// 一律拒绝，返回 `None`，避免拷贝时写到模型目录之外。
+pub fn safe_texture_rel(reference: &str) -> Option<String> {
+    let rel = reference.replace('\\', "/");
+    if rel.is_empty() || rel.starts_with('/') || rel.contains(':') {
+        return None;
+    }
+    let parts: Vec<&str> = rel.split('/').filter(|p| !p.is_empty() && *p != ".").collect();
+    if parts.is_empty() || parts.contains(&"..") {
+        return None;
+    }
+    Some(parts.join("/"))
+}
+
+/// 把 `rel` 拼到 `dir` 下，并确认结果（按真实路径，符号链接也算）仍落在 `dir` 之内；
+/// 越界时返回 `None`。目标可以尚不存在：取它最近的已存在祖先比较。
+pub fn join_within(dir: &Path, rel: &str) -> Option<PathBuf> {
+    let rel = safe_texture_rel(rel)?;
+    let joined = rel.split('/').fold(dir.to_path_buf(), |acc, p| acc.join(p));
+    let base = dir.canonicalize().ok()?;
+    let mut cur = joined.as_path();
+    loop {
+        if let Ok(real) = cur.canonicalize() {
+            return real.starts_with(&base).then_some(joined);
+        }
+        cur = cur.parent()?;
+    }
+}
+
+/// 在模型所在目录下查找它引用的贴图：先按原路径，再退化为同目录下的同名文件。
+/// 越界的引用（见 [`safe_texture_rel`]）标记为 `rejected`，不做解析。
+pub fn resolve_textures(model: &Path) -> Vec<ModelTexture> {
+    let dir = model.parent().map(Path::to_path_buf).unwrap_or_default();
+    texture_paths(model)
+        .into_iter()
+        .map(|reference| {
+            let Some(rel_full) = safe_texture_rel(&reference) else {
+                return ModelTexture { reference, source: None, rel: String::new(), rejected: true };
+            };
+            let basename = rel_full.rsplit('/').next().unwrap_or(&rel_full);
+            let found = |rel: &str| join_within(&dir, rel).filter(|p| p.is_file());
+            let source = found(&rel_full).or_else(|| found(basename));
+            ModelTexture { reference, source, rel: rel_full, rejected: false }
+        })
+        .collect()
+}
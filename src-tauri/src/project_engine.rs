@@ -2,10 +2,16 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 
+use crate::font_files;
+use crate::library_roots::{self, LibraryRoots};
+use crate::model_files;
+use crate::resource_fields;
+use crate::widget_types;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectSnapshot {
@@ -32,6 +38,7 @@ pub struct ProjectData {
 
 pub const PROJECT_SCHEMA_VERSION: &str = "2.0.0";
 
+
 fn default_schema_version() -> String { PROJECT_SCHEMA_VERSION.to_string() }
 
 fn default_project() -> ProjectData {
@@ -91,6 +98,7 @@ pub struct ProjectEngine {
     project_path: Option<PathBuf>,
     action_audit: Vec<AuditEvent>,
     transaction_audit: Vec<TransactionAuditEvent>,
+    idempotency: IdempotencyCache,
 }
 
 impl ProjectEngine {
@@ -100,6 +108,7 @@ impl ProjectEngine {
             project_path: None,
             action_audit: Vec::new(),
             transaction_audit: Vec::new(),
+            idempotency: IdempotencyCache::default(),
         }
     }
 
@@ -132,47 +141,83 @@ impl ProjectEngine {
         self.project = serde_json::from_value(base).map_err(|e| e.to_string())?;
         self.project.schema_version = PROJECT_SCHEMA_VERSION.to_string();
         self.project_path = Some(PathBuf::from(project_path));
+        self.idempotency.clear();
         Ok(self.get_snapshot())
     }
 
-    /// schema 2.0.0 运行时约定：widget.image/clickImage/hoverImage 应是绝对路径。
+    /// schema 2.0.0 运行时约定：资源字段（见 `resource_fields`）应是绝对路径。
     ///
     /// 当项目从磁盘打开时，这些字段通常是相对全局库根的 relPath；此函数用于在内存中
-    /// 做 rel -> abs 水合，便于画布与预览直接读取。
-    pub fn hydrate_runtime_image_paths(&mut self, global_root: Option<String>) -> usize {
-        let root = global_root
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty())
-            .unwrap_or_default();
-
-        let mut changed = 0usize;
-        for w in &mut self.project.widgets {
-            let Some(obj) = w.as_object_mut() else {
-                continue;
+    /// 做 rel -> abs 水合，便于画布与预览直接读取。有多个库根时按顺序取第一个包含该文件的根。
+    /// 字体例外：库里找不到的相对路径是游戏内字体，保留原样。
+    pub fn hydrate_runtime_image_paths(&mut self, roots: impl Into<LibraryRoots>) -> usize {
+        let roots = roots.into();
+        resource_fields::visit_files_mut(&mut self.project.widgets, &mut self.project.settings, |_, field, v| {
+            let rel = strip_war3_imported_prefix(v);
+            if field.kind == resource_fields::ResourceKind::Font && !is_absolute_path(rel) && roots.resolve(rel).is_none() {
+                return;
+            }
+            let root = roots.root_for(rel).map(|r| r.path.as_str()).unwrap_or("");
+            *v = rel_to_abs_runtime_path(v, root);
+        })
+    }
+
+    /// 落盘前的反向换算（hydrate 的逆操作）：全局库根下的绝对路径写成相对库根的 relPath，
+    /// 内存里仍保持绝对路径。库外的绝对路径原样写出并给出诊断——这类项目换台机器就找不到资源，
+    /// 应先 `normalize_resource_paths` 把文件收进库。没有库根时所有绝对路径都算库外。
+    /// 有多个库根时相对于所在的那个根；打开时按根的顺序查找，靠前根里的同名文件会遮蔽它。
+    ///
+    /// 返回 (落盘用的项目 JSON, 换算个数, 诊断)。
+    fn dehydrated_project(&self, roots: &LibraryRoots) -> Result<(serde_json::Value, usize, Vec<String>), String> {
+        let mut widgets = self.project.widgets.clone();
+        let mut settings = self.project.settings.clone();
+        let mut outside: Vec<String> = Vec::new();
+        let changed = resource_fields::visit_files_mut(&mut widgets, &mut settings, |id, field, v| {
+            let rel = match roots.locate(v) {
+                Some((root, _)) => abs_to_rel_storage_path(v, &root.path),
+                None => abs_to_rel_storage_path(v, ""),
             };
-            for field in ["image", "clickImage", "hoverImage"] {
-                let Some(v) = obj.get_mut(field) else {
-                    continue;
-                };
-                let Some(s) = v.as_str() else {
-                    continue;
-                };
-                if s.is_empty() {
-                    continue;
-                }
-                let hydrated = rel_to_abs_runtime_path(s, &root);
-                if hydrated != s {
-                    *v = serde_json::Value::String(hydrated);
-                    changed += 1;
+            match rel {
+                Some(rel) => *v = rel,
+                None => {
+                    let owner = match id {
+                        Some(id) => format!("widget {}", id),
+                        None => "project".to_string(),
+                    };
+                    outside.push(format!("{} {}: {}", owner, field.path, v));
                 }
             }
+        });
+        let mut diagnostics = Vec::new();
+        if !outside.is_empty() {
+            let reason = if roots.is_empty() {
+                "no globalResourceRoot, absolute paths saved as-is"
+            } else {
+                "absolute paths outside the global resource library"
+            };
+            diagnostics.push(format!(
+                "{}: {} (e.g. {})",
+                reason,
+                outside.len(),
+                outside.iter().take(3).cloned().collect::<Vec<_>>().join("; ")
+            ));
         }
-        changed
+        let mut data = serde_json::to_value(&self.project).map_err(|e| e.to_string())?;
+        if let Some(obj) = data.as_object_mut() {
+            obj.insert("widgets".into(), serde_json::Value::Array(widgets));
+            obj.insert("settings".into(), settings);
+        }
+        Ok((data, changed, diagnostics))
     }
 
-    
-
-    pub async fn save_project(&mut self, project_path: Option<String>) -> Result<serde_json::Value, String> {
+    /// 保存项目。资源字段按 [`Self::dehydrated_project`] 换算成相对全局库根的路径后写盘，
+    /// 与设计器前端保存出的 `.uiproj` 一致。
+    pub async fn save_project(
+        &mut self,
+        project_path: Option<String>,
+        roots: impl Into<LibraryRoots>,
+    ) -> Result<serde_json::Value, String> {
+        let roots = roots.into();
         let path = project_path
             .or_else(|| self.project_path.as_ref().map(|p| p.to_string_lossy().to_string()))
             .ok_or_else(|| "projectPath is required".to_string())?;
@@ -186,12 +231,13 @@ impl ProjectEngine {
             }
         }
 
-        let data = serde_json::to_string_pretty(&self.project).map_err(|e| e.to_string())?;
+        let (project, dehydrated, diagnostics) = self.dehydrated_project(&roots)?;
+        let data = serde_json::to_string_pretty(&project).map_err(|e| e.to_string())?;
         fs::write(&path, data)
             .await
             .map_err(|e| format!("写入项目文件失败 {}: {}", path, e))?;
         self.project_path = Some(PathBuf::from(&path));
-        Ok(json!({ "path": path }))
+        Ok(json!({ "path": path, "dehydrated": dehydrated, "diagnostics": diagnostics }))
     }
 
     /// 从 codegen.mjs 写出的 `*.ui.json` sidecar 恢复项目快照。
@@ -273,16 +319,18 @@ impl ProjectEngine {
         }
 
         self.project = serde_json::from_value(base).map_err(|e| e.to_string())?;
+        self.idempotency.clear();
         // 注意：不改 project_path——sidecar 只代表 UI 数据，不是 .uiproj 项目文件本身
         Ok(self.get_snapshot())
     }
 
-    // -------- 资源管理（image/clickImage/hoverImage） --------
+    // -------- 资源管理（字段见 resource_fields 登记表） --------
     //
     // schema 2.0.0 约定：
-    //   - 项目不再维护资源登记表；widget.image（以及 clickImage/hoverImage）是对
-    //     **全局资源库里某个文件的绝对磁盘路径引用**。
-    //   - `list_resources` 直接扫 widgets 生成视图。
+    //   - 项目不再维护资源登记表；widget.image（以及其它登记过的资源字段、
+    //     `settings.canvasBgImage`）是对**全局资源库里某个文件的绝对磁盘路径引用**。
+    //   - `list_resources` 直接扫 widgets 与项目设置生成视图；预设 / 内置字体等游戏内置
+    //     路径带 `builtin: true` 列出，不解析本地文件。
     //   - `normalize_resource_paths` 把"裸绝对路径"指向的文件拷进全局库，
     //     并把 widget 字段改写成"**全局库内**的绝对路径"。
     //   - `copy_resources` 把 widget 引用到的文件拷到目标目录（模板仓 `resource/`），
@@ -294,50 +342,47 @@ impl ProjectEngine {
     pub fn list_resources(
         &self,
         _include_unused: bool,
-        global_resource_root: Option<String>,
+        roots: impl Into<LibraryRoots>,
     ) -> serde_json::Value {
         use std::collections::BTreeMap;
 
-        let global_root: Option<PathBuf> = global_resource_root
-            .as_ref()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(PathBuf::from);
+        let roots = roots.into();
 
-        let mut used_by: BTreeMap<String, Vec<i64>> = BTreeMap::new();
-        for w in &self.project.widgets {
-            let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-            for field in ["image", "clickImage", "hoverImage"] {
-                if let Some(v) = w.get(field).and_then(|x| x.as_str()) {
-                    if v.is_empty() {
-                        continue;
-                    }
-                    used_by.entry(v.to_string()).or_default().push(id);
+        // value -> (kind, 是否磁盘文件, 引用它的 widget, 引用它的字段)
+        type Usage = (resource_fields::ResourceKind, bool, Vec<i64>, Vec<&'static str>);
+        let mut used_by: BTreeMap<String, Usage> = BTreeMap::new();
+        for r in resource_fields::collect(&self.project.widgets, &self.project.settings) {
+            if r.resolved.is_empty() {
+                continue;
+            }
+            let entry = used_by
+                .entry(r.resolved.clone())
+                .or_insert_with(|| (r.field.kind, r.is_file, Vec::new(), Vec::new()));
+            if let Some(id) = r.widget_id {
+                if !entry.2.contains(&id) {
+                    entry.2.push(id);
                 }
             }
+            if !entry.3.contains(&r.field.path) {
+                entry.3.push(r.field.path);
+            }
         }
 
         let mut out = Vec::with_capacity(used_by.len());
-        for (value, widgets) in used_by {
+        for (value, (kind, is_file, widgets, fields)) in used_by {
             let is_absolute = is_absolute_path(&value);
-            let (local_path, exists) = if is_absolute {
+            let (local_path, exists) = if !is_file {
+                (String::new(), false)
+            } else if is_absolute {
                 let lp = value.clone();
                 let ok = Path::new(&lp).is_file();
                 (lp, ok)
-            } else if let Some(root) = &global_root {
-                // 兼容两种相对值：`war3mapImported/...` 与库内相对路径（如 `ui\\...`）。
-                let war3_rel = value
-                    .trim_start_matches(|c| c == '\\' || c == '/')
-                    .to_string();
-                let stripped = war3_rel
-                    .strip_prefix("war3mapImported/")
-                    .or_else(|| war3_rel.strip_prefix("war3mapImported\\"))
-                    .unwrap_or(&war3_rel)
-                    .to_string();
-                let rel_sys = stripped
-                    .replace('\\', std::path::MAIN_SEPARATOR_STR)
-                    .replace('/', std::path::MAIN_SEPARATOR_STR);
-                let abs = root.join(rel_sys);
+            } else if let Some(abs) = roots
+                .root_for(strip_war3_imported_prefix(&value))
+                .and_then(|root| library_roots::join(&root.path, strip_war3_imported_prefix(&value)))
+            {
+                // 兼容两种相对值：`war3mapImported/...` 与库内相对路径（如 `ui\\...`）；
+                // 多个库根时取第一个包含该文件的根。含 `..` 的相对值不解析。
                 (abs.to_string_lossy().to_string(), abs.is_file())
             } else {
                 (String::new(), false)
@@ -352,52 +397,73 @@ impl ProjectEngine {
                 "localPath": local_path,
                 "exists": exists,
                 "isAbsolutePath": is_absolute,
+                "kind": kind,
+                "builtin": !is_file,
+                "fields": fields,
                 "usedByWidgetIds": widgets,
             }));
         }
         json!({ "resources": out })
     }
 
-    /// 把项目里被引用的图片从 widget 自身的绝对路径拷到 target_dir。
+    /// 可选字体：内置字体 + 全局库里的 .ttf / .otf（带 family / style），以及项目里每个
+    /// `font` 值被哪些 widget 使用。`known=false` 的值既不是内置字体也不在库里，`ui_validate` 会报出。
+    /// 多个库根里相对路径相同的字体只列靠前根里的那份。
+    pub fn list_fonts(&self, roots: impl Into<LibraryRoots>) -> serde_json::Value {
+        let library = library_fonts(&roots.into());
+        let builtin: Vec<serde_json::Value> = font_files::BUILTIN_FONTS
+            .iter()
+            .map(|(value, label)| json!({ "value": value, "label": label }))
+            .collect();
+
+        let mut used_by: std::collections::BTreeMap<String, Vec<i64>> = std::collections::BTreeMap::new();
+        for w in &self.project.widgets {
+            if let Some(v) = get_str(w, "font").filter(|v| !v.is_empty()) {
+                used_by.entry(v.to_string()).or_default().push(get_i64(w, "id", 0));
+            }
+        }
+        let used: Vec<serde_json::Value> = used_by
+            .into_iter()
+            .map(|(value, ids)| {
+                let known = font_known(&value, &library);
+                json!({ "value": value, "known": known, "usedByWidgetIds": ids })
+            })
+            .collect();
+        json!({ "builtin": builtin, "library": library, "used": used })
+    }
+
+    /// 把项目里被引用的资源从 widget 自身的绝对路径拷到 target_dir。
     ///
     /// schema 2.0.0：
     /// - 不再依赖 `project.resources` 登记表——widget 的绝对路径本身就是"源"。
     /// - `values`：可选，一组 widget 引用值。空或缺省时默认拷贝所有被 widget 引用到的值。
-    /// - `global_resource_root`：用来把 abs 还原成库内 relPath，决定目标子目录层级。
-    ///   没提供时退化为直接用 basename 作为目标子路径。
+    /// - `roots`：全局库根（可多个），用来把 abs 还原成库内 relPath，决定目标子目录层级。
+    ///   不在任何库根下时退化为直接用 basename 作为目标子路径。
     /// - 非绝对路径的值（例如 `war3mapImported\icon.blp`）会进入 `skipped`，除非它们恰好
-    ///   可以在 `global_resource_root` 下解析到真实文件——这种情况主要留给老项目兜底。
+    ///   可以在某个库根下解析到真实文件——这种情况主要留给老项目兜底。
+    /// - 模型（.mdx / .mdl）会连同它引用、且能在模型目录下找到的贴图一起拷贝，条目带 `texture`
+    ///   字段；找不到的贴图（多为游戏内置）进 `skipped`。
     pub async fn copy_resources(
         &self,
         target_dir: String,
         values: Option<Vec<String>>,
         overwrite: bool,
-        global_resource_root: Option<String>,
+        roots: impl Into<LibraryRoots>,
     ) -> Result<serde_json::Value, String> {
+        let roots = roots.into();
         let target_root = PathBuf::from(&target_dir);
         fs::create_dir_all(&target_root)
             .await
             .map_err(|e| format!("建立目标目录失败 {}: {}", target_dir, e))?;
 
-        let global_root: Option<PathBuf> = global_resource_root
-            .as_ref()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(PathBuf::from);
-
         let target_values: Vec<String> = match values {
             Some(v) if !v.is_empty() => v,
             _ => {
-                let mut set: HashSet<String> = HashSet::new();
-                for w in &self.project.widgets {
-                    for field in ["image", "clickImage", "hoverImage"] {
-                        if let Some(v) = w.get(field).and_then(|x| x.as_str()) {
-                            if !v.is_empty() {
-                                set.insert(v.to_string());
-                            }
-                        }
-                    }
-                }
+                let set: HashSet<String> = resource_fields::collect(&self.project.widgets, &self.project.settings)
+                    .into_iter()
+                    .filter(|r| r.is_file)
+                    .map(|r| r.value)
+                    .collect();
                 set.into_iter().collect()
             }
         };
@@ -405,39 +471,32 @@ impl ProjectEngine {
         let mut copied = Vec::new();
         let mut skipped = Vec::new();
         let mut errors = Vec::new();
+        // 多个模型共用同一张贴图时只拷一次
+        let mut copied_textures: HashSet<PathBuf> = HashSet::new();
 
         for value in &target_values {
             // 解析出 (源绝对路径, 相对全局库的子路径)
             let (src_abs, rel_in_lib): (String, String) = if is_absolute_path(value) {
-                let abs_buf = PathBuf::from(value);
-                let rel = match &global_root {
-                    Some(root) => abs_buf
-                        .strip_prefix(root)
-                        .ok()
-                        .map(|p| p.to_string_lossy().to_string())
-                        .unwrap_or_else(|| {
-                            abs_buf
-                                .file_name()
-                                .map(|n| n.to_string_lossy().to_string())
-                                .unwrap_or_else(|| value.clone())
-                        }),
-                    None => abs_buf
+                let rel = match roots.locate(value) {
+                    Some((_, rel)) => rel,
+                    None => Path::new(value)
                         .file_name()
                         .map(|n| n.to_string_lossy().to_string())
                         .unwrap_or_else(|| value.clone()),
                 };
                 (value.clone(), rel)
             } else {
-                // 兜底：老项目的 `war3mapImported\...`，只有在有 global_root 时才能解析源文件
-                let war3_rel = value.trim_start_matches(|c| c == '\\' || c == '/').to_string();
-                let stripped = war3_rel
-                    .strip_prefix("war3mapImported/")
-                    .or_else(|| war3_rel.strip_prefix("war3mapImported\\"))
-                    .unwrap_or(&war3_rel)
-                    .to_string();
-                match &global_root {
-                    Some(root) => {
-                        let abs_buf = root.join(stripped.replace('\\', std::path::MAIN_SEPARATOR_STR.chars().next().unwrap_or('/').to_string().as_str()));
+                // 兜底：老项目的 `war3mapImported\...`，只有在有库根时才能解析源文件
+                let stripped = strip_war3_imported_prefix(value).to_string();
+                if !library_roots::is_safe_rel(&stripped) {
+                    skipped.push(json!({
+                        "value": value,
+                        "reason": "relative path escapes the library root (rejected)",
+                    }));
+                    continue;
+                }
+                match roots.root_for(&stripped).and_then(|root| library_roots::join(&root.path, &stripped)) {
+                    Some(abs_buf) => {
                         if !abs_buf.is_file() {
                             skipped.push(json!({
                                 "value": value,
@@ -497,12 +556,82 @@ impl ProjectEngine {
                     "destPath": dest.to_string_lossy(),
                     "bytes": bytes,
                 })),
-                Err(e) => errors.push(json!({
-                    "value": value,
-                    "fromPath": src_abs,
-                    "destPath": dest.to_string_lossy(),
-                    "reason": format!("copy failed: {}", e)
-                })),
+                Err(e) => {
+                    errors.push(json!({
+                        "value": value,
+                        "fromPath": src_abs,
+                        "destPath": dest.to_string_lossy(),
+                        "reason": format!("copy failed: {}", e)
+                    }));
+                    continue;
+                }
+            }
+
+            // 模型：把它引用的贴图按相对模型目录的层级一起拷过去，保证模型里的路径仍然有效。
+            let is_model = Path::new(&src_abs)
+                .extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(model_files::is_model_ext);
+            if !is_model {
+                continue;
+            }
+            let dest_dir = dest.parent().map(Path::to_path_buf).unwrap_or_else(|| target_root.clone());
+            for tex in model_files::resolve_textures(Path::new(&src_abs)) {
+                if tex.rejected {
+                    errors.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "reason": "model texture path escapes the model directory (rejected)",
+                    }));
+                    continue;
+                }
+                let Some(tex_src) = tex.source else {
+                    skipped.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "reason": "model texture not found next to the model (built-in game texture?)",
+                    }));
+                    continue;
+                };
+                let Some(tex_dest) = model_files::join_within(&dest_dir, &tex.rel) else {
+                    errors.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "reason": "model texture path escapes the destination directory (rejected)",
+                    }));
+                    continue;
+                };
+                if !copied_textures.insert(tex_dest.clone()) {
+                    continue;
+                }
+                if !overwrite && tex_dest.exists() {
+                    skipped.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "reason": "destination exists and overwrite=false",
+                        "destPath": tex_dest.to_string_lossy(),
+                    }));
+                    continue;
+                }
+                if let Some(parent) = tex_dest.parent() {
+                    let _ = fs::create_dir_all(parent).await;
+                }
+                match fs::copy(&tex_src, &tex_dest).await {
+                    Ok(bytes) => copied.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "fromPath": tex_src.to_string_lossy(),
+                        "destPath": tex_dest.to_string_lossy(),
+                        "bytes": bytes,
+                    })),
+                    Err(e) => errors.push(json!({
+                        "value": value,
+                        "texture": tex.reference,
+                        "fromPath": tex_src.to_string_lossy(),
+                        "destPath": tex_dest.to_string_lossy(),
+                        "reason": format!("copy failed: {}", e)
+                    })),
+                }
             }
         }
 
@@ -517,37 +646,26 @@ impl ProjectEngine {
     /// 把 widget 里"裸绝对路径"指向的文件拷进全局资源库，并把 widget 字段改写成
     /// **全局库内的绝对路径**（保持 schema 2.0.0：运行时 widget.image = 绝对磁盘路径）。
     ///
-    /// - **`global_root` 必填**：没有全局库就没有新模型。缺失 → `{ ok: false, diagnostics: [...] }`。
-    /// - 源路径若已经落在 `global_root` 下，不拷贝，仅规范化分隔符。
-    /// - 源路径在别处：按 `<global_root>/<basename>` 拷贝（文件已存在且大小相同时跳过）。
+    /// - **可写库根必填**：没有全局库就没有新模型。缺失 → `{ ok: false, diagnostics: [...] }`。
+    /// - 源路径若已经落在任一库根（含只读共享根）下，不拷贝，保持原值。
+    /// - 源路径在别处：按 `<可写根>/<basename>` 拷贝（目标已存在时复用）。
     /// - 非绝对路径（如 `war3mapImported\foo.blp`）不处理，留给上层 / 前端。
+    /// - 模型（.mdx / .mdl）进库时，能在其目录下找到的贴图一并拷到模型旁边。
     ///
     /// 参数（向后兼容 `prefix`，2.0.0 起不再使用）：
     /// - `prefix`：保留入参以免破坏调用方签名；不影响输出。
-    /// - `global_root`：来自前端 `settings.globalResourceRootPath`；建议显式传。
+    /// - `roots`：来自前端 `settings.globalResourceRootPath`（可写）与共享只读根；建议显式传。
     pub fn normalize_resource_paths(
         &mut self,
         _prefix: Option<String>,
-        global_root: Option<String>,
+        roots: impl Into<LibraryRoots>,
     ) -> serde_json::Value {
-        let mut needs_global_root = false;
-        for w in self.project.widgets.iter() {
-            for field in ["image", "clickImage", "hoverImage"] {
-                if let Some(s) = w.get(field).and_then(|x| x.as_str()) {
-                    if !s.is_empty() && is_absolute_path(s) {
-                        needs_global_root = true;
-                        break;
-                    }
-                }
-            }
-            if needs_global_root { break; }
-        }
+        let roots = roots.into();
+        let needs_global_root = resource_fields::collect(&self.project.widgets, &self.project.settings)
+            .iter()
+            .any(|r| r.is_file && is_absolute_path(&r.value) && roots.locate(&r.value).is_none());
 
-        let global_root_path: Option<PathBuf> = global_root
-            .as_ref()
-            .map(|s| s.trim())
-            .filter(|s| !s.is_empty())
-            .map(PathBuf::from);
+        let global_root_path: Option<PathBuf> = roots.writable().map(|r| PathBuf::from(&r.path));
 
         if needs_global_root && global_root_path.is_none() {
             return json!({
@@ -567,78 +685,93 @@ impl ProjectEngine {
         // basename 去重——多个 widget 指向同一 abs 时只拷贝一次
         let mut basename_to_dest: HashMap<String, PathBuf> = HashMap::new();
 
-        for w in self.project.widgets.iter_mut() {
-            let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-            let wobj = match w.as_object_mut() {
-                Some(o) => o,
-                None => continue,
-            };
-            for field in ["image", "clickImage", "hoverImage"] {
-                let cur = match wobj.get(field).and_then(|x| x.as_str()) {
-                    Some(s) if !s.is_empty() => s.to_string(),
-                    _ => continue,
-                };
-                if !is_absolute_path(&cur) {
-                    continue;
-                }
-                let cur_buf = PathBuf::from(&cur);
+        resource_fields::visit_files_mut(&mut self.project.widgets, &mut self.project.settings, |id, field, value| {
+            let field = field.path;
+            let cur = value.clone();
+            if !is_absolute_path(&cur) {
+                return;
+            }
+            let cur_buf = PathBuf::from(&cur);
 
-                // 已经在 global_root 下了，只归一化字符串，不拷贝
-                let already_in_root = match &global_root_path {
-                    Some(root) => cur_buf.starts_with(root),
-                    None => false,
-                };
+            // 已经在某个库根下了，不拷贝
+            let already_in_root = roots.locate(&cur).is_some();
 
-                let dest_path: PathBuf = if already_in_root {
-                    cur_buf.clone()
-                } else {
-                    let root = global_root_path.as_ref().expect("checked above");
-                    let basename = cur_buf
-                        .file_name()
-                        .map(|n| n.to_string_lossy().to_string())
-                        .unwrap_or_else(|| cur.clone());
-                    let dest = basename_to_dest
-                        .entry(basename.to_ascii_lowercase())
-                        .or_insert_with(|| root.join(&basename))
-                        .clone();
-                    if !dest.exists() {
-                        if let Some(parent) = dest.parent() {
-                            let _ = std::fs::create_dir_all(parent);
-                        }
-                        match std::fs::copy(&cur, &dest) {
-                            Ok(_) => copied.push(json!({
+            let dest_path: PathBuf = if already_in_root {
+                cur_buf.clone()
+            } else {
+                let root = global_root_path.as_ref().expect("checked above");
+                let basename = cur_buf
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| cur.clone());
+                let dest = basename_to_dest
+                    .entry(basename.to_ascii_lowercase())
+                    .or_insert_with(|| root.join(&basename))
+                    .clone();
+                if !dest.exists() {
+                    if let Some(parent) = dest.parent() {
+                        let _ = std::fs::create_dir_all(parent);
+                    }
+                    match std::fs::copy(&cur, &dest) {
+                        Ok(_) => {
+                            copied.push(json!({
                                 "widgetId": id,
                                 "field": field,
                                 "src": cur.clone(),
                                 "dst": dest.to_string_lossy(),
-                            })),
-                            Err(e) => {
-                                errors.push(json!({
-                                    "widgetId": id,
-                                    "field": field,
-                                    "src": cur.clone(),
-                                    "dst": dest.to_string_lossy(),
-                                    "message": format!("拷贝到全局库失败: {e}"),
-                                }));
-                                continue;
+                            }));
+                            // 模型引用的贴图跟着进库，按相对模型目录的层级摆放；
+                            // 本地找不到的（游戏内置贴图）不处理。
+                            if cur_buf.extension().and_then(|s| s.to_str()).is_some_and(model_files::is_model_ext) {
+                                let dest_dir = dest.parent().map(Path::to_path_buf).unwrap_or_else(|| root.clone());
+                                for tex in model_files::resolve_textures(&cur_buf) {
+                                    // 越出模型目录的引用（`rejected`，source 为 None）在这里同样被跳过。
+                                    let Some(tex_src) = tex.source else { continue };
+                                    let Some(tex_dest) = model_files::join_within(&dest_dir, &tex.rel) else { continue };
+                                    if tex_dest.exists() {
+                                        continue;
+                                    }
+                                    if let Some(parent) = tex_dest.parent() {
+                                        let _ = std::fs::create_dir_all(parent);
+                                    }
+                                    if std::fs::copy(&tex_src, &tex_dest).is_ok() {
+                                        copied.push(json!({
+                                            "widgetId": id,
+                                            "field": field,
+                                            "texture": tex.reference,
+                                            "src": tex_src.to_string_lossy(),
+                                            "dst": tex_dest.to_string_lossy(),
+                                        }));
+                                    }
+                                }
                             }
                         }
+                        Err(e) => {
+                            errors.push(json!({
+                                "widgetId": id,
+                                "field": field,
+                                "src": cur.clone(),
+                                "dst": dest.to_string_lossy(),
+                                "message": format!("拷贝到全局库失败: {e}"),
+                            }));
+                            return;
+                        }
                     }
-                    dest
-                };
-
-                let new_value = dest_path.to_string_lossy().to_string();
-                if new_value != cur {
-                    wobj.insert(field.to_string(), serde_json::Value::String(new_value.clone()));
-                    rewritten.push(json!({
-                        "widgetId": id,
-                        "field": field,
-                        "from": cur,
-                        "to": new_value,
-                    }));
                 }
+                dest
+            };
+
+            let new_value = dest_path.to_string_lossy().to_string();
+            if new_value != cur {
+                *value = new_value.clone();
+                rewritten.push(json!({
+                    "widgetId": id,
+                    "field": field,
+                    "from": cur,
+                    "to": new_value,
+                }));
             }
-        }
+        });
 
         json!({
             "ok": errors.is_empty(),
@@ -648,8 +781,70 @@ impl ProjectEngine {
         })
     }
 
+    /// 按条件筛选 widgets，避免为了找几个控件就拉取整份快照。
+    ///
+    /// 各条件之间为 AND；结果保持 `widgets` 数组顺序，超过 `limit` 时截断并标记 `truncated`。
+    pub fn query(&self, q: &WidgetQuery) -> Result<serde_json::Value, String> {
+        let matched = select_widgets(&self.project.widgets, q)?;
+        let limit = q.limit.unwrap_or(DEFAULT_QUERY_LIMIT);
+        let out: Vec<serde_json::Value> = matched
+            .iter()
+            .take(limit)
+            .map(|w| match &q.fields {
+                Some(fields) if !fields.is_empty() => {
+                    let mut o = serde_json::Map::new();
+                    o.insert("id".into(), w.get("id").cloned().unwrap_or(json!(0)));
+                    for f in fields {
+                        if let Some(v) = get_path(w, f) {
+                            o.insert(f.clone(), v.clone());
+                        }
+                    }
+                    serde_json::Value::Object(o)
+                }
+                _ => (*w).clone(),
+            })
+            .collect();
+        Ok(json!({
+            "total": matched.len(),
+            "returned": out.len(),
+            "truncated": matched.len() > out.len(),
+            "widgets": out,
+        }))
+    }
+
+    /// 把 widget 层级渲染成缩进文本，一行一个 widget，供 agent 低成本理解大项目。
+    ///
+    /// 每行：`#id type "name" [x,y wxh]`，随后是 `text` / `image`（仅文件名），
+    /// 以及与 `make_default_widget` 默认值不同的其它字段。`max_depth` 为 0 时只输出
+    /// 起点层；被截断的子节点以 `(+N children)` 标出。
+    pub fn outline(
+        &self,
+        root: Option<&serde_json::Value>,
+        max_depth: Option<usize>,
+    ) -> Result<serde_json::Value, String> {
+        let tree = build_widget_tree(&self.project.widgets);
+        let starts: Vec<&serde_json::Value> = match root {
+            Some(r) => {
+                let id = resolve_widget_ref(&self.project.widgets, r)?;
+                let node = find_tree_node(&tree, id)
+                    .ok_or_else(|| format!("widget not found: {}", id))?;
+                vec![node]
+            }
+            None => tree.iter().collect(),
+        };
+        let mut lines = Vec::new();
+        for node in starts {
+            render_outline_node(node, 0, max_depth, &mut lines);
+        }
+        Ok(json!({
+            "widgetCount": self.project.widgets.len(),
+            "lineCount": lines.len(),
+            "outline": lines.join("\n"),
+        }))
+    }
+
     pub fn get_snapshot(&self) -> ProjectSnapshot {
-        let diagnostics = self.validate().diagnostics;
+        let diagnostics = self.diagnostics();
         ProjectSnapshot {
             project_path: self
                 .project_path
@@ -675,7 +870,11 @@ impl ProjectEngine {
             + 1;
         let mut errors = Vec::new();
         let mut warnings = Vec::new();
+        let mut results = Vec::with_capacity(actions.len());
+        // 批内别名：createWidget 的 `payload.ref` -> 新建根节点 id（子节点登记为 `<ref>.<role>`）
+        let mut refs: HashMap<String, i64> = HashMap::new();
         let mut applied = 0usize;
+        let mut replayed = 0usize;
         let session_id = options
             .session_id
             .clone()
@@ -706,7 +905,52 @@ impl ProjectEngine {
                         applied + errors.len()
                     )
                 });
-            let res = (|| -> Result<(), String> {
+            // 显式给出的 idempotencyKey / actionId 命中缓存：直接返回首次结果，不再执行
+            let idempotency_key = action
+                .get("idempotencyKey")
+                .or_else(|| action.get("actionId"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            if let Some(cached) = idempotency_key.as_ref().and_then(|k| self.idempotency.get(k)) {
+                let mut replay = cached.clone();
+                if replay.action_type != action_type {
+                    let e = format!(
+                        "idempotency key {} was already used by a {} action",
+                        idempotency_key.as_deref().unwrap_or(""),
+                        replay.action_type
+                    );
+                    replay.status = "failed".into();
+                    replay.error = Some(e.clone());
+                    replay.action_type = action_type.to_string();
+                    replay.created.clear();
+                    replay.affected.clear();
+                    errors.push(e);
+                } else {
+                    replay.status = "replayed".into();
+                    replayed += 1;
+                    let alias = action
+                        .get("payload")
+                        .and_then(|p| p.get("ref"))
+                        .and_then(|x| x.as_str())
+                        .map(|s| s.trim())
+                        .filter(|s| !s.is_empty());
+                    if let Some(a) = alias {
+                        register_created_refs(&mut refs, a, &replay.created);
+                    }
+                }
+                replay.action_id = action_id;
+                results.push(replay);
+                continue;
+            }
+            // 锁定保护：触及锁定子树的动作整条拒绝（status = refused），除非 force + allowDangerous
+            let refusal = resolve_batch_refs(action, &refs)
+                .ok()
+                .and_then(|a| lock_refusal(action_type, &a, &working.widgets, options.allow_dangerous));
+            let res = (|| -> Result<ActionEffect, String> {
+                let action = &resolve_batch_refs(action, &refs)?;
+                if let Some(e) = &refusal {
+                    return Err(e.clone());
+                }
                 if action_type == "clearProject" && !options.allow_dangerous {
                     return Err("dangerous action blocked: clearProject".into());
                 }
@@ -721,106 +965,659 @@ impl ProjectEngine {
                         .get("overrides")
                         .cloned()
                         .unwrap_or(json!({}));
+                    if let Some(o) = overrides.as_object() {
+                        widget_types::check_props(&widget_type, o)?;
+                    }
+                    let alias = payload
+                        .get("ref")
+                        .and_then(|x| x.as_str())
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty());
+                    if let Some(a) = &alias {
+                        if refs.contains_key(a) {
+                            return Err(format!("duplicate ref in batch: {}", a));
+                        }
+                    }
                     let created = build_composite_widgets(
                         &widget_type,
                         &overrides,
                         &mut next_id,
                     );
-                    for w in created {
+                    let mut effect = ActionEffect::default();
+                    for (role, w) in created {
+                        let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+                        effect.created.push(CreatedWidget { id, role });
                         working.widgets.push(w);
                     }
+                    if let Some(a) = &alias {
+                        register_created_refs(&mut refs, a, &effect.created);
+                    }
                     self.push_audit(&session_id, &action_id, action_type, dry_run);
                     applied += 1;
-                    return Ok(());
+                    return Ok(effect);
                 }
                 if action_type == "updateWidgetProps" {
-                    let target_id = action
-                        .get("targetId")
-                        .and_then(|x| x.as_i64())
-                        .ok_or_else(|| "missing targetId".to_string())?;
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    if let Some(p) = action.get("payload").and_then(|p| p.get("parentId")) {
+                        check_reparent(&working.widgets, target_id, p.as_i64())?;
+                    }
                     let target = working
                         .widgets
                         .iter_mut()
                         .find(|w| w.get("id").and_then(|x| x.as_i64()) == Some(target_id))
                         .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    // mode=merge：RFC 7396 merge-patch，只改动 payload 里出现的嵌套键，null 表示删除
+                    let mode = action.get("mode").and_then(|x| x.as_str()).unwrap_or("replace");
                     if let Some(p) = action.get("payload").and_then(|x| x.as_object()) {
-                        for (k, v) in p {
-                            target[k] = v.clone();
+                        widget_types::check_props(get_str(target, "type").unwrap_or(""), p)?;
+                    }
+                    match (mode, action.get("payload")) {
+                        ("merge", Some(p @ serde_json::Value::Object(_))) => merge_patch(target, p),
+                        ("replace", Some(serde_json::Value::Object(p))) => {
+                            for (k, v) in p {
+                                target[k] = v.clone();
+                            }
                         }
+                        ("merge" | "replace", _) => {}
+                        (other, _) => return Err(format!("unsupported update mode: {}", other)),
                     }
                     self.push_audit(&session_id, &action_id, action_type, dry_run);
                     applied += 1;
-                    return Ok(());
+                    return Ok(ActionEffect::affecting(vec![target_id]));
                 }
-                if action_type == "deleteWidget" {
-                    let target_id = action
-                        .get("targetId")
-                        .and_then(|x| x.as_i64())
-                        .ok_or_else(|| "missing targetId".to_string())?;
-                    let mut to_delete = HashSet::new();
-                    to_delete.insert(target_id);
-                    let mut expanded = true;
-                    while expanded {
-                        expanded = false;
-                        for w in &working.widgets {
-                            let wid = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-                            let parent = w
-                                .get("parentId")
-                                .and_then(|x| x.as_i64());
-                            if let Some(p) = parent {
-                                if to_delete.contains(&p) && !to_delete.contains(&wid) {
-                                    to_delete.insert(wid);
-                                    expanded = true;
-                                }
-                            }
+                if action_type == "patchWidget" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let ops = action
+                        .get("payload")
+                        .and_then(|x| x.as_array())
+                        .ok_or_else(|| "patchWidget payload must be an array of JSON Patch operations".to_string())?;
+                    let target = working
+                        .widgets
+                        .iter()
+                        .find(|w| w.get("id").and_then(|x| x.as_i64()) == Some(target_id))
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    // 在副本上逐条执行，任何一条失败（含 test 不通过）整条动作作废
+                    let mut patched = target.clone();
+                    for (i, op) in ops.iter().enumerate() {
+                        // id 由引擎分配，patch 不能删改或挪走它
+                        let kind = get_str(op, "op").unwrap_or("");
+                        let on_id = |k: &str| get_str(op, k).is_some_and(|p| p.split('/').nth(1) == Some("id"));
+                        if kind != "test" && (on_id("path") || (kind == "move" && on_id("from"))) {
+                            return Err(format!("patch op #{} failed: id is read-only", i));
                         }
+                        apply_json_patch_op(&mut patched, op)
+                            .map_err(|e| format!("patch op #{} failed: {}", i, e))?;
                     }
-                    if to_delete.len() > 10 && !options.allow_dangerous {
-                        return Err("dangerous action blocked: deleteWidget affects more than 10 widgets".into());
+                    // 只校验被改动的顶层字段；被删除的字段按 null 校验
+                    let empty = serde_json::Map::new();
+                    let (before, after) = (
+                        target.as_object().unwrap_or(&empty),
+                        patched.as_object().unwrap_or(&empty),
+                    );
+                    let changed: serde_json::Map<String, serde_json::Value> = before
+                        .keys()
+                        .chain(after.keys())
+                        .filter(|k| before.get(*k) != after.get(*k))
+                        .map(|k| (k.clone(), after.get(k).cloned().unwrap_or(serde_json::Value::Null)))
+                        .collect();
+                    widget_types::check_props(get_str(&patched, "type").unwrap_or(""), &changed)?;
+                    if let Some(p) = changed.get("parentId") {
+                        check_reparent(&working.widgets, target_id, p.as_i64())?;
                     }
-                    working.widgets.retain(|w| {
+                    let target = working
+                        .widgets
+                        .iter_mut()
+                        .find(|w| get_i64(w, "id", 0) == target_id)
+                        .expect("target found above");
+                    *target = patched;
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(vec![target_id]));
+                }
+                if action_type == "updateWhere" {
+                    let selector: WidgetQuery = serde_json::from_value(
+                        action.get("selector").cloned().unwrap_or(json!({})),
+                    )
+                    .map_err(|e| format!("invalid selector: {}", e))?;
+                    let matched: Vec<i64> = select_widgets(&working.widgets, &selector)?
+                        .iter()
+                        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+                        .collect();
+                    if matched.len() > DANGEROUS_WIDGET_COUNT && !options.allow_dangerous {
+                        return Err(format!(
+                            "dangerous action blocked: updateWhere matches {} widgets (more than {})",
+                            matched.len(),
+                            DANGEROUS_WIDGET_COUNT
+                        ));
+                    }
+                    let patch = action
+                        .get("payload")
+                        .and_then(|x| x.as_object())
+                        .cloned()
+                        .unwrap_or_default();
+                    for w in working.widgets.iter().filter(|w| matched.contains(&get_i64(w, "id", 0))) {
+                        widget_types::check_props(get_str(w, "type").unwrap_or(""), &patch)
+                            .map_err(|e| format!("widget {}: {}", get_i64(w, "id", 0), e))?;
+                        if let Some(p) = patch.get("parentId") {
+                            check_reparent(&working.widgets, get_i64(w, "id", 0), p.as_i64())?;
+                        }
+                    }
+                    for w in working.widgets.iter_mut() {
                         let wid = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-                        !to_delete.contains(&wid)
-                    });
+                        if !matched.contains(&wid) {
+                            continue;
+                        }
+                        for (k, v) in &patch {
+                            w[k] = v.clone();
+                        }
+                    }
                     self.push_audit(&session_id, &action_id, action_type, dry_run);
                     applied += 1;
-                    return Ok(());
+                    return Ok(ActionEffect::affecting(matched));
                 }
-                if action_type == "setParent" {
-                    let target_id = action
-                        .get("targetId")
-                        .and_then(|x| x.as_i64())
-                        .ok_or_else(|| "missing targetId".to_string())?;
+                if action_type == "duplicateWidget" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let payload = action.get("payload").cloned().unwrap_or(json!({}));
+                    let source = working
+                        .widgets
+                        .iter()
+                        .find(|w| w.get("id").and_then(|x| x.as_i64()) == Some(target_id))
+                        .cloned()
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    let new_parent = match payload.get("parentId") {
+                        None => source.get("parentId").and_then(|x| x.as_i64()),
+                        Some(serde_json::Value::Null) => None,
+                        Some(v) => {
+                            let pid = resolve_widget_ref(&working.widgets, v)?;
+                            if !working.widgets.iter().any(|w| get_i64(w, "id", 0) == pid) {
+                                return Err(format!("parent widget not found: {}", pid));
+                            }
+                            Some(pid)
+                        }
+                    };
+                    let offset = |k: &str| {
+                        payload
+                            .get("offset")
+                            .and_then(|o| o.get(k))
+                            .and_then(|x| x.as_f64())
+                            .unwrap_or(0.0)
+                    };
+                    let (dx, dy) = (offset("x"), offset("y"));
+                    let alias = get_str(&payload, "ref")
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty());
+                    if let Some(a) = &alias {
+                        if refs.contains_key(a) {
+                            return Err(format!("duplicate ref in batch: {}", a));
+                        }
+                    }
+                    let old_root_name = get_str(&source, "name").unwrap_or("").to_string();
+                    let new_root_name = match get_str(&payload, "name").map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                        // 显式给的名字同样要在新父节点下唯一，否则名字路径会有歧义
+                        Some(n) if sibling_names(&working.widgets, new_parent).contains(n) => {
+                            return Err(format!("name already used under the same parent: {}", n));
+                        }
+                        Some(n) => n.to_string(),
+                        None => unique_copy_name(&working.widgets, new_parent, &old_root_name),
+                    };
+
+                    let subtree = collect_subtree(&working.widgets, target_id);
+                    let sources: Vec<serde_json::Value> = working
+                        .widgets
+                        .iter()
+                        .filter(|w| subtree.contains(&get_i64(w, "id", 0)))
+                        .cloned()
+                        .collect();
+                    let mut id_map: HashMap<i64, i64> = HashMap::new();
+                    for w in &sources {
+                        id_map.insert(get_i64(w, "id", 0), next_id);
+                        next_id += 1;
+                    }
+
+                    let mut effect = ActionEffect::default();
+                    for mut copy in sources {
+                        let old_id = get_i64(&copy, "id", 0);
+                        let new_id = id_map[&old_id];
+                        let Some(o) = copy.as_object_mut() else {
+                            continue;
+                        };
+                        o.insert("id".into(), json!(new_id));
+                        let parent = if old_id == target_id {
+                            new_parent
+                        } else {
+                            o.get("parentId")
+                                .and_then(|x| x.as_i64())
+                                .and_then(|p| id_map.get(&p).copied())
+                        };
+                        o.insert("parentId".into(), parent.map_or(serde_json::Value::Null, |p| json!(p)));
+                        for (k, d) in [("x", dx), ("y", dy)] {
+                            if let Some(v) = o.get(k) {
+                                let moved = offset_number(v, d);
+                                o.insert(k.into(), moved);
+                            }
+                        }
+                        // 子节点名字若以 `<原根名>_` 开头（组合控件的 `<name>_label` 等），跟随改成新根名；
+                        // `buyer_x` 这类只是字面上以 `buy` 开头的不算
+                        let renamed = if old_id == target_id {
+                            Some(new_root_name.clone())
+                        } else {
+                            o.get("name")
+                                .and_then(|x| x.as_str())
+                                .filter(|_| !old_root_name.is_empty())
+                                .and_then(|n| n.strip_prefix(old_root_name.as_str()))
+                                .filter(|rest| rest.is_empty() || rest.starts_with('_'))
+                                .map(|rest| format!("{}{}", new_root_name, rest))
+                        };
+                        if let Some(n) = renamed {
+                            o.insert("name".into(), json!(n));
+                        }
+                        let role = if old_id == target_id {
+                            "root".to_string()
+                        } else {
+                            format!("src_{}", old_id)
+                        };
+                        effect.created.push(CreatedWidget { id: new_id, role });
+                        working.widgets.push(copy);
+                    }
+
+                    // 挂在被复制控件上的动画一并复制，重新分配动画 id
+                    let anim_copies: Vec<serde_json::Value> = working
+                        .animations
+                        .iter()
+                        .filter_map(|a| {
+                            let wid = a.get("widgetId").and_then(|x| x.as_i64())?;
+                            let new_wid = *id_map.get(&wid)?;
+                            let mut c = a.clone();
+                            c["widgetId"] = json!(new_wid);
+                            Some(c)
+                        })
+                        .collect();
+                    for mut a in anim_copies {
+                        a["id"] = json!(working.next_anim_id);
+                        working.next_anim_id += 1;
+                        working.animations.push(a);
+                    }
+
+                    if let Some(a) = &alias {
+                        register_created_refs(&mut refs, a, &effect.created);
+                    }
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(effect);
+                }
+                if action_type == "lock" || action_type == "unlock" {
+                    let target_id = target_id_of(action, &working.widgets)?;
                     let target = working
                         .widgets
                         .iter_mut()
+                        .find(|w| get_i64(w, "id", 0) == target_id)
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    target["locked"] = json!(action_type == "lock");
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(vec![target_id]));
+                }
+                if matches!(action_type, "bringToFront" | "sendToBack" | "moveUp" | "moveDown") {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let target = working
+                        .widgets
+                        .iter()
                         .find(|w| w.get("id").and_then(|x| x.as_i64()) == Some(target_id))
                         .ok_or_else(|| format!("widget not found: {}", target_id))?;
-                    let parent_id = action
+                    let parent = target.get("parentId").and_then(|x| x.as_i64());
+                    let mut order = sibling_ids(&working.widgets, parent);
+                    let pos = order.iter().position(|id| *id == target_id).unwrap_or(0);
+                    match action_type {
+                        "bringToFront" => {
+                            let id = order.remove(pos);
+                            order.push(id);
+                        }
+                        "sendToBack" => {
+                            let id = order.remove(pos);
+                            order.insert(0, id);
+                        }
+                        "moveUp" if pos + 1 < order.len() => order.swap(pos, pos + 1),
+                        "moveDown" if pos > 0 => order.swap(pos, pos - 1),
+                        _ => {}
+                    }
+                    reorder_siblings(&mut working.widgets, &order);
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(vec![target_id]));
+                }
+                if action_type == "reorderChildren" {
+                    // targetId 缺省或为 null 时重排顶层 widget
+                    let parent = match action.get("targetId") {
+                        None | Some(serde_json::Value::Null) => None,
+                        Some(_) => Some(target_id_of(action, &working.widgets)?),
+                    };
+                    let current = sibling_ids(&working.widgets, parent);
+                    let order = action
                         .get("payload")
-                        .and_then(|p| p.get("parentId"))
-                        .cloned()
-                        .unwrap_or(serde_json::Value::Null);
-                    target["parentId"] = parent_id;
+                        .and_then(|p| p.get("order"))
+                        .and_then(|x| x.as_array())
+                        .ok_or_else(|| "reorderChildren requires payload.order array".to_string())?
+                        .iter()
+                        .map(|v| resolve_widget_ref(&working.widgets, v))
+                        .collect::<Result<Vec<i64>, String>>()?;
+                    let listed: HashSet<i64> = order.iter().copied().collect();
+                    if order.len() != current.len()
+                        || listed.len() != order.len()
+                        || !current.iter().all(|id| listed.contains(id))
+                    {
+                        return Err(format!(
+                            "payload.order must list every child exactly once (expected {} ids: {:?})",
+                            current.len(),
+                            current
+                        ));
+                    }
+                    reorder_siblings(&mut working.widgets, &order);
                     self.push_audit(&session_id, &action_id, action_type, dry_run);
                     applied += 1;
-                    return Ok(());
+                    return Ok(ActionEffect::affecting(order));
                 }
-                if action_type == "clearProject" {
-                    working.widgets = vec![];
-                    working.animations = vec![];
+                if action_type == "moveWidget" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let payload = action.get("payload").cloned().unwrap_or(json!({}));
+                    let target = working
+                        .widgets
+                        .iter()
+                        .find(|w| get_i64(w, "id", 0) == target_id)
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    let rect = widget_rect(target);
+                    // x / y 为目标绝对坐标，优先于 dx / dy
+                    let delta = |abs: &str, rel: &str, cur: f64| match payload.get(abs).and_then(|v| v.as_f64()) {
+                        Some(v) => v - cur,
+                        None => payload.get(rel).and_then(|v| v.as_f64()).unwrap_or(0.0),
+                    };
+                    let (dx, dy) = (delta("x", "dx", rect[0]), delta("y", "dy", rect[1]));
+                    let subtree = collect_subtree(&working.widgets, target_id);
+                    let mut affected = Vec::new();
+                    for w in working.widgets.iter_mut() {
+                        let wid = get_i64(w, "id", 0);
+                        if !subtree.contains(&wid) {
+                            continue;
+                        }
+                        for (k, d) in [("x", dx), ("y", dy)] {
+                            let moved = offset_number(w.get(k).unwrap_or(&json!(0)), d);
+                            w[k] = moved;
+                        }
+                        affected.push(wid);
+                    }
                     self.push_audit(&session_id, &action_id, action_type, dry_run);
                     applied += 1;
-                    return Ok(());
+                    return Ok(ActionEffect::affecting(affected));
                 }
-                Err(format!("unsupported action type: {}", action_type))
-            })();
-            if let Err(e) = res {
-                errors.push(e);
-            }
-        }
+                if action_type == "resizeWidget" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let payload = action.get("payload").cloned().unwrap_or(json!({}));
+                    let target = working
+                        .widgets
+                        .iter()
+                        .find(|w| get_i64(w, "id", 0) == target_id)
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    let old = widget_rect(target);
+                    let field = |k: &str, i: usize| payload.get(k).and_then(|v| v.as_f64()).unwrap_or(old[i]);
+                    let new = [field("x", 0), field("y", 1), field("w", 2), field("h", 3)];
+                    if new[2] <= 0.0 || new[3] <= 0.0 {
+                        return Err("resizeWidget requires positive w and h".into());
+                    }
+                    let default_rule = get_str(&payload, "childRule").unwrap_or("keepOffset").to_string();
+                    let mut rules: HashMap<i64, String> = HashMap::new();
+                    if let Some(map) = payload.get("rules").and_then(|x| x.as_object()) {
+                        // 键为后代 id，或相对被缩放控件的名字路径（如 `dlg_btn_1/dlg_btn_1_label`）
+                        for (k, v) in map {
+                            let id = match k.parse::<i64>() {
+                                Ok(id) => id,
+                                Err(_) => resolve_path_from(&working.widgets, Some(target_id), k)?,
+                            };
+                            rules.insert(id, v.as_str().unwrap_or("").to_string());
+                        }
+                    }
 
-        if !dry_run {
+                    let mut rects: HashMap<i64, [f64; 4]> = HashMap::new();
+                    rects.insert(target_id, new);
+                    // 自上而下逐层计算：每个子节点按 rules > 自身 resizeRule > childRule 选规则，
+                    // 相对于父节点的新旧外框换算
+                    let mut queue = vec![(target_id, old, new)];
+                    while let Some((pid, p_old, p_new)) = queue.pop() {
+                        for w in working.widgets.iter() {
+                            if w.get("parentId").and_then(|x| x.as_i64()) != Some(pid) {
+                                continue;
+                            }
+                            let cid = get_i64(w, "id", 0);
+                            // 已算过的节点不再入队：parentId 成环时也能结束
+                            if rects.contains_key(&cid) {
+                                continue;
+                            }
+                            let rule = rules
+                                .get(&cid)
+                                .map(|s| s.as_str())
+                                .or_else(|| get_str(w, "resizeRule").filter(|s| !s.is_empty()))
+                                .unwrap_or(&default_rule);
+                            let c_old = widget_rect(w);
+                            let c_new = resize_child_rect(rule, p_old, p_new, c_old)?;
+                            rects.insert(cid, c_new);
+                            queue.push((cid, c_old, c_new));
+                        }
+                    }
+                    let mut affected = Vec::new();
+                    for w in working.widgets.iter_mut() {
+                        let wid = get_i64(w, "id", 0);
+                        let Some(r) = rects.get(&wid) else {
+                            continue;
+                        };
+                        let before = widget_rect(w);
+                        for (i, k) in ["x", "y", "w", "h"].into_iter().enumerate() {
+                            if r[i] != before[i] {
+                                w[k] = json_number(r[i]);
+                            }
+                        }
+                        affected.push(wid);
+                    }
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(affected));
+                }
+                if action_type == "groupWidgets" {
+                    let payload = action.get("payload").cloned().unwrap_or(json!({}));
+                    let members = payload
+                        .get("ids")
+                        .and_then(|x| x.as_array())
+                        .filter(|a| !a.is_empty())
+                        .ok_or_else(|| "groupWidgets requires non-empty payload.ids array".to_string())?
+                        .iter()
+                        .map(|v| resolve_widget_ref(&working.widgets, v))
+                        .collect::<Result<Vec<i64>, String>>()?;
+                    let mut parent: Option<Option<i64>> = None;
+                    let (mut x0, mut y0, mut x1, mut y1) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+                    for id in &members {
+                        let w = working
+                            .widgets
+                            .iter()
+                            .find(|w| get_i64(w, "id", 0) == *id)
+                            .ok_or_else(|| format!("widget not found: {}", id))?;
+                        let p = w.get("parentId").and_then(|x| x.as_i64());
+                        if parent.is_some_and(|q| q != p) {
+                            return Err("groupWidgets requires all widgets to share the same parent".into());
+                        }
+                        parent = Some(p);
+                        let num = |k: &str| w.get(k).and_then(|x| x.as_f64()).unwrap_or(0.0);
+                        x0 = x0.min(num("x"));
+                        y0 = y0.min(num("y"));
+                        x1 = x1.max(num("x") + num("w"));
+                        y1 = y1.max(num("y") + num("h"));
+                    }
+                    let parent = parent.flatten();
+                    let padding = payload.get("padding").and_then(|x| x.as_f64()).unwrap_or(0.0);
+                    let alias = get_str(&payload, "ref")
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty());
+                    if let Some(a) = &alias {
+                        if refs.contains_key(a) {
+                            return Err(format!("duplicate ref in batch: {}", a));
+                        }
+                    }
+
+                    let group_id = next_id;
+                    next_id += 1;
+                    let mut group = make_default_widget("panel", group_id, parent);
+                    if let Some(o) = group.as_object_mut() {
+                        o.insert("name".into(), json!(format!("group_{}", group_id)));
+                    }
+                    let overrides = payload.get("overrides").cloned().unwrap_or(json!({}));
+                    if let Some(o) = overrides.as_object() {
+                        widget_types::check_props("panel", o)?;
+                    }
+                    shallow_merge(&mut group, overrides);
+                    if let Some(o) = group.as_object_mut() {
+                        o.insert("id".into(), json!(group_id));
+                        o.insert("parentId".into(), parent.map_or(serde_json::Value::Null, |p| json!(p)));
+                        o.insert("x".into(), json_number(x0 - padding));
+                        o.insert("y".into(), json_number(y0 - padding));
+                        o.insert("w".into(), json_number(x1 - x0 + padding * 2.0));
+                        o.insert("h".into(), json_number(y1 - y0 + padding * 2.0));
+                    }
+                    // 容器插在第一个成员之前，成员保持原有相对层级
+                    let at = working
+                        .widgets
+                        .iter()
+                        .position(|w| members.contains(&get_i64(w, "id", 0)))
+                        .unwrap_or(working.widgets.len());
+                    working.widgets.insert(at, group);
+                    for w in working.widgets.iter_mut() {
+                        if members.contains(&get_i64(w, "id", 0)) {
+                            w["parentId"] = json!(group_id);
+                        }
+                    }
+
+                    let created = vec![CreatedWidget {
+                        id: group_id,
+                        role: "root".into(),
+                    }];
+                    if let Some(a) = &alias {
+                        register_created_refs(&mut refs, a, &created);
+                    }
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect {
+                        created,
+                        affected: members,
+                    });
+                }
+                if action_type == "ungroup" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let container = working
+                        .widgets
+                        .iter()
+                        .find(|w| get_i64(w, "id", 0) == target_id)
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    // 只拆纯容器；button / dialog 等组合控件拆开会丢掉语义
+                    let wtype = get_str(container, "type").unwrap_or("");
+                    if wtype != "panel" || get_str(container, "templateKind").is_some_and(|k| !k.is_empty() && k != "Panel") {
+                        return Err(format!("ungroup only supports plain panel containers (widget {} is {})", target_id, wtype));
+                    }
+                    let grandparent = container.get("parentId").cloned().unwrap_or(serde_json::Value::Null);
+                    let children = sibling_ids(&working.widgets, Some(target_id));
+                    for w in working.widgets.iter_mut() {
+                        if children.contains(&get_i64(w, "id", 0)) {
+                            w["parentId"] = grandparent.clone();
+                        }
+                    }
+                    working.widgets.retain(|w| get_i64(w, "id", 0) != target_id);
+                    let mut affected = children;
+                    affected.push(target_id);
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(affected));
+                }
+                if action_type == "deleteWidget" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let to_delete = collect_subtree(&working.widgets, target_id);
+                    if to_delete.len() > DANGEROUS_WIDGET_COUNT && !options.allow_dangerous {
+                        return Err(format!(
+                            "dangerous action blocked: deleteWidget affects more than {} widgets",
+                            DANGEROUS_WIDGET_COUNT
+                        ));
+                    }
+                    let deleted: Vec<i64> = working
+                        .widgets
+                        .iter()
+                        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+                        .filter(|id| to_delete.contains(id))
+                        .collect();
+                    working.widgets.retain(|w| {
+                        let wid = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+                        !to_delete.contains(&wid)
+                    });
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(deleted));
+                }
+                if action_type == "setParent" {
+                    let target_id = target_id_of(action, &working.widgets)?;
+                    let parent_id = match action.get("payload").and_then(|p| p.get("parentId")) {
+                        None | Some(serde_json::Value::Null) => serde_json::Value::Null,
+                        Some(v) => json!(resolve_widget_ref(&working.widgets, v)?),
+                    };
+                    check_reparent(&working.widgets, target_id, parent_id.as_i64())?;
+                    let target = working
+                        .widgets
+                        .iter_mut()
+                        .find(|w| w.get("id").and_then(|x| x.as_i64()) == Some(target_id))
+                        .ok_or_else(|| format!("widget not found: {}", target_id))?;
+                    target["parentId"] = parent_id;
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(vec![target_id]));
+                }
+                if action_type == "clearProject" {
+                    let removed: Vec<i64> = working
+                        .widgets
+                        .iter()
+                        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+                        .collect();
+                    working.widgets = vec![];
+                    working.animations = vec![];
+                    self.push_audit(&session_id, &action_id, action_type, dry_run);
+                    applied += 1;
+                    return Ok(ActionEffect::affecting(removed));
+                }
+                Err(format!("unsupported action type: {}", action_type))
+            })();
+            match res {
+                Ok(effect) => {
+                    let result = ActionResult {
+                        action_id,
+                        action_type: action_type.to_string(),
+                        status: "applied".into(),
+                        error: None,
+                        created: effect.created,
+                        affected: effect.affected,
+                    };
+                    if let (Some(key), false) = (idempotency_key, dry_run) {
+                        self.idempotency.insert(key, result.clone());
+                    }
+                    results.push(result);
+                }
+                Err(e) => {
+                    let status = if refusal.is_some() { "refused" } else { "failed" };
+                    results.push(ActionResult {
+                        action_id,
+                        action_type: action_type.to_string(),
+                        status: status.into(),
+                        error: Some(e.clone()),
+                        created: Vec::new(),
+                        affected: Vec::new(),
+                    });
+                    errors.push(e);
+                }
+            }
+        }
+
+        // dry-run 与真实执行都基于同一份 working 做前后对比，保证预演看到的就是落地后的差异
+        let changes = diff_widgets(&self.project.widgets, &working.widgets);
+        if !dry_run {
             self.project = working;
         }
         let elapsed_ms = start.elapsed().as_millis() as u64;
@@ -835,6 +1632,9 @@ impl ProjectEngine {
             dry_run,
             elapsed_ms,
             session_id,
+            changes,
+            results,
+            replayed,
         }
     }
 
@@ -848,7 +1648,27 @@ impl ProjectEngine {
         });
     }
 
-    pub fn validate(&self) -> ValidateResult {
+    /// 校验项目。除结构与资源检查外，`font` 必须是内置字体或 `roots` 某个库根下的字体文件。
+    pub fn validate(&self, roots: impl Into<LibraryRoots>) -> ValidateResult {
+        let library = library_fonts(&roots.into());
+        let mut diagnostics = self.diagnostics();
+        for w in &self.project.widgets {
+            if let Some(font) = get_str(w, "font").filter(|v| !v.is_empty() && !font_known(v, &library)) {
+                diagnostics.push(format!(
+                    "{}: unknown font {} (not a builtin font or a font in the global library)",
+                    get_str(w, "name").unwrap_or(""),
+                    font
+                ));
+            }
+        }
+        ValidateResult {
+            ok: diagnostics.is_empty(),
+            diagnostics,
+        }
+    }
+
+    /// 不依赖库根的那部分校验；快照与结构化导出里附带的 diagnostics 只用这部分。
+    fn diagnostics(&self) -> Vec<String> {
         let mut diagnostics = Vec::new();
         let mut by_parent: HashMap<String, HashMap<String, usize>> = HashMap::new();
         for w in &self.project.widgets {
@@ -874,30 +1694,28 @@ impl ProjectEngine {
                 }
             }
         }
-        // schema 2.0.0：widget.image 是"绝对路径引用"，不再有独立登记表需要比对。
-        // 只检查"image 是绝对路径但文件缺失"的情况，方便 AI 及早发现漂移。
-        let mut missing: Vec<String> = Vec::new();
         for w in &self.project.widgets {
-            for field in ["image", "clickImage", "hoverImage"] {
-                let v = w.get(field).and_then(|x| x.as_str()).unwrap_or("");
-                if v.is_empty() || !is_absolute_path(v) { continue; }
-                if !Path::new(v).is_file() {
-                    missing.push(v.to_string());
-                }
+            diagnostics.extend(widget_types::check_widget(w));
+        }
+        // schema 2.0.0：资源字段是"绝对路径引用"，不再有独立登记表需要比对。
+        // 只检查"是绝对路径但文件缺失"与"预设键不存在"，方便 AI 及早发现漂移。
+        let mut missing: Vec<String> = Vec::new();
+        for r in resource_fields::collect(&self.project.widgets, &self.project.settings) {
+            if r.unknown_preset {
+                diagnostics.push(format!("{}: unknown {} {}", r.widget_name, r.field.path, r.value));
+            } else if r.is_file && is_absolute_path(&r.value) && !Path::new(&r.value).is_file() && !missing.contains(&r.value) {
+                missing.push(r.value);
             }
         }
         if !missing.is_empty() {
             let preview: Vec<&str> = missing.iter().take(3).map(|s| s.as_str()).collect();
             diagnostics.push(format!(
-                "missing image files: {} (e.g. {})",
+                "missing resource files: {} (e.g. {})",
                 missing.len(),
                 preview.join(", ")
             ));
         }
-        ValidateResult {
-            ok: diagnostics.is_empty(),
-            diagnostics,
-        }
+        diagnostics
     }
 
     pub fn export_structured_json(&self) -> String {
@@ -929,7 +1747,7 @@ impl ProjectEngine {
                 "tree": tree
             },
             "animations": self.project.animations,
-            "diagnostics": self.validate().diagnostics
+            "diagnostics": self.diagnostics()
         });
         serde_json::to_string_pretty(&payload).unwrap_or_else(|_| "{}".into())
     }
@@ -1016,6 +1834,27 @@ impl ProjectEngine {
                     lua.push_str(&format!("        image = [[{}]],\n", escape_lua_text(im)));
                 }
             }
+            if let Some(font) = w.get("font").and_then(|x| x.as_str()) {
+                if !font.is_empty() {
+                    lua.push_str(&format!("        font = [[{}]],\n", escape_lua_text(font)));
+                }
+            }
+            if let Some(frame_type) = widget_types::type_spec(wtype).and_then(|t| t.frame_type) {
+                lua.push_str(&format!("        frame_type = '{}',\n", frame_type));
+                for field in lua_frame_fields(frame_type) {
+                    if let Some(v) = w.get(*field).and_then(lua_literal) {
+                        lua.push_str(&format!("        {} = {},\n", snake_case(field), v));
+                    }
+                }
+            }
+            // 同级多于一个时带上层级（兄弟间的绘制顺序，0 在最下），由 Frame 框架设置 frame level
+            let parent = w.get("parentId").and_then(|x| x.as_i64());
+            let siblings = sibling_ids(&self.project.widgets, parent);
+            if siblings.len() > 1 {
+                let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+                let level = siblings.iter().position(|s| *s == id).unwrap_or(0);
+                lua.push_str(&format!("        level = {},\n", level));
+            }
             lua.push_str("    })\n");
         }
         lua.push_str("end\n");
@@ -1077,6 +1916,105 @@ impl ProjectEngine {
     }
 }
 
+/// `ProjectEngine::query` 的筛选条件；所有字段可选，之间为 AND。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WidgetQuery {
+    /// 精确匹配 `type`。
+    #[serde(rename = "type")]
+    pub widget_type: Option<String>,
+    /// `name` 通配（`*` 任意串、`?` 单字符，ASCII 大小写不敏感）。
+    pub name: Option<String>,
+    /// 只在该 widget（id 或名字路径）及其后代里查找。
+    pub root: Option<serde_json::Value>,
+    pub template_kind: Option<String>,
+    /// 与给定矩形相交（边缘相接不算）。
+    pub intersects: Option<QueryRect>,
+    /// 字段谓词，例如 `{ "field": "fontSize", "op": "gt", "value": 20 }`。
+    pub r#where: Vec<WidgetPredicate>,
+    /// 字段投影：只返回这些字段（`id` 总会带上，支持 `padding.top` 形式的嵌套路径）。
+    pub fields: Option<Vec<String>>,
+    /// 最多返回多少条，默认 [`DEFAULT_QUERY_LIMIT`]。
+    pub limit: Option<usize>,
+}
+
+pub const DEFAULT_QUERY_LIMIT: usize = 50;
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct QueryRect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl QueryRect {
+    fn of_widget(w: &serde_json::Value) -> Self {
+        let num = |k: &str| w.get(k).and_then(|v| v.as_f64()).unwrap_or(0.0);
+        Self {
+            x: num("x"),
+            y: num("y"),
+            w: num("w"),
+            h: num("h"),
+        }
+    }
+
+    fn intersects(&self, o: &QueryRect) -> bool {
+        self.x < o.x + o.w && o.x < self.x + self.w && self.y < o.y + o.h && o.y < self.y + self.h
+    }
+}
+
+const PREDICATE_OPS: &[&str] = &[
+    "eq", "ne", "gt", "gte", "lt", "lte", "empty", "notEmpty", "contains",
+];
+
+/// 单个字段谓词。`op` 取值见 `PREDICATE_OPS`；`empty` / `notEmpty` 不需要 `value`，
+/// 缺失字段、`null`、空字符串 / 数组 / 对象都算 empty。
+#[derive(Debug, Clone, Deserialize)]
+pub struct WidgetPredicate {
+    pub field: String,
+    pub op: String,
+    #[serde(default)]
+    pub value: serde_json::Value,
+}
+
+impl WidgetPredicate {
+    fn matches(&self, w: &serde_json::Value) -> bool {
+        let actual = get_path(w, &self.field);
+        let is_empty = match actual {
+            None | Some(serde_json::Value::Null) => true,
+            Some(serde_json::Value::String(s)) => s.is_empty(),
+            Some(serde_json::Value::Array(a)) => a.is_empty(),
+            Some(serde_json::Value::Object(o)) => o.is_empty(),
+            Some(_) => false,
+        };
+        let cmp = || {
+            let a = actual?.as_f64()?;
+            let b = self.value.as_f64()?;
+            a.partial_cmp(&b)
+        };
+        use std::cmp::Ordering::*;
+        match self.op.as_str() {
+            "eq" => actual == Some(&self.value),
+            "ne" => actual != Some(&self.value),
+            "gt" => cmp() == Some(Greater),
+            "gte" => matches!(cmp(), Some(Greater | Equal)),
+            "lt" => cmp() == Some(Less),
+            "lte" => matches!(cmp(), Some(Less | Equal)),
+            "empty" => is_empty,
+            "notEmpty" => !is_empty,
+            "contains" => match (actual.and_then(|v| v.as_str()), self.value.as_str()) {
+                (Some(a), Some(b)) => a.contains(b),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+/// 单条动作影响的 widget 数超过该值即视为危险动作，需 `allow_dangerous`。
+const DANGEROUS_WIDGET_COUNT: usize = 10;
+
 pub struct ApplyOptions {
     pub dry_run: bool,
     pub session_id: Option<String>,
@@ -1092,6 +2030,108 @@ pub struct ApplyResult {
     pub dry_run: bool,
     pub elapsed_ms: u64,
     pub session_id: String,
+    /// 本批动作对 widgets 造成的变更（dry-run 时为"将会发生"的变更）。
+    pub changes: ChangeSet,
+    /// 与入参 `actions` 一一对应、保持顺序的逐条结果。
+    pub results: Vec<ActionResult>,
+    /// 命中幂等缓存、未重复执行的动作数（对应 `results[].status == "replayed"`）。
+    pub replayed: usize,
+}
+
+/// 单条动作的执行结果。`status` 为 `applied` / `failed` / `replayed`；
+/// `replayed` 时 `created` / `affected` 是首次执行时的结果。
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ActionResult {
+    pub action_id: String,
+    #[serde(rename = "type")]
+    pub action_type: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 本动作新建的 widget（组合控件按创建顺序列出每个子节点）。
+    pub created: Vec<CreatedWidget>,
+    /// 本动作修改 / 删除 / 重挂的已有 widget id。
+    pub affected: Vec<i64>,
+}
+
+/// `role` 标识 widget 在组合控件里的角色：根节点为 `root`，
+/// 其余如 `label` / `title` / `btn_1` / `btn_1_label`。
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CreatedWidget {
+    pub id: i64,
+    pub role: String,
+}
+
+/// 最近成功执行过的 idempotencyKey / actionId -> 首次结果，按插入顺序淘汰。
+/// 随项目切换（open / import）清空，dry-run 不写入。
+#[derive(Default)]
+struct IdempotencyCache {
+    order: VecDeque<String>,
+    results: HashMap<String, ActionResult>,
+}
+
+const IDEMPOTENCY_CACHE_CAPACITY: usize = 512;
+
+impl IdempotencyCache {
+    fn get(&self, key: &str) -> Option<&ActionResult> {
+        self.results.get(key)
+    }
+
+    fn insert(&mut self, key: String, result: ActionResult) {
+        if self.results.insert(key.clone(), result).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > IDEMPOTENCY_CACHE_CAPACITY {
+            if let Some(old) = self.order.pop_front() {
+                self.results.remove(&old);
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        self.order.clear();
+        self.results.clear();
+    }
+}
+
+/// 动作闭包内部的产出，由 `apply_actions` 包装成 [`ActionResult`]。
+#[derive(Debug, Default)]
+struct ActionEffect {
+    created: Vec<CreatedWidget>,
+    affected: Vec<i64>,
+}
+
+impl ActionEffect {
+    fn affecting(ids: Vec<i64>) -> Self {
+        Self {
+            created: Vec::new(),
+            affected: ids,
+        }
+    }
+}
+
+/// `apply_actions` 前后 widgets 的差异：新建 / 删除（含级联子节点）/ 字段级修改。
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ChangeSet {
+    pub created: Vec<i64>,
+    pub deleted: Vec<i64>,
+    pub updated: Vec<WidgetChange>,
+    /// 在兄弟节点之间层级（绘制顺序）发生变化的 widget
+    pub reordered: Vec<i64>,
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WidgetChange {
+    pub id: i64,
+    pub fields: Vec<FieldChange>,
+}
+
+/// 单个顶层字段的新旧值；字段新增时 `before` 为 null，被移除时 `after` 为 null。
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -1112,104 +2152,818 @@ fn shallow_merge(base: &mut serde_json::Value, patch: serde_json::Value) {
     }
 }
 
-/// 构造一条默认 widget（与前端 `useWidgets.buildWidget` 的语义对齐）。
-/// 不处理 `overrides` 合并，调用方负责。
-fn make_default_widget(
-    widget_type: &str,
-    id: i64,
-    parent_id: Option<i64>,
-) -> serde_json::Value {
-    let default_text = match widget_type {
-        "text" => "文本",
-        "button" => "按钮",
-        _ => "",
+/// 按 [`WidgetQuery`] 的筛选条件（忽略 `fields` / `limit`）挑出 widgets，保持数组顺序。
+/// `query` 与 `updateWhere` 共用。
+fn select_widgets<'a>(
+    widgets: &'a [serde_json::Value],
+    q: &WidgetQuery,
+) -> Result<Vec<&'a serde_json::Value>, String> {
+    let subtree = match &q.root {
+        Some(r) => Some(collect_subtree(widgets, resolve_widget_ref(widgets, r)?)),
+        None => None,
     };
-    let parent_val = match parent_id {
-        Some(p) => json!(p),
-        None => serde_json::Value::Null,
+    for p in &q.r#where {
+        if !PREDICATE_OPS.contains(&p.op.as_str()) {
+            return Err(format!("unsupported predicate op: {}", p.op));
+        }
+    }
+    let mut out = Vec::new();
+    for w in widgets {
+        let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+        if let Some(set) = &subtree {
+            if !set.contains(&id) {
+                continue;
+            }
+        }
+        if let Some(t) = &q.widget_type {
+            if get_str(w, "type") != Some(t.as_str()) {
+                continue;
+            }
+        }
+        if let Some(k) = &q.template_kind {
+            if get_str(w, "templateKind") != Some(k.as_str()) {
+                continue;
+            }
+        }
+        if let Some(pattern) = &q.name {
+            if !glob_match(pattern, get_str(w, "name").unwrap_or("")) {
+                continue;
+            }
+        }
+        if let Some(r) = &q.intersects {
+            if !r.intersects(&QueryRect::of_widget(w)) {
+                continue;
+            }
+        }
+        if !q.r#where.iter().all(|p| p.matches(w)) {
+            continue;
+        }
+        out.push(w);
+    }
+    Ok(out)
+}
+
+/// RFC 7396 JSON Merge Patch：对象逐键递归合并，`null` 删除键，非对象整体替换。
+fn merge_patch(target: &mut serde_json::Value, patch: &serde_json::Value) {
+    let serde_json::Value::Object(p) = patch else {
+        *target = patch.clone();
+        return;
     };
-    json!({
-        "id": id,
-        "name": format!("{}_{}", widget_type, id),
-        "type": widget_type,
-        "parentId": parent_val,
-        "x": 960,
-        "y": 540,
-        "w": 100,
-        "h": 100,
-        "enable": true,
-        "visible": true,
-        "locked": false,
-        "font": "",
-        "fontSize": 14,
-        "outlineSize": 0,
-        "textAlignH": "left",
-        "textAlignV": "top",
-        "text": default_text,
-        "image": "",
-        "clickImage": "",
-        "hoverImage": "",
-        "draggable": false,
-        "checked": false,
-        "selectedIndex": 0,
-    })
+    if !target.is_object() {
+        *target = json!({});
+    }
+    let t = target.as_object_mut().expect("ensured object above");
+    for (k, v) in p {
+        if v.is_null() {
+            t.remove(k);
+        } else {
+            merge_patch(t.entry(k.clone()).or_insert(serde_json::Value::Null), v);
+        }
+    }
 }
 
-fn get_i64(v: &serde_json::Value, k: &str, default: i64) -> i64 {
-    v.get(k).and_then(|x| x.as_i64()).unwrap_or(default)
+/// 解析 JSON Pointer（RFC 6901）。空指针（整份文档）不允许——patch 只针对 widget 内部字段。
+fn parse_json_pointer(ptr: &str) -> Result<Vec<String>, String> {
+    let rest = ptr
+        .strip_prefix('/')
+        .ok_or_else(|| format!("invalid JSON pointer: {:?}", ptr))?;
+    Ok(rest
+        .split('/')
+        .map(|s| s.replace("~1", "/").replace("~0", "~"))
+        .collect())
 }
 
-fn get_str<'a>(v: &'a serde_json::Value, k: &str) -> Option<&'a str> {
-    v.get(k).and_then(|x| x.as_str())
+fn pointer_parent_mut<'a>(
+    doc: &'a mut serde_json::Value,
+    tokens: &[String],
+) -> Result<&'a mut serde_json::Value, String> {
+    let mut cur = doc;
+    for t in tokens {
+        cur = match cur {
+            serde_json::Value::Object(o) => o.get_mut(t),
+            serde_json::Value::Array(a) => t.parse::<usize>().ok().and_then(|i| a.get_mut(i)),
+            _ => None,
+        }
+        .ok_or_else(|| format!("path segment not found: {}", t))?;
+    }
+    Ok(cur)
 }
 
-/// 按照 `widgetType` 生成一条或一组 widgets（组合控件时带上子节点），
-/// 与前端 [`useWidgets.addWidget`](src/composables/useWidgets.ts) 行为完全一致：
-/// - `button` 追加一个 `type='text'` 的居中 label 子节点
-/// - `dialog` 展开成 `panel + title(text) + 两个 button + 两个 label`
-/// - 其他 type 只返回单个 widget
-///
-/// `next_id` 是进出参数（按引用自增），保证批量调用时 id 不冲突。
-fn build_composite_widgets(
-    widget_type: &str,
-    overrides: &serde_json::Value,
-    next_id: &mut i64,
-) -> Vec<serde_json::Value> {
-    match widget_type {
-        "dialog" => build_dialog_tree(overrides, next_id),
-        "button" => build_button_tree(overrides, next_id),
-        _ => {
-            let id = *next_id;
-            *next_id += 1;
-            let mut w = make_default_widget(widget_type, id, None);
-            shallow_merge(&mut w, overrides.clone());
-            // 强制保留 id 不被 overrides 覆盖（冲突隐患）
-            if let Some(o) = w.as_object_mut() {
-                o.insert("id".into(), json!(id));
+fn pointer_get(doc: &serde_json::Value, ptr: &str) -> Result<serde_json::Value, String> {
+    parse_json_pointer(ptr)?;
+    doc.pointer(ptr)
+        .cloned()
+        .ok_or_else(|| format!("path not found: {}", ptr))
+}
+
+fn pointer_add(doc: &mut serde_json::Value, ptr: &str, value: serde_json::Value) -> Result<(), String> {
+    let tokens = parse_json_pointer(ptr)?;
+    let (last, parents) = tokens.split_last().expect("pointer has at least one token");
+    match pointer_parent_mut(doc, parents)? {
+        serde_json::Value::Object(o) => {
+            o.insert(last.clone(), value);
+            Ok(())
+        }
+        serde_json::Value::Array(a) => {
+            let idx = if last == "-" {
+                a.len()
+            } else {
+                last.parse::<usize>()
+                    .map_err(|_| format!("invalid array index: {}", last))?
+            };
+            if idx > a.len() {
+                return Err(format!("array index out of bounds: {}", idx));
             }
-            vec![w]
+            a.insert(idx, value);
+            Ok(())
         }
+        _ => Err(format!("cannot add into a scalar at {}", ptr)),
     }
 }
 
-fn build_button_tree(
-    overrides: &serde_json::Value,
-    next_id: &mut i64,
-) -> Vec<serde_json::Value> {
-    let btn_id = *next_id;
-    *next_id += 1;
-    let label_id = *next_id;
-    *next_id += 1;
+fn pointer_remove(doc: &mut serde_json::Value, ptr: &str) -> Result<serde_json::Value, String> {
+    let tokens = parse_json_pointer(ptr)?;
+    let (last, parents) = tokens.split_last().expect("pointer has at least one token");
+    let removed = match pointer_parent_mut(doc, parents)? {
+        serde_json::Value::Object(o) => o.remove(last),
+        serde_json::Value::Array(a) => match last.parse::<usize>() {
+            Ok(i) if i < a.len() => Some(a.remove(i)),
+            _ => None,
+        },
+        _ => None,
+    };
+    removed.ok_or_else(|| format!("path not found: {}", ptr))
+}
 
-    let mut btn = make_default_widget("button", btn_id, None);
-    shallow_merge(&mut btn, overrides.clone());
-    if let Some(o) = btn.as_object_mut() {
-        o.insert("id".into(), json!(btn_id));
-        o.insert("type".into(), json!("button"));
-        // parentId 允许通过 overrides 设定外层 parent，否则保持 null
+/// 执行一条 RFC 6902 操作：add / remove / replace / move / copy / test。
+fn apply_json_patch_op(doc: &mut serde_json::Value, op: &serde_json::Value) -> Result<(), String> {
+    let kind = op.get("op").and_then(|x| x.as_str()).unwrap_or("");
+    let path = op
+        .get("path")
+        .and_then(|x| x.as_str())
+        .ok_or_else(|| "missing path".to_string())?;
+    let value = || {
+        op.get("value")
+            .cloned()
+            .ok_or_else(|| format!("{} requires value", kind))
+    };
+    let from = || {
+        op.get("from")
+            .and_then(|x| x.as_str())
+            .ok_or_else(|| format!("{} requires from", kind))
+    };
+    match kind {
+        "add" => pointer_add(doc, path, value()?),
+        "remove" => pointer_remove(doc, path).map(|_| ()),
+        "replace" => {
+            pointer_remove(doc, path)?;
+            pointer_add(doc, path, value()?)
+        }
+        "move" => {
+            let from = from()?;
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(format!("cannot move {} into its own child {}", from, path));
+            }
+            let v = pointer_remove(doc, from)?;
+            pointer_add(doc, path, v)
+        }
+        "copy" => {
+            let v = pointer_get(doc, from()?)?;
+            pointer_add(doc, path, v)
+        }
+        "test" => {
+            let actual = pointer_get(doc, path)?;
+            let expected = value()?;
+            if actual == expected {
+                Ok(())
+            } else {
+                Err(format!("test failed at {}: expected {}, got {}", path, expected, actual))
+            }
+        }
+        other => Err(format!("unsupported op: {:?}", other)),
     }
+}
 
-    let bx = get_i64(&btn, "x", 960);
+/// 处于锁定状态的 widget：自身或任一祖先 `locked = true`。
+fn effective_locked(widgets: &[serde_json::Value]) -> HashSet<i64> {
+    let nodes: HashMap<i64, (Option<i64>, bool)> = widgets
+        .iter()
+        .filter_map(|w| {
+            let id = w.get("id").and_then(|x| x.as_i64())?;
+            let locked = w.get("locked").and_then(|x| x.as_bool()).unwrap_or(false);
+            Some((id, (w.get("parentId").and_then(|x| x.as_i64()), locked)))
+        })
+        .collect();
+    nodes
+        .keys()
+        .copied()
+        .filter(|id| {
+            let mut cur = Some(*id);
+            // 深度上限防御 parentId 成环
+            for _ in 0..=nodes.len() {
+                let Some((parent, locked)) = cur.and_then(|c| nodes.get(&c)) else {
+                    return false;
+                };
+                if *locked {
+                    return true;
+                }
+                cur = *parent;
+            }
+            false
+        })
+        .collect()
+}
+
+/// 动作触及锁定 widget 时返回拒绝原因。
+///
+/// 触及范围按动作类型估算：改字段 / 层级只看目标；删除、移动、缩放、拆组看整棵子树；
+/// 往锁定容器里新增或挪入子节点（含改 `parentId` 字段）同样视为修改该容器。
+/// 未列出的动作类型按整棵目标子树计，默认拒绝而不是放行。引用解析失败时不在这里报错，
+/// 交给动作本身给出更具体的错误。
+fn lock_refusal(
+    action_type: &str,
+    action: &serde_json::Value,
+    widgets: &[serde_json::Value],
+    allow_dangerous: bool,
+) -> Option<String> {
+    match action_type {
+        "lock" => return None,
+        "unlock" if !allow_dangerous => {
+            return Some("dangerous action blocked: unlock requires allowDangerous".into())
+        }
+        "unlock" => return None,
+        _ => {}
+    }
+    let force = action.get("force").and_then(|x| x.as_bool()) == Some(true);
+    if force && allow_dangerous {
+        return None;
+    }
+    let locked = effective_locked(widgets);
+    if locked.is_empty() {
+        return None;
+    }
+    let payload = action.get("payload");
+    let target = || target_id_of(action, widgets).ok();
+    let resolve = |v: Option<&serde_json::Value>| v.and_then(|v| resolve_widget_ref(widgets, v).ok());
+    let parent_of = |id: i64| {
+        widgets
+            .iter()
+            .find(|w| get_i64(w, "id", 0) == id)
+            .and_then(|w| w.get("parentId").and_then(|x| x.as_i64()))
+    };
+    let ids_in = |v: Option<&serde_json::Value>| -> Vec<i64> {
+        v.and_then(|x| x.as_array())
+            .map(|a| a.iter().filter_map(|v| resolve(Some(v))).collect())
+            .unwrap_or_default()
+    };
+    // 字段写法改父节点时的新父节点：updateWidgetProps / updateWhere 的 payload.parentId，
+    // patchWidget 里写 `/parentId` 的 add / replace
+    let new_parent = match (action_type, payload) {
+        ("patchWidget", Some(serde_json::Value::Array(ops))) => ops
+            .iter()
+            .filter(|op| get_str(op, "path") == Some("/parentId") && matches!(get_str(op, "op"), Some("add" | "replace")))
+            .filter_map(|op| op.get("value").and_then(|x| x.as_i64()))
+            .collect(),
+        ("patchWidget", _) => Vec::new(),
+        _ => payload.and_then(|p| p.get("parentId")).and_then(|x| x.as_i64()).into_iter().collect::<Vec<i64>>(),
+    };
+    let touched: Vec<i64> = match action_type {
+        "updateWidgetProps" | "patchWidget" => target().into_iter().chain(new_parent).collect(),
+        "bringToFront" | "sendToBack" | "moveUp" | "moveDown" => target().into_iter().collect(),
+        "deleteWidget" | "moveWidget" | "resizeWidget" | "ungroup" => target()
+            .map(|id| collect_subtree(widgets, id).into_iter().collect())
+            .unwrap_or_default(),
+        "setParent" => target()
+            .into_iter()
+            .chain(resolve(payload.and_then(|p| p.get("parentId"))))
+            .collect(),
+        "createWidget" => resolve(payload.and_then(|p| p.get("overrides")).and_then(|o| o.get("parentId")))
+            .into_iter()
+            .collect(),
+        "duplicateWidget" => match payload.and_then(|p| p.get("parentId")) {
+            Some(v) => resolve(Some(v)).into_iter().collect(),
+            None => target().and_then(parent_of).into_iter().collect(),
+        },
+        "groupWidgets" => {
+            let members = ids_in(payload.and_then(|p| p.get("ids")));
+            let parents: Vec<i64> = members.iter().filter_map(|id| parent_of(*id)).collect();
+            members.into_iter().chain(parents).collect()
+        }
+        "reorderChildren" => {
+            let parent = match action.get("targetId") {
+                None | Some(serde_json::Value::Null) => None,
+                Some(_) => target(),
+            };
+            parent
+                .into_iter()
+                .chain(ids_in(payload.and_then(|p| p.get("order"))))
+                .collect()
+        }
+        "updateWhere" => serde_json::from_value::<WidgetQuery>(action.get("selector").cloned().unwrap_or(json!({})))
+            .ok()
+            .and_then(|q| select_widgets(widgets, &q).ok())
+            .map(|ws| ws.iter().map(|w| get_i64(w, "id", 0)).collect::<Vec<i64>>())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(new_parent)
+            .collect(),
+        "clearProject" => locked.iter().copied().collect(),
+        _ => target()
+            .map(|id| collect_subtree(widgets, id).into_iter().collect::<Vec<i64>>())
+            .unwrap_or_default()
+            .into_iter()
+            .chain(new_parent)
+            .collect(),
+    };
+    touched.into_iter().find(|id| locked.contains(id)).map(|id| {
+        format!(
+            "widget {} is locked; pass force: true with allowDangerous to modify it",
+            id
+        )
+    })
+}
+
+/// `parent` 的直接子节点 id，按数组顺序（即绘制顺序，越靠后越在上层）。
+fn sibling_ids(widgets: &[serde_json::Value], parent: Option<i64>) -> Vec<i64> {
+    widgets
+        .iter()
+        .filter(|w| w.get("parentId").and_then(|x| x.as_i64()) == parent)
+        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+        .collect()
+}
+
+/// 按 `order` 重排一组兄弟节点。每个节点连同其后代作为一个整体挪动，
+/// 整体插回原先第一个被挪动元素的位置，其余 widget 的相对顺序保持不变。
+fn reorder_siblings(widgets: &mut Vec<serde_json::Value>, order: &[i64]) {
+    let blocks: Vec<HashSet<i64>> = order.iter().map(|id| collect_subtree(widgets, *id)).collect();
+    let moving: HashSet<i64> = blocks.iter().flatten().copied().collect();
+    let insert_at = widgets
+        .iter()
+        .position(|w| moving.contains(&get_i64(w, "id", 0)))
+        .unwrap_or(widgets.len());
+    let (moved, mut rest): (Vec<serde_json::Value>, Vec<serde_json::Value>) = std::mem::take(widgets)
+        .into_iter()
+        .partition(|w| moving.contains(&get_i64(w, "id", 0)));
+    let ordered: Vec<serde_json::Value> = blocks
+        .iter()
+        .flat_map(|b| moved.iter().filter(|w| b.contains(&get_i64(w, "id", 0))).cloned())
+        .collect();
+    let tail = rest.split_off(insert_at.min(rest.len()));
+    rest.extend(ordered);
+    rest.extend(tail);
+    *widgets = rest;
+}
+
+/// 为副本生成在 `parent` 下不重名的名字：`<base>_copy`、`<base>_copy2`……
+fn unique_copy_name(widgets: &[serde_json::Value], parent: Option<i64>, base: &str) -> String {
+    let siblings = sibling_names(widgets, parent);
+    let base = if base.is_empty() { "widget" } else { base };
+    let mut n = 1;
+    loop {
+        let cand = if n == 1 {
+            format!("{}_copy", base)
+        } else {
+            format!("{}_copy{}", base, n)
+        };
+        if !siblings.contains(cand.as_str()) {
+            return cand;
+        }
+        n += 1;
+    }
+}
+
+/// `parent` 下已有的子节点名字（去掉首尾空白）。
+fn sibling_names(widgets: &[serde_json::Value], parent: Option<i64>) -> HashSet<&str> {
+    widgets
+        .iter()
+        .filter(|w| w.get("parentId").and_then(|x| x.as_i64()) == parent)
+        .filter_map(|w| get_str(w, "name"))
+        .map(|n| n.trim())
+        .collect()
+}
+
+/// 坐标加偏移；原值是整数且偏移也是整数时保持整数，避免 `960` 变成 `960.0`。
+fn offset_number(v: &serde_json::Value, d: f64) -> serde_json::Value {
+    match (v.as_i64(), d.fract() == 0.0) {
+        (Some(i), true) => json!(i + d as i64),
+        _ => json!(v.as_f64().unwrap_or(0.0) + d),
+    }
+}
+
+/// widget 的 `[x, y, w, h]`（绝对坐标）。
+fn widget_rect(w: &serde_json::Value) -> [f64; 4] {
+    let num = |k: &str| w.get(k).and_then(|x| x.as_f64()).unwrap_or(0.0);
+    [num("x"), num("y"), num("w"), num("h")]
+}
+
+/// 父节点外框从 `p_old` 变为 `p_new` 时，按规则换算子节点外框：
+/// - `keepOffset`：保持相对父节点左上角的偏移，尺寸不变；
+/// - `stretch`：位置与尺寸按父节点缩放比例拉伸；
+/// - `center`：保持相对父节点中心的偏移，尺寸不变。
+fn resize_child_rect(
+    rule: &str,
+    p_old: [f64; 4],
+    p_new: [f64; 4],
+    c: [f64; 4],
+) -> Result<[f64; 4], String> {
+    let axis = |i: usize| -> Result<(f64, f64), String> {
+        let (po, ps, no, ns) = (p_old[i], p_old[i + 2], p_new[i], p_new[i + 2]);
+        Ok(match rule {
+            "keepOffset" => (no + (c[i] - po), c[i + 2]),
+            "stretch" => {
+                let k = if ps > 0.0 { ns / ps } else { 1.0 };
+                (no + (c[i] - po) * k, c[i + 2] * k)
+            }
+            "center" => (no + ns / 2.0 + (c[i] + c[i + 2] / 2.0 - po - ps / 2.0) - c[i + 2] / 2.0, c[i + 2]),
+            other => return Err(format!("unsupported resize rule: {}", other)),
+        })
+    };
+    let (x, w) = axis(0)?;
+    let (y, h) = axis(1)?;
+    Ok([x, y, w, h])
+}
+
+/// 计算出的坐标 / 尺寸：整数值写成整数，其余保留小数。
+fn json_number(v: f64) -> serde_json::Value {
+    if v.fract() == 0.0 && v.abs() < i64::MAX as f64 {
+        json!(v as i64)
+    } else {
+        json!(v)
+    }
+}
+
+/// 把 `id` 挂到 `parent` 下之前的检查：父节点必须存在，且不能是 `id` 自身或其后代，否则 parentId 会成环。
+fn check_reparent(widgets: &[serde_json::Value], id: i64, parent: Option<i64>) -> Result<(), String> {
+    let Some(parent) = parent else {
+        return Ok(());
+    };
+    if !widgets.iter().any(|w| get_i64(w, "id", 0) == parent) {
+        return Err(format!("parent widget not found: {}", parent));
+    }
+    if collect_subtree(widgets, id).contains(&parent) {
+        return Err(format!("cannot move widget {} under {}: parentId would form a cycle", id, parent));
+    }
+    Ok(())
+}
+
+/// `root` 及其所有后代（沿 `parentId` 展开）的 id 集合；`root` 本身总在集合里。
+fn collect_subtree(widgets: &[serde_json::Value], root: i64) -> HashSet<i64> {
+    let mut set = HashSet::new();
+    set.insert(root);
+    let mut expanded = true;
+    while expanded {
+        expanded = false;
+        for w in widgets {
+            let wid = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+            if let Some(p) = w.get("parentId").and_then(|x| x.as_i64()) {
+                if set.contains(&p) && !set.contains(&wid) {
+                    set.insert(wid);
+                    expanded = true;
+                }
+            }
+        }
+    }
+    set
+}
+
+/// 按 `a.b.c` 形式的点路径读取嵌套字段。
+fn get_path<'a>(v: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.')
+        .try_fold(v, |cur, seg| cur.get(seg))
+}
+
+/// 简单通配匹配：`*` 匹配任意串（含空串），`?` 匹配单个字符；ASCII 大小写不敏感。
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let p: Vec<char> = pattern.to_ascii_lowercase().chars().collect();
+    let t: Vec<char> = text.to_ascii_lowercase().chars().collect();
+    let (mut pi, mut ti) = (0usize, 0usize);
+    let mut star: Option<(usize, usize)> = None;
+    while ti < t.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == t[ti]) {
+            pi += 1;
+            ti += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ti));
+            pi += 1;
+        } else if let Some((sp, st)) = star {
+            pi = sp + 1;
+            ti = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|c| *c == '*')
+}
+
+/// 取动作的 `targetId`：数字 id 或名字路径（见 [`resolve_widget_ref`]）。
+fn target_id_of(action: &serde_json::Value, widgets: &[serde_json::Value]) -> Result<i64, String> {
+    let v = action
+        .get("targetId")
+        .ok_or_else(|| "missing targetId".to_string())?;
+    resolve_widget_ref(widgets, v)
+}
+
+/// 把 widget 引用解析成数字 id。
+///
+/// - 数字：原样返回（是否存在由调用方检查，保持 "widget not found" 报错一致）；
+/// - 字符串：按名字路径解析，例如 `shopDialog/shopDialog_btn_1`——首段在顶层
+///   widget 中按 `name` 匹配，后续各段在上一段的直接子节点中匹配。
+///   `validate` 的同级重名规则保证合法项目里每条路径至多命中一个 widget；
+///   若仍命中多个则报 ambiguous。
+pub fn resolve_widget_ref(
+    widgets: &[serde_json::Value],
+    value: &serde_json::Value,
+) -> Result<i64, String> {
+    if let Some(id) = value.as_i64() {
+        return Ok(id);
+    }
+    let Some(path) = value.as_str() else {
+        return Err(format!("widget reference must be an id or a name path: {}", value));
+    };
+    resolve_path_from(widgets, None, path)
+}
+
+/// 从 `start` 的直接子节点开始（`None` 为顶层）逐段按名字解析路径。
+fn resolve_path_from(
+    widgets: &[serde_json::Value],
+    start: Option<i64>,
+    path: &str,
+) -> Result<i64, String> {
+    let segments: Vec<&str> = path
+        .split('/')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if segments.is_empty() {
+        return Err("empty widget path".to_string());
+    }
+    let mut parent = start;
+    for (depth, seg) in segments.iter().enumerate() {
+        let matches: Vec<i64> = widgets
+            .iter()
+            .filter(|w| w.get("parentId").and_then(|x| x.as_i64()) == parent)
+            .filter(|w| w.get("name").and_then(|x| x.as_str()).map(|n| n.trim()) == Some(*seg))
+            .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+            .collect();
+        let walked = segments[..=depth].join("/");
+        match matches.as_slice() {
+            [id] => parent = Some(*id),
+            [] => return Err(format!("widget path not found: {}", walked)),
+            _ => {
+                return Err(format!(
+                    "ambiguous widget path: {} matches {} widgets",
+                    walked,
+                    matches.len()
+                ))
+            }
+        }
+    }
+    parent.ok_or_else(|| format!("widget path not found: {}", path))
+}
+
+/// 登记 createWidget 的批内别名：根节点为 `alias`，其余子节点为 `alias.role`。
+fn register_created_refs(refs: &mut HashMap<String, i64>, alias: &str, created: &[CreatedWidget]) {
+    for c in created {
+        let key = if c.role == "root" {
+            alias.to_string()
+        } else {
+            format!("{}.{}", alias, c.role)
+        };
+        refs.insert(key, c.id);
+    }
+}
+
+/// 把动作里所有 `{"$ref": "alias"}` 替换成本批次已登记的 widget id。
+///
+/// 不限定字段位置：`targetId`、`payload.parentId`、`overrides.parentId`、
+/// 动画的 `widgetId` 等都走同一套替换。未登记的别名报错，整条动作失败。
+fn resolve_batch_refs(
+    value: &serde_json::Value,
+    refs: &HashMap<String, i64>,
+) -> Result<serde_json::Value, String> {
+    match value {
+        serde_json::Value::Object(o) => {
+            if o.len() == 1 {
+                if let Some(r) = o.get("$ref") {
+                    let name = r.as_str().ok_or_else(|| "$ref must be a string".to_string())?;
+                    return refs
+                        .get(name)
+                        .map(|id| json!(id))
+                        .ok_or_else(|| format!("unknown ref: {}", name));
+                }
+            }
+            let mut out = serde_json::Map::with_capacity(o.len());
+            for (k, v) in o {
+                out.insert(k.clone(), resolve_batch_refs(v, refs)?);
+            }
+            Ok(serde_json::Value::Object(out))
+        }
+        serde_json::Value::Array(a) => a
+            .iter()
+            .map(|v| resolve_batch_refs(v, refs))
+            .collect::<Result<Vec<_>, _>>()
+            .map(serde_json::Value::Array),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// 按 id 对比两份 widget 列表，生成 [`ChangeSet`]。
+///
+/// - `created` 按 `after` 中的顺序，`deleted` 按 `before` 中的顺序；
+/// - `updated` 只比较顶层字段（嵌套对象整体作为一个值比较）。
+fn diff_widgets(before: &[serde_json::Value], after: &[serde_json::Value]) -> ChangeSet {
+    let before_by_id: HashMap<i64, &serde_json::Value> = before
+        .iter()
+        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()).map(|id| (id, w)))
+        .collect();
+    let after_ids: HashSet<i64> = after
+        .iter()
+        .filter_map(|w| w.get("id").and_then(|x| x.as_i64()))
+        .collect();
+
+    let mut changes = ChangeSet::default();
+    for w in after {
+        let Some(id) = w.get("id").and_then(|x| x.as_i64()) else {
+            continue;
+        };
+        let Some(old) = before_by_id.get(&id) else {
+            changes.created.push(id);
+            continue;
+        };
+        let empty = serde_json::Map::new();
+        let old_obj = old.as_object().unwrap_or(&empty);
+        let new_obj = w.as_object().unwrap_or(&empty);
+        let mut fields = Vec::new();
+        for (k, v) in new_obj {
+            let prev = old_obj.get(k).cloned().unwrap_or(serde_json::Value::Null);
+            if prev != *v {
+                fields.push(FieldChange {
+                    field: k.clone(),
+                    before: prev,
+                    after: v.clone(),
+                });
+            }
+        }
+        for (k, v) in old_obj {
+            if !new_obj.contains_key(k) {
+                fields.push(FieldChange {
+                    field: k.clone(),
+                    before: v.clone(),
+                    after: serde_json::Value::Null,
+                });
+            }
+        }
+        if !fields.is_empty() {
+            changes.updated.push(WidgetChange { id, fields });
+        }
+    }
+    for w in before {
+        if let Some(id) = w.get("id").and_then(|x| x.as_i64()) {
+            if !after_ids.contains(&id) {
+                changes.deleted.push(id);
+            }
+        }
+    }
+
+    // 层级：只比较前后都在同一 parent 下的兄弟之间的相对顺序，增删节点本身不算重排
+    let parent_of = |list: &[serde_json::Value]| -> HashMap<i64, Option<i64>> {
+        list.iter()
+            .filter_map(|w| {
+                let id = w.get("id").and_then(|x| x.as_i64())?;
+                Some((id, w.get("parentId").and_then(|x| x.as_i64())))
+            })
+            .collect()
+    };
+    let (parents_before, parents_after) = (parent_of(before), parent_of(after));
+    let stayed = |id: &i64| parents_before.get(id).is_some_and(|p| parents_after.get(id) == Some(p));
+    let mut groups: Vec<Option<i64>> = parents_after.values().copied().collect();
+    groups.sort();
+    groups.dedup();
+    for parent in groups {
+        let old: Vec<i64> = sibling_ids(before, parent).into_iter().filter(|id| stayed(id)).collect();
+        let new: Vec<i64> = sibling_ids(after, parent).into_iter().filter(|id| stayed(id)).collect();
+        for (i, id) in new.iter().enumerate() {
+            if old.get(i) != Some(id) {
+                changes.reordered.push(*id);
+            }
+        }
+    }
+    changes.reordered.sort();
+    changes
+}
+
+/// 构造一条默认 widget：字段与默认值来自 [`widget_types`] 注册表
+/// （与前端 `useWidgets.buildWidget` 的语义对齐）。不处理 `overrides` 合并，调用方负责。
+fn make_default_widget(
+    widget_type: &str,
+    id: i64,
+    parent_id: Option<i64>,
+) -> serde_json::Value {
+    let mut w = widget_types::default_fields(widget_type);
+    w.insert("id".into(), json!(id));
+    w.insert("name".into(), json!(format!("{}_{}", widget_type, id)));
+    w.insert("type".into(), json!(widget_type));
+    w.insert("parentId".into(), parent_id.map_or(serde_json::Value::Null, |p| json!(p)));
+    serde_json::Value::Object(w)
+}
+
+fn get_i64(v: &serde_json::Value, k: &str, default: i64) -> i64 {
+    v.get(k).and_then(|x| x.as_i64()).unwrap_or(default)
+}
+
+fn get_str<'a>(v: &'a serde_json::Value, k: &str) -> Option<&'a str> {
+    v.get(k).and_then(|x| x.as_str())
+}
+
+/// 按照 `widgetType` 生成一条或一组 widgets（组合控件时带上子节点），
+/// 与前端 [`useWidgets.addWidget`](src/composables/useWidgets.ts) 行为完全一致：
+/// - `button` 追加一个 `type='text'` 的居中 label 子节点
+/// - `dialog` 展开成 `panel + title(text) + 两个 button + 两个 label`
+/// - 其他 type 只返回单个 widget
+///
+/// `next_id` 是进出参数（按引用自增），保证批量调用时 id 不冲突。
+/// 返回值按创建顺序给出 `(role, widget)`，role 见 [`CreatedWidget`]。
+fn build_composite_widgets(
+    widget_type: &str,
+    overrides: &serde_json::Value,
+    next_id: &mut i64,
+) -> Vec<(String, serde_json::Value)> {
+    match widget_type {
+        "dialog" => build_dialog_tree(overrides, next_id),
+        "button" => build_button_tree(overrides, next_id),
+        "checkbox" => build_checkbox_tree(overrides, next_id),
+        _ => {
+            let id = *next_id;
+            *next_id += 1;
+            let mut w = make_default_widget(widget_type, id, None);
+            shallow_merge(&mut w, overrides.clone());
+            // 强制保留 id 不被 overrides 覆盖（冲突隐患）
+            if let Some(o) = w.as_object_mut() {
+                o.insert("id".into(), json!(id));
+            }
+            vec![("root".to_string(), w)]
+        }
+    }
+}
+
+/// 复选框：勾选框本体 + 右侧说明文字（`type='text'`，`name=${checkbox.name}_label`）。
+fn build_checkbox_tree(
+    overrides: &serde_json::Value,
+    next_id: &mut i64,
+) -> Vec<(String, serde_json::Value)> {
+    let box_id = *next_id;
+    *next_id += 1;
+    let label_id = *next_id;
+    *next_id += 1;
+
+    let mut cb = make_default_widget("checkbox", box_id, None);
+    shallow_merge(&mut cb, overrides.clone());
+    if let Some(o) = cb.as_object_mut() {
+        o.insert("id".into(), json!(box_id));
+        o.insert("type".into(), json!("checkbox"));
+    }
+
+    let bx = get_i64(&cb, "x", 960);
+    let by = get_i64(&cb, "y", 540);
+    let bw = get_i64(&cb, "w", 24);
+    let bh = get_i64(&cb, "h", 24);
+    let cb_name = get_str(&cb, "name").unwrap_or("checkbox").to_string();
+    let cb_text = get_str(&cb, "text").unwrap_or("复选框").to_string();
+
+    let mut label = make_default_widget("text", label_id, Some(box_id));
+    if let Some(o) = label.as_object_mut() {
+        o.insert("x".into(), json!(bx + bw + 8));
+        o.insert("y".into(), json!(by));
+        o.insert("w".into(), json!(160));
+        o.insert("h".into(), json!(bh));
+        o.insert("text".into(), json!(cb_text));
+        o.insert("textAlignV".into(), json!("middle"));
+        o.insert("textColor".into(), json!("FFFFFF"));
+        o.insert("name".into(), json!(format!("{}_label", cb_name)));
+    }
+
+    vec![("root".to_string(), cb), ("label".to_string(), label)]
+}
+
+fn build_button_tree(
+    overrides: &serde_json::Value,
+    next_id: &mut i64,
+) -> Vec<(String, serde_json::Value)> {
+    let btn_id = *next_id;
+    *next_id += 1;
+    let label_id = *next_id;
+    *next_id += 1;
+
+    let mut btn = make_default_widget("button", btn_id, None);
+    shallow_merge(&mut btn, overrides.clone());
+    if let Some(o) = btn.as_object_mut() {
+        o.insert("id".into(), json!(btn_id));
+        o.insert("type".into(), json!("button"));
+        // parentId 允许通过 overrides 设定外层 parent，否则保持 null
+    }
+
+    let bx = get_i64(&btn, "x", 960);
     let by = get_i64(&btn, "y", 540);
     let bw = get_i64(&btn, "w", 100);
     let bh = get_i64(&btn, "h", 100);
@@ -1230,13 +2984,13 @@ fn build_button_tree(
         o.insert("name".into(), json!(format!("{}_label", btn_name)));
     }
 
-    vec![btn, label]
+    vec![("root".to_string(), btn), ("label".to_string(), label)]
 }
 
 fn build_dialog_tree(
     overrides: &serde_json::Value,
     next_id: &mut i64,
-) -> Vec<serde_json::Value> {
+) -> Vec<(String, serde_json::Value)> {
     let panel_id = *next_id;
     *next_id += 1;
 
@@ -1291,7 +3045,7 @@ fn build_dialog_tree(
     let start_x = panel_x + (panel_w - total_w) / 2;
     let btn_y = panel_y + panel_h - btn_h - 16;
 
-    let mut result = vec![panel, title];
+    let mut result = vec![("root".to_string(), panel), ("title".to_string(), title)];
 
     for (i, text) in ["确定", "取消"].iter().enumerate() {
         let idx = i as i64;
@@ -1331,53 +3085,138 @@ fn build_dialog_tree(
             o.insert("name".into(), json!(format!("{}_label", btn_name)));
         }
 
-        result.push(btn);
-        result.push(label);
+        let role = format!("btn_{}", idx + 1);
+        let label_role = format!("{}_label", role);
+        result.push((role, btn));
+        result.push((label_role, label));
     }
 
     result
 }
 
 fn build_widget_tree(widgets: &[serde_json::Value]) -> Vec<serde_json::Value> {
-    let mut node_map: HashMap<i64, serde_json::Value> = HashMap::new();
-    for w in widgets {
+    fn node(widgets: &[serde_json::Value], w: &serde_json::Value, depth: usize) -> serde_json::Value {
         let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
+        // children 按数组顺序排列（即层级从下到上），与画布绘制顺序一致
+        let children: Vec<serde_json::Value> = if depth > widgets.len() {
+            Vec::new()
+        } else {
+            widgets
+                .iter()
+                .filter(|c| c.get("parentId").and_then(|x| x.as_i64()) == Some(id))
+                .map(|c| node(widgets, c, depth + 1))
+                .collect()
+        };
         let mut n = w.clone();
         if let Some(o) = n.as_object_mut() {
-            o.insert("children".into(), json!([]));
+            let frame_type = get_str(w, "type")
+                .and_then(widget_types::type_spec)
+                .and_then(|t| t.frame_type);
+            if let Some(ft) = frame_type {
+                o.insert("frameType".into(), json!(ft));
+            }
+            o.insert("children".into(), json!(children));
         }
-        node_map.insert(id, n);
+        n
     }
-    for w in widgets {
-        let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-        let pid = w.get("parentId");
-        if pid.is_none() || pid == Some(&serde_json::Value::Null) {
-            continue;
+    widgets
+        .iter()
+        .filter(|w| matches!(w.get("parentId"), None | Some(serde_json::Value::Null)))
+        .map(|w| node(widgets, w, 0))
+        .collect()
+}
+
+fn find_tree_node(nodes: &[serde_json::Value], id: i64) -> Option<&serde_json::Value> {
+    for n in nodes {
+        if n.get("id").and_then(|x| x.as_i64()) == Some(id) {
+            return Some(n);
         }
-        let Some(parent_id) = pid.and_then(|x| x.as_i64()) else {
-            continue;
-        };
-        let child = match node_map.get(&id) {
-            Some(c) => c.clone(),
-            None => continue,
-        };
-        if let Some(parent) = node_map.get_mut(&parent_id) {
-            if let Some(arr) = parent.get_mut("children").and_then(|c| c.as_array_mut()) {
-                arr.push(child);
-            }
+        let children = n.get("children").and_then(|c| c.as_array());
+        if let Some(found) = children.and_then(|c| find_tree_node(c, id)) {
+            return Some(found);
         }
     }
-    let mut roots = Vec::new();
-    for w in widgets {
-        let id = w.get("id").and_then(|x| x.as_i64()).unwrap_or(0);
-        let pid = w.get("parentId");
-        if pid.is_none() || pid == Some(&serde_json::Value::Null) {
-            if let Some(n) = node_map.get(&id) {
-                roots.push(n.clone());
+    None
+}
+
+/// outline 里总是单独展示（或不展示）的字段，不参与"与默认值不同"的比较。
+const OUTLINE_FIXED_FIELDS: &[&str] = &[
+    "id", "type", "name", "parentId", "x", "y", "w", "h", "text", "image", "children",
+];
+
+const OUTLINE_TEXT_MAX_CHARS: usize = 40;
+
+fn render_outline_node(
+    node: &serde_json::Value,
+    depth: usize,
+    max_depth: Option<usize>,
+    lines: &mut Vec<String>,
+) {
+    let id = get_i64(node, "id", 0);
+    let wtype = get_str(node, "type").unwrap_or("panel");
+    let num = |k: &str| fmt_outline_number(node.get(k));
+    let mut line = format!(
+        "{}#{} {} {:?} [{},{} {}x{}]",
+        "  ".repeat(depth),
+        id,
+        wtype,
+        get_str(node, "name").unwrap_or(""),
+        num("x"),
+        num("y"),
+        num("w"),
+        num("h"),
+    );
+    if let Some(t) = get_str(node, "text").filter(|t| !t.is_empty()) {
+        let one_line = t.replace(['\r', '\n'], " ");
+        let short: String = one_line.chars().take(OUTLINE_TEXT_MAX_CHARS).collect();
+        let ellipsis = if one_line.chars().count() > OUTLINE_TEXT_MAX_CHARS { "…" } else { "" };
+        line.push_str(&format!(" text={:?}", format!("{}{}", short, ellipsis)));
+    }
+    if let Some(im) = get_str(node, "image").filter(|s| !s.is_empty()) {
+        line.push_str(&format!(" image={}", path_basename(im)));
+    }
+
+    let defaults = make_default_widget(wtype, id, None);
+    if let Some(obj) = node.as_object() {
+        for (k, v) in obj {
+            if OUTLINE_FIXED_FIELDS.contains(&k.as_str()) || defaults.get(k) == Some(v) {
+                continue;
             }
+            let rendered = match v.as_str() {
+                Some(s) if k.ends_with("Image") => path_basename(s),
+                _ => v.to_string(),
+            };
+            line.push_str(&format!(" {}={}", k, rendered));
+        }
+    }
+
+    let children = node
+        .get("children")
+        .and_then(|c| c.as_array())
+        .map(|c| c.as_slice())
+        .unwrap_or(&[]);
+    let expand = !matches!(max_depth, Some(d) if depth >= d);
+    if !expand && !children.is_empty() {
+        line.push_str(&format!(" (+{} children)", children.len()));
+    }
+    lines.push(line);
+    if expand {
+        for c in children {
+            render_outline_node(c, depth + 1, max_depth, lines);
         }
     }
-    roots
+}
+
+fn fmt_outline_number(v: Option<&serde_json::Value>) -> String {
+    match v.and_then(|x| x.as_f64()) {
+        Some(f) if f.fract() == 0.0 => format!("{}", f as i64),
+        Some(f) => format!("{}", f),
+        None => "?".to_string(),
+    }
+}
+
+fn path_basename(s: &str) -> String {
+    s.rsplit(['\\', '/']).next().unwrap_or(s).to_string()
 }
 
 /// `a >= b` 语义化版本比较（逐段数字比较；非数字段按 0 处理；段数不足也按 0 补足）。
@@ -1396,6 +3235,30 @@ fn version_gte(a: &str, b: &str) -> bool {
     true
 }
 
+/// 两个路径字符串是否指向同一文件：忽略正反斜杠与大小写（全局库多在 Windows 上）。
+fn same_path(a: &str, b: &str) -> bool {
+    a.replace('\\', "/").eq_ignore_ascii_case(&b.replace('\\', "/"))
+}
+
+/// 各库根里的 .ttf / .otf；多个根里相对路径相同的只留靠前根里的那份。
+fn library_fonts(roots: &LibraryRoots) -> Vec<font_files::LibraryFont> {
+    let mut library: Vec<font_files::LibraryFont> = Vec::new();
+    for root in roots.roots() {
+        for f in font_files::scan_library_fonts(Path::new(&root.path)) {
+            if !library.iter().any(|o| o.rel_path.eq_ignore_ascii_case(&f.rel_path)) {
+                library.push(f);
+            }
+        }
+    }
+    library
+}
+
+/// `font` 值是内置字体，或指向库里的字体（绝对路径，或尚未换算的相对库根路径）。
+fn font_known(value: &str, library: &[font_files::LibraryFont]) -> bool {
+    font_files::is_builtin_font(value)
+        || library.iter().any(|f| same_path(&f.value, value) || same_path(&f.rel_path, value))
+}
+
 /// 判断字符串是否是"裸绝对路径"。跨平台放宽：
 ///   - Windows 盘符：`C:\foo` / `C:/foo`
 ///   - UNC：`\\server\share`
@@ -1422,10 +3285,53 @@ pub fn is_absolute_path(s: &str) -> bool {
     false
 }
 
+/// `maxLength` -> `max_length`，与前端 Lua 导出插件的键名风格一致。
+fn snake_case(field: &str) -> String {
+    let mut out = String::with_capacity(field.len() + 4);
+    for c in field.chars() {
+        if c.is_ascii_uppercase() {
+            out.push('_');
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// 各原生 frame 类型在 Lua 导出里额外携带的字段（widget 上的字段名）。
+fn lua_frame_fields(frame_type: &str) -> &'static [&'static str] {
+    match frame_type {
+        "EDITBOX" => &["maxLength"],
+        "SLIDER" => &["minValue", "maxValue", "value", "stepSize", "vertical"],
+        "CHECKBOX" => &["checked"],
+        "LISTBOX" | "POPUPMENU" => &["items", "selectedIndex"],
+        "SIMPLESTATUSBAR" => &["minValue", "maxValue", "value", "barTexture", "barColor"],
+        "SPRITE" | "MODEL" => &["model", "cameraIndex"],
+        _ => &[],
+    }
+}
+
+/// JSON 标量 / 字符串数组转 Lua 字面量；字符串一律用长括号。
+fn lua_literal(v: &serde_json::Value) -> Option<String> {
+    match v {
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::String(s) => Some(format!("[[{}]]", escape_lua_text(s))),
+        serde_json::Value::Array(a) => {
+            let items: Vec<String> = a.iter().filter_map(lua_literal).collect();
+            Some(format!("{{ {} }}", items.join(", ")))
+        }
+        _ => None,
+    }
+}
+
 fn escape_lua_text(text: &str) -> String {
     text.replace("]]", "] ]")
 }
 
+/// 剥掉 `war3mapImported\` 前缀（前面的分隔符一并剥掉）；没有前缀时原样返回，
+/// 不动 POSIX 绝对路径开头的 `/`。
 fn strip_war3_imported_prefix(s: &str) -> &str {
     let t = s.trim_start_matches(|c| c == '\\' || c == '/');
     let lower = t.to_ascii_lowercase();
@@ -1435,7 +3341,7 @@ fn strip_war3_imported_prefix(s: &str) -> &str {
     if lower.starts_with("war3mapimported\\") {
         return &t["war3mapImported\\".len()..];
     }
-    t
+    s
 }
 
 fn rel_to_abs_runtime_path(raw: &str, global_root: &str) -> String {
@@ -1454,6 +3360,139 @@ fn rel_to_abs_runtime_path(raw: &str, global_root: &str) -> String {
     format!("{}/{}", root, rel)
 }
 
+/// `rel_to_abs_runtime_path` 的逆：返回落盘用的路径（反斜杠分隔，与前端 `absToRel` 一致）。
+///   - `war3mapImported\xxx` 与相对路径：统一成反斜杠的 relPath
+///   - 全局库根下的绝对路径：相对库根的 relPath（大小写与正反斜杠不敏感）
+///   - 库外的绝对路径：`None`，由调用方原样保留并报告
+fn abs_to_rel_storage_path(raw: &str, global_root: &str) -> Option<String> {
+    let stripped = strip_war3_imported_prefix(raw);
+    if !is_absolute_path(stripped) {
+        return Some(stripped.replace('/', "\\"));
+    }
+    if global_root.is_empty() {
+        return None;
+    }
+    let root = global_root.replace('\\', "/").trim_end_matches('/').to_string();
+    let abs = stripped.replace('\\', "/");
+    let rest = abs.get(root.len()..)?;
+    if !abs[..root.len()].eq_ignore_ascii_case(&root) || !rest.starts_with('/') {
+        return None;
+    }
+    let rel = rest.trim_start_matches('/');
+    if rel.is_empty() {
+        return None;
+    }
+    Some(rel.replace('/', "\\"))
+}
+
+/// 全局库里的文件 / 文件夹改名或移动后，改写一份落盘形态的项目 JSON 里指向它的资源引用。
+///   - `from` / `to` 是相对 `root` 的路径；`from` 是文件夹时，其下所有文件的引用都跟着改
+///   - 相对引用改成新的 relPath，保留原有的 `war3mapImported\` 前缀与斜杠风格；库根下的绝对路径改成新的绝对路径
+///   - 配了多个库根时，按 `roots` 解析到别的根（被靠前的根遮蔽）的相对引用不改；要在移动之前调用
+///   - 比较时大小写与正反斜杠不敏感
+///
+/// 返回被改写字段的（旧值, 新值）。
+pub fn rewrite_library_refs(
+    project: &mut serde_json::Value,
+    roots: &LibraryRoots,
+    root: &str,
+    from: &str,
+    to: &str,
+) -> Vec<(String, String)> {
+    let norm = |s: &str| s.replace('/', "\\").trim_matches('\\').to_string();
+    let (from, to) = (norm(from), norm(to));
+    if from.is_empty() {
+        return Vec::new();
+    }
+    let moved = |rel: &str| -> Option<String> {
+        let head = rel.get(..from.len())?;
+        let rest = &rel[from.len()..];
+        if !head.eq_ignore_ascii_case(&from) || !(rest.is_empty() || rest.starts_with('\\')) {
+            return None;
+        }
+        Some(format!("{to}{rest}"))
+    };
+    let trim_sep = |s: &str| s.trim_end_matches(['\\', '/']).to_string();
+    let in_other_root = |rel: &str| roots.root_for(rel).is_some_and(|r| !same_path(&trim_sep(&r.path), &trim_sep(root)));
+    let mut changes: Vec<(String, String)> = Vec::new();
+    let mut widgets = project
+        .get_mut("widgets")
+        .and_then(|w| w.as_array_mut())
+        .map(std::mem::take)
+        .unwrap_or_default();
+    let mut settings = project.get_mut("settings").map(serde_json::Value::take).unwrap_or_default();
+    resource_fields::visit_files_mut(&mut widgets, &mut settings, |_, _, v| {
+        let stripped = strip_war3_imported_prefix(v);
+        let prefix = &v[..v.len() - stripped.len()];
+        let next = if is_absolute_path(stripped) {
+            abs_to_rel_storage_path(stripped, root)
+                .and_then(|rel| moved(&rel))
+                .map(|rel| rel_to_abs_runtime_path(&rel, root))
+        } else if in_other_root(&norm(stripped)) {
+            None
+        } else {
+            let forward = stripped.contains('/') && !stripped.contains('\\');
+            moved(&norm(stripped)).map(|rel| if forward { rel.replace('\\', "/") } else { rel })
+        };
+        if let Some(next) = next.map(|n| format!("{prefix}{n}")).filter(|n| n != v) {
+            changes.push((v.clone(), next.clone()));
+            *v = next;
+        }
+    });
+    if let Some(obj) = project.as_object_mut() {
+        obj.insert("widgets".into(), serde_json::Value::Array(widgets));
+        if !settings.is_null() {
+            obj.insert("settings".into(), settings);
+        }
+    }
+    changes
+}
+
+/// 在 `.uiproj` 原文上做 `rewrite_library_refs`：只替换被改写的字符串值，键顺序、缩进等其余内容原样保留，
+/// 不会因为重新序列化产生无关的 diff。返回新文本与改写个数。
+pub fn rewrite_library_refs_in_text(
+    text: &str,
+    roots: &LibraryRoots,
+    root: &str,
+    from: &str,
+    to: &str,
+) -> Result<(String, usize), String> {
+    let mut project: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let changes = rewrite_library_refs(&mut project, roots, root, from, to);
+    if changes.is_empty() {
+        return Ok((text.to_string(), 0));
+    }
+    let map: HashMap<String, String> = changes.iter().cloned().collect();
+    Ok((replace_json_string_values(text, &map), changes.len()))
+}
+
+/// 把 JSON 原文里等于 `map` 某个键的字符串值（对象的键不算）换成对应的新值。
+/// 与被改写字段原值相同的其它字符串值也会一起换掉；资源路径这样的值实际上只会出现在资源字段里。
+fn replace_json_string_values(text: &str, map: &HashMap<String, String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('"') {
+        out.push_str(&rest[..start]);
+        let lit = &rest[start..];
+        let bytes = lit.as_bytes();
+        let mut end = 1;
+        while end < bytes.len() && bytes[end] != b'"' {
+            end += if bytes[end] == b'\\' { 2 } else { 1 };
+        }
+        let (literal, after) = lit.split_at((end + 1).min(lit.len()));
+        let is_key = after.trim_start().starts_with(':');
+        let next = serde_json::from_str::<String>(literal)
+            .ok()
+            .filter(|_| !is_key)
+            .and_then(|s| map.get(&s))
+            .and_then(|n| serde_json::to_string(n).ok());
+        out.push_str(next.as_deref().unwrap_or(literal));
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
 fn chrono_timestamp_ms() -> u128 {
     use std::time::{SystemTime, UNIX_EPOCH};
     SystemTime::now()
@@ -1466,6 +3505,14 @@ fn chrono_timestamp_ms() -> u128 {
 mod tests {
     use super::*;
 
+    fn opts() -> ApplyOptions {
+        ApplyOptions {
+            dry_run: false,
+            session_id: None,
+            allow_dangerous: false,
+        }
+    }
+
     #[tokio::test]
     async fn smoke_apply_validate_audit_export() {
         let mut engine = ProjectEngine::new();
@@ -1485,7 +3532,7 @@ mod tests {
             },
         );
         assert!(apply.ok, "{:?}", apply.errors);
-        let validate = engine.validate();
+        let validate = engine.validate(LibraryRoots::default());
         assert!(validate.ok, "{:?}", validate.diagnostics);
         let audit = engine.get_audit_trail(20);
         assert!(!audit.is_empty());
@@ -1500,4 +3547,940 @@ mod tests {
         assert!(out.is_ok(), "{:?}", out);
         let _ = std::fs::remove_file(&tmp);
     }
+
+    #[test]
+    fn dry_run_reports_change_set() {
+        let mut engine = ProjectEngine::new();
+        let created = engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "button" } })],
+            opts(),
+        );
+        assert_eq!(created.changes.created, vec![1, 2]);
+
+        let preview = engine.apply_actions(
+            &[
+                json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "text": "OK" } }),
+                json!({ "type": "deleteWidget", "targetId": 1 }),
+            ],
+            ApplyOptions { dry_run: true, ..opts() },
+        );
+        assert!(preview.ok, "{:?}", preview.errors);
+        assert_eq!(preview.changes.deleted, vec![1, 2]);
+        assert!(preview.changes.updated.is_empty());
+        assert_eq!(engine.get_snapshot().project.widgets.len(), 2);
+
+        let update = engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "text": "OK" } })],
+            opts(),
+        );
+        let change = &update.changes.updated[0];
+        assert_eq!(change.id, 1);
+        assert_eq!(change.fields[0].field, "text");
+        assert_eq!(change.fields[0].before, json!("按钮"));
+        assert_eq!(change.fields[0].after, json!("OK"));
+    }
+
+    #[test]
+    fn results_list_created_roles_in_order() {
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "actionId": "dlg", "payload": { "widgetType": "dialog" } }),
+                json!({ "type": "updateWidgetProps", "actionId": "bad", "targetId": 99, "payload": {} }),
+            ],
+            ApplyOptions {
+                dry_run: false,
+                session_id: None,
+                allow_dangerous: false,
+            },
+        );
+        assert_eq!(r.results.len(), 2);
+        let roles: Vec<&str> = r.results[0].created.iter().map(|c| c.role.as_str()).collect();
+        assert_eq!(
+            roles,
+            ["root", "title", "btn_1", "btn_1_label", "btn_2", "btn_2_label"]
+        );
+        assert_eq!(r.results[0].created[2].id, 3);
+        assert_eq!(r.results[1].action_id, "bad");
+        assert_eq!(r.results[1].status, "failed");
+        assert!(r.results[1].error.is_some());
+    }
+
+    #[test]
+    fn batch_refs_resolve_to_created_ids() {
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "ref": "shopPanel" } }),
+                json!({ "type": "createWidget", "payload": {
+                    "widgetType": "button", "ref": "buy",
+                    "overrides": { "parentId": { "$ref": "shopPanel" } }
+                } }),
+                json!({ "type": "updateWidgetProps", "targetId": { "$ref": "buy.label" }, "payload": { "text": "Buy" } }),
+                json!({ "type": "setParent", "targetId": { "$ref": "missing" }, "payload": { "parentId": null } }),
+            ],
+            ApplyOptions {
+                dry_run: false,
+                session_id: None,
+                allow_dangerous: false,
+            },
+        );
+        assert_eq!(r.applied, 3);
+        assert_eq!(r.errors, vec!["unknown ref: missing".to_string()]);
+        let widgets = engine.get_snapshot().project.widgets;
+        assert_eq!(widgets[1]["parentId"], json!(1));
+        assert_eq!(widgets[2]["text"], json!("Buy"));
+    }
+
+    #[test]
+    fn name_paths_resolve_through_parents() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "dialog", "overrides": { "name": "shop" } } })],
+            opts(),
+        );
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "updateWidgetProps", "targetId": "shop/shop_btn_1", "payload": { "text": "Buy" } }),
+                json!({ "type": "deleteWidget", "targetId": "shop/nope" }),
+            ],
+            opts(),
+        );
+        assert_eq!(r.results[0].affected, vec![3]);
+        assert_eq!(r.errors, vec!["widget path not found: shop/nope".to_string()]);
+
+        let widgets = engine.get_snapshot().project.widgets;
+        assert_eq!(resolve_widget_ref(&widgets, &json!("shop/shop_btn_2/shop_btn_2_label")), Ok(6));
+    }
+
+    #[test]
+    fn query_filters_and_projects() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "dialog", "overrides": { "name": "shop" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "fontSize": 24, "x": 0, "y": 0 } } }),
+            ],
+            ApplyOptions {
+                dry_run: false,
+                session_id: None,
+                allow_dangerous: false,
+            },
+        );
+        let q: WidgetQuery = serde_json::from_value(json!({
+            "root": "shop",
+            "type": "text",
+            "name": "shop_btn_?_LABEL",
+            "fields": ["text"],
+            "limit": 1
+        }))
+        .unwrap();
+        let r = engine.query(&q).unwrap();
+        assert_eq!(r["total"], json!(2));
+        assert_eq!(r["truncated"], json!(true));
+        assert_eq!(r["widgets"], json!([{ "id": 4, "text": "确定" }]));
+
+        let q: WidgetQuery = serde_json::from_value(json!({
+            "intersects": { "x": -10, "y": -10, "w": 20, "h": 20 },
+            "where": [
+                { "field": "fontSize", "op": "gt", "value": 20 },
+                { "field": "image", "op": "empty" }
+            ]
+        }))
+        .unwrap();
+        let r = engine.query(&q).unwrap();
+        assert_eq!(r["total"], json!(1));
+        assert_eq!(r["widgets"][0]["id"], json!(7));
+    }
+
+    #[test]
+    fn update_where_patches_matches_and_guards_large_batches() {
+        let mut engine = ProjectEngine::new();
+        let buttons: Vec<serde_json::Value> = (0..11)
+            .map(|_| json!({ "type": "createWidget", "payload": { "widgetType": "button" } }))
+            .collect();
+        engine.apply_actions(&buttons, opts());
+
+        let r = engine.apply_actions(
+            &[json!({
+                "type": "updateWhere",
+                "selector": { "type": "text", "where": [{ "field": "fontSize", "op": "eq", "value": 14 }] },
+                "payload": { "fontSize": 18 }
+            })],
+            opts(),
+        );
+        assert!(r.errors[0].contains("updateWhere matches 11 widgets"), "{:?}", r.errors);
+
+        let r = engine.apply_actions(
+            &[json!({
+                "type": "updateWhere",
+                "selector": { "type": "text", "root": 1 },
+                "payload": { "fontSize": 18 }
+            })],
+            opts(),
+        );
+        assert_eq!(r.results[0].affected, vec![2]);
+        assert_eq!(engine.get_snapshot().project.widgets[1]["fontSize"], json!(18));
+    }
+
+    #[test]
+    fn merge_and_json_patch_edit_nested_fields() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": {
+                "padding": { "top": 1, "left": 2 }, "meta": { "a": 1, "b": 2 }
+            } } })],
+            opts(),
+        );
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "updateWidgetProps", "targetId": 1, "mode": "merge",
+                        "payload": { "padding": { "top": 8 }, "meta": { "b": null } } }),
+                json!({ "type": "patchWidget", "targetId": 1, "payload": [
+                    { "op": "test", "path": "/padding/top", "value": 8 },
+                    { "op": "add", "path": "/meta/tags", "value": ["x"] },
+                    { "op": "add", "path": "/meta/tags/-", "value": "y" },
+                    { "op": "move", "from": "/meta/a", "path": "/meta/c" },
+                    { "op": "replace", "path": "/padding/left", "value": 4 }
+                ] }),
+                json!({ "type": "patchWidget", "targetId": 1, "payload": [
+                    { "op": "remove", "path": "/padding" },
+                    { "op": "test", "path": "/meta/c", "value": 999 }
+                ] }),
+            ],
+            opts(),
+        );
+        assert_eq!(r.applied, 2);
+        assert!(r.errors[0].starts_with("patch op #1 failed: test failed"), "{:?}", r.errors);
+        let w = &engine.get_snapshot().project.widgets[0];
+        assert_eq!(w["padding"], json!({ "top": 8, "left": 4 }));
+        assert_eq!(w["meta"], json!({ "c": 1, "tags": ["x", "y"] }));
+
+        // id 不能被 patch 改掉；parentId 与 setParent 走同一套检查
+        for ops in [
+            json!([{ "op": "remove", "path": "/id" }]),
+            json!([{ "op": "move", "from": "/id", "path": "/meta/old" }]),
+            json!([{ "op": "replace", "path": "/parentId", "value": 1 }]),
+            json!([{ "op": "add", "path": "/parentId", "value": 42 }]),
+        ] {
+            let r = engine.apply_actions(&[json!({ "type": "patchWidget", "targetId": 1, "payload": ops })], opts());
+            assert_eq!(r.applied, 0, "{:?}", ops);
+        }
+        assert_eq!(engine.get_snapshot().project.widgets[0]["id"], json!(1));
+    }
+
+    #[test]
+    fn repeated_idempotency_keys_replay_first_result() {
+        let mut engine = ProjectEngine::new();
+        let batch = [
+            json!({ "type": "createWidget", "idempotencyKey": "panel-v1", "payload": { "widgetType": "panel", "ref": "p" } }),
+            json!({ "type": "createWidget", "idempotencyKey": "btn-v1", "payload": {
+                "widgetType": "button", "overrides": { "parentId": { "$ref": "p" } }
+            } }),
+        ];
+        let first = engine.apply_actions(&batch, opts());
+        assert_eq!((first.applied, first.replayed), (2, 0));
+
+        let retry = engine.apply_actions(&batch, opts());
+        assert!(retry.ok, "{:?}", retry.errors);
+        assert_eq!((retry.applied, retry.replayed), (0, 2));
+        assert_eq!(retry.results[1].status, "replayed");
+        assert_eq!(retry.results[1].created[0].id, 2);
+        assert!(retry.changes.created.is_empty());
+        assert_eq!(engine.get_snapshot().project.widgets.len(), 3);
+
+        let misuse = engine.apply_actions(
+            &[json!({ "type": "deleteWidget", "idempotencyKey": "btn-v1", "targetId": 2 })],
+            opts(),
+        );
+        assert_eq!(misuse.results[0].status, "failed");
+        assert_eq!(engine.get_snapshot().project.widgets.len(), 3);
+    }
+
+    #[test]
+    fn duplicate_widget_copies_subtree_and_animations() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "name": "buy", "x": 10, "y": 10 } } })],
+            opts(),
+        );
+        engine.project.animations.push(json!({ "id": 1, "widgetId": 2, "kind": "fade" }));
+        engine.project.next_anim_id = 2;
+
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "duplicateWidget", "targetId": "buy", "payload": { "offset": { "x": 0, "y": 40 } } }),
+                json!({ "type": "duplicateWidget", "targetId": "buy" }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        assert_eq!(r.results[0].created[1].role, "src_2");
+        let snap = engine.get_snapshot();
+        assert!(!snap.diagnostics.iter().any(|d| d.starts_with("duplicate child name")), "{:?}", snap.diagnostics);
+        let w = &snap.project.widgets;
+        assert_eq!(w[2]["name"], json!("buy_copy"));
+        assert_eq!((w[2]["x"].clone(), w[2]["y"].clone()), (json!(10), json!(50)));
+        assert_eq!(w[3]["name"], json!("buy_copy_label"));
+        assert_eq!(w[3]["parentId"], json!(3));
+        assert_eq!(w[4]["name"], json!("buy_copy2"));
+        assert_eq!(snap.project.animations[1], json!({ "id": 2, "widgetId": 4, "kind": "fade" }));
+        assert_eq!(snap.project.next_anim_id, 4);
+
+        let r = engine.apply_actions(
+            &[json!({ "type": "duplicateWidget", "targetId": "buy", "payload": { "name": "buy_copy" } })],
+            opts(),
+        );
+        assert!(r.errors[0].contains("name already used"), "{:?}", r.errors);
+
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "buyer_x", "parentId": 1 } } }),
+                json!({ "type": "duplicateWidget", "targetId": "buy", "payload": { "name": "shop" } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let copies: Vec<&str> = r.results[1]
+            .created
+            .iter()
+            .filter_map(|c| engine.project.widgets.iter().find(|w| w["id"] == json!(c.id)))
+            .filter_map(|w| get_str(w, "name"))
+            .collect();
+        assert_eq!(copies, vec!["shop", "shop_label", "buyer_x"]);
+    }
+
+    #[test]
+    fn widget_type_registry_validates_writes() {
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "fdfTemplate": "NORMAL_UP" } } }),
+                json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "type": "slider" } }),
+                json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "fontSize": "big" } }),
+                json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "x": null } }),
+                json!({ "type": "updateWidgetProps", "targetId": 1, "mode": "merge", "payload": { "alpha": 300 } }),
+                json!({ "type": "patchWidget", "targetId": 1, "payload": [{ "op": "remove", "path": "/visible" }] }),
+                json!({ "type": "updateWhere", "selector": { "type": "text" }, "payload": { "textAlignH": "middle" } }),
+                json!({ "type": "updateWidgetProps", "targetId": 1, "payload": { "textColor": "FFCC00", "hoverAlpha": 200 } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "fdfTemplate": "NORMAL_UP" } } }),
+            ],
+            opts(),
+        );
+        assert_eq!(r.applied, 3, "{:?}", r.errors);
+        assert!(r.errors[0].starts_with("type is read-only"), "{:?}", r.errors);
+        assert!(r.errors[1].starts_with("invalid fontSize: expected integer in 1..=200"), "{:?}", r.errors);
+        assert!(r.errors[2].starts_with("x cannot be null"), "{:?}", r.errors);
+        assert!(r.errors[4].starts_with("visible cannot be null"), "{:?}", r.errors);
+        assert_eq!(engine.project.widgets[0]["text"], json!("按钮"));
+        assert_eq!(engine.project.widgets[1]["text"], json!("按钮"));
+
+        // 未登记的插件类型只校验已知属性
+        let r = engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "myGauge", "overrides": { "ratio": 0.5 } } })],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        assert_eq!(engine.project.widgets[3]["fontSize"], json!(14));
+    }
+
+    #[test]
+    fn native_frame_types_create_validate_and_export() {
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "checkbox", "overrides": { "name": "mute", "x": 10, "y": 10, "text": "静音" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "slider", "overrides": { "name": "vol", "value": 150 } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "popupmenu", "overrides": { "name": "mode", "items": ["简单", "困难"], "selectedIndex": 1 } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "statusbar", "overrides": { "name": "hp", "barColor": "FF0000" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "listbox", "overrides": { "items": [1, 2] } } }),
+            ],
+            ApplyOptions {
+                dry_run: false,
+                session_id: None,
+                allow_dangerous: false,
+            },
+        );
+        assert_eq!(r.applied, 4);
+        assert!(r.errors[0].starts_with("invalid items: expected array of strings"), "{:?}", r.errors);
+        assert_eq!(r.results[0].created[1].role, "label");
+        let w = &engine.project.widgets;
+        assert_eq!((w[0]["w"].clone(), w[1]["x"].clone(), w[1]["text"].clone()), (json!(24), json!(42), json!("静音")));
+        assert_eq!(w[2]["stepSize"], json!(1));
+
+        let diags = engine.validate(LibraryRoots::default()).diagnostics;
+        assert_eq!(diags, vec!["vol: value 150 out of range 0..=100".to_string()]);
+
+        let lua = engine.export_lua();
+        assert!(lua.contains("frame_type = 'POPUPMENU',\n        items = { [[简单]], [[困难]] },\n        selected_index = 1,"), "{}", lua);
+        assert!(lua.contains("bar_color = [[FF0000]],"));
+        let tree = build_widget_tree(&engine.project.widgets);
+        assert_eq!(tree[3]["frameType"], json!("SIMPLESTATUSBAR"));
+    }
+
+    #[test]
+    fn locked_subtrees_refuse_mutations_without_force() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "name": "ok" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "free" } } }),
+                json!({ "type": "lock", "targetId": "ok" }),
+            ],
+            opts(),
+        );
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "updateWidgetProps", "targetId": "ok/ok_label", "payload": { "text": "x" } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "parentId": 1 } } }),
+                json!({ "type": "updateWhere", "selector": { "type": "text" }, "payload": { "fontSize": 20 } }),
+                json!({ "type": "unlock", "targetId": "ok" }),
+                json!({ "type": "moveWidget", "targetId": "ok", "force": true, "payload": { "dx": 5 } }),
+                json!({ "type": "updateWidgetProps", "targetId": "free", "payload": { "parentId": 1 } }),
+                json!({ "type": "patchWidget", "targetId": "free", "payload": [{ "op": "replace", "path": "/parentId", "value": 1 }] }),
+                json!({ "type": "futureAction", "targetId": "ok" }),
+            ],
+            opts(),
+        );
+        assert_eq!(r.applied, 0);
+        assert!(r.results.iter().all(|x| x.status == "refused"), "{:?}", r.results);
+        assert!(r.errors[0].contains("widget 2 is locked"), "{:?}", r.errors);
+
+        let r = engine.apply_actions(
+            &[json!({ "type": "moveWidget", "targetId": "ok", "force": true, "payload": { "dx": 5 } })],
+            ApplyOptions { allow_dangerous: true, ..opts() },
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "unlock", "targetId": "ok" }),
+                json!({ "type": "deleteWidget", "targetId": "ok" }),
+            ],
+            ApplyOptions { allow_dangerous: true, ..opts() },
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        assert_eq!(engine.project.widgets.len(), 1);
+    }
+
+    #[test]
+    fn move_and_resize_carry_descendants() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": { "widgetType": "dialog", "overrides": {
+                "name": "dlg", "x": 100, "y": 100, "w": 400, "h": 300
+            } } })],
+            opts(),
+        );
+        let rect = |e: &ProjectEngine, path: &str| -> [f64; 4] {
+            let id = resolve_widget_ref(&e.project.widgets, &json!(path)).unwrap();
+            widget_rect(e.project.widgets.iter().find(|w| get_i64(w, "id", 0) == id).unwrap())
+        };
+        let title = rect(&engine, "dlg/dlg_title");
+        let btn = rect(&engine, "dlg/dlg_btn_1");
+        let label = rect(&engine, "dlg/dlg_btn_1/dlg_btn_1_label");
+
+        let r = engine.apply_actions(
+            &[json!({ "type": "moveWidget", "targetId": "dlg", "payload": { "x": 150, "dy": -20 } })],
+            opts(),
+        );
+        assert_eq!(r.results[0].affected.len(), 6);
+        assert_eq!(rect(&engine, "dlg"), [150.0, 80.0, 400.0, 300.0]);
+        assert_eq!(rect(&engine, "dlg/dlg_btn_1/dlg_btn_1_label"), [label[0] + 50.0, label[1] - 20.0, label[2], label[3]]);
+
+        let r = engine.apply_actions(
+            &[json!({ "type": "resizeWidget", "targetId": "dlg", "payload": {
+                "w": 800, "h": 600, "childRule": "center", "rules": { "dlg_title": "stretch" }
+            } })],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let t = rect(&engine, "dlg/dlg_title");
+        assert_eq!((t[0], t[2]), (150.0 + (title[0] - 100.0) * 2.0, title[2] * 2.0));
+        let b = rect(&engine, "dlg/dlg_btn_1");
+        assert_eq!(b[0] + b[2] / 2.0 - 550.0, btn[0] + btn[2] / 2.0 - 300.0);
+        assert_eq!(rect(&engine, "dlg/dlg_btn_1/dlg_btn_1_label")[2], label[2]);
+        assert_eq!(engine.project.widgets[0]["w"], json!(800));
+
+        let bad = engine.apply_actions(
+            &[json!({ "type": "resizeWidget", "targetId": "dlg", "payload": { "w": 10, "childRule": "bogus" } })],
+            opts(),
+        );
+        assert!(bad.errors[0].contains("unsupported resize rule"), "{:?}", bad.errors);
+
+        // 不允许把控件挂到自己的后代下；已经成环的旧项目缩放也要能结束
+        let label_id = resolve_widget_ref(&engine.project.widgets, &json!("dlg/dlg_btn_1/dlg_btn_1_label")).unwrap();
+        for action in [
+            json!({ "type": "setParent", "targetId": "dlg", "payload": { "parentId": label_id } }),
+            json!({ "type": "updateWidgetProps", "targetId": "dlg", "payload": { "parentId": label_id } }),
+        ] {
+            let r = engine.apply_actions(&[action], opts());
+            assert!(r.errors[0].contains("cycle"), "{:?}", r.errors);
+        }
+        let dlg_id = get_i64(&engine.project.widgets[0], "id", 0);
+        engine.project.widgets[0]["parentId"] = json!(label_id);
+        let r = engine.apply_actions(
+            &[json!({ "type": "resizeWidget", "targetId": dlg_id, "payload": { "w": 400, "h": 300 } })],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        assert_eq!(r.results[0].affected.len(), 6);
+    }
+
+    #[test]
+    fn group_and_ungroup_round_trip() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "a", "x": 10, "y": 20, "w": 50, "h": 10 } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "name": "b", "x": 100, "y": 40, "w": 20, "h": 30 } } }),
+            ],
+            opts(),
+        );
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "groupWidgets", "payload": { "ids": ["a", 2], "padding": 5, "ref": "g", "overrides": { "name": "row" } } }),
+                json!({ "type": "updateWidgetProps", "targetId": { "$ref": "g" }, "payload": { "alpha": 128 } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let g = &engine.project.widgets[0];
+        assert_eq!(g["name"], json!("row"));
+        assert_eq!((g["x"].clone(), g["y"].clone(), g["w"].clone(), g["h"].clone()), (json!(5), json!(15), json!(120), json!(60)));
+        assert_eq!(engine.project.widgets[1]["parentId"], json!(4));
+        assert_eq!(engine.project.widgets[3]["parentId"], json!(2));
+
+        let bad = engine.apply_actions(&[json!({ "type": "ungroup", "targetId": "row/b" })], opts());
+        assert!(!bad.ok);
+        let r = engine.apply_actions(&[json!({ "type": "ungroup", "targetId": "row" })], opts());
+        assert_eq!(r.changes.deleted, vec![4]);
+        assert_eq!(engine.project.widgets.len(), 3);
+        assert_eq!(engine.project.widgets[0]["parentId"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn z_order_actions_reorder_siblings_only() {
+        let mut engine = ProjectEngine::new();
+        // 1: a, 2: b(button), 3: b_label, 4: c
+        engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "a" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "name": "b" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "c" } } }),
+            ],
+            opts(),
+        );
+        let ids = |e: &ProjectEngine| -> Vec<i64> {
+            e.project.widgets.iter().map(|w| get_i64(w, "id", 0)).collect()
+        };
+
+        let r = engine.apply_actions(&[json!({ "type": "bringToFront", "targetId": "a" })], opts());
+        assert_eq!(ids(&engine), vec![2, 3, 4, 1]);
+        assert_eq!(r.changes.reordered, vec![1, 2, 4]);
+        assert!(r.changes.updated.is_empty());
+
+        engine.apply_actions(&[json!({ "type": "moveDown", "targetId": 1 })], opts());
+        assert_eq!(ids(&engine), vec![2, 3, 1, 4]);
+        engine.apply_actions(&[json!({ "type": "sendToBack", "targetId": 4 })], opts());
+        assert_eq!(ids(&engine), vec![4, 2, 3, 1]);
+
+        let bad = engine.apply_actions(
+            &[json!({ "type": "reorderChildren", "targetId": null, "payload": { "order": [1, 2] } })],
+            opts(),
+        );
+        assert!(!bad.ok);
+        engine.apply_actions(
+            &[json!({ "type": "reorderChildren", "payload": { "order": ["c", "a", "b"] } })],
+            opts(),
+        );
+        assert_eq!(ids(&engine), vec![4, 1, 2, 3]);
+
+        let tree = build_widget_tree(&engine.project.widgets);
+        let names: Vec<&str> = tree.iter().filter_map(|n| get_str(n, "name")).collect();
+        assert_eq!(names, vec!["c", "a", "b"]);
+        assert_eq!(tree[2]["children"][0]["name"], json!("b_label"));
+        assert!(engine.export_lua().contains("self.b = self:createChild('button', {\n        x = "));
+        assert!(engine.export_lua().contains("        level = 2,\n    })"));
+    }
+
+    #[test]
+    fn outline_shows_hierarchy_and_non_default_fields() {
+        let mut engine = ProjectEngine::new();
+        engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": {
+                "widgetType": "button",
+                "overrides": { "name": "buy", "x": 10, "y": 20, "image": "D:/lib/ui/btn.blp", "fontSize": 18 }
+            } })],
+            ApplyOptions {
+                dry_run: false,
+                session_id: None,
+                allow_dangerous: false,
+            },
+        );
+        let r = engine.outline(None, None).unwrap();
+        let text = r["outline"].as_str().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "#1 button \"buy\" [10,20 100x100] text=\"按钮\" image=btn.blp fontSize=18"
+        );
+        assert!(lines[1].starts_with("  #2 text \"buy_label\""), "{}", lines[1]);
+
+        let r = engine.outline(Some(&json!("buy")), Some(0)).unwrap();
+        assert!(r["outline"].as_str().unwrap().ends_with("(+1 children)"));
+    }
+
+    #[tokio::test]
+    async fn model_resources_copy_referenced_textures() {
+        let root = std::env::temp_dir().join(format!("ui-designer-model-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let lib = root.join("lib");
+        std::fs::create_dir_all(lib.join("war3mapImported")).unwrap();
+        let mut texs = Vec::new();
+        for path in ["war3mapImported\\glow.blp", "", "Textures\\Black32.blp", "..\\..\\evil.blp", "Textures\\flat.blp"] {
+            let mut rec = vec![0u8; 268];
+            rec[4..4 + path.len()].copy_from_slice(path.as_bytes());
+            texs.extend(rec);
+        }
+        let mut mdx = b"MDLX".to_vec();
+        mdx.extend(b"VERS");
+        mdx.extend(4u32.to_le_bytes());
+        mdx.extend(800u32.to_le_bytes());
+        mdx.extend(b"TEXS");
+        mdx.extend((texs.len() as u32).to_le_bytes());
+        mdx.extend(texs);
+        let model = lib.join("glow.mdx");
+        std::fs::write(&model, &mdx).unwrap();
+        std::fs::write(lib.join("war3mapImported").join("glow.blp"), b"BLP1").unwrap();
+        // 同名文件就在模型旁边，也不能借"同目录同名"的退路绕过越界检查。
+        std::fs::write(lib.join("evil.blp"), b"BLP1").unwrap();
+        // 引用 `Textures\flat.blp`、文件却平放在模型旁边：拷贝时仍按引用的子路径摆放
+        std::fs::write(lib.join("flat.blp"), b"BLP1").unwrap();
+        assert_eq!(
+            model_files::texture_paths(&model),
+            vec!["war3mapImported\\glow.blp", "Textures\\Black32.blp", "..\\..\\evil.blp", "Textures\\flat.blp"]
+        );
+        assert_eq!(model_files::safe_texture_rel("war3mapImported\\.\\a.blp").as_deref(), Some("war3mapImported/a.blp"));
+        for bad in ["..\\..\\evil.blp", "a/../../b.blp", "C:\\evil.blp", "\\\\host\\share\\x.blp", "/etc/x.blp"] {
+            assert_eq!(model_files::safe_texture_rel(bad), None, "{bad}");
+        }
+        assert_eq!(
+            model_files::mdl_texture_paths("Textures 1 {\n\tBitmap {\n\t\tImage \"a\\b.blp\",\n\t}\n}"),
+            vec!["a\\b.blp"]
+        );
+
+        let mut engine = ProjectEngine::new();
+        let model_abs = model.to_string_lossy().to_string();
+        let r = engine.apply_actions(
+            &[json!({ "type": "createWidget", "payload": {
+                "widgetType": "sprite",
+                "overrides": { "name": "glow", "model": model_abs }
+            } })],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let listed = engine.list_resources(false, Some(lib.to_string_lossy().to_string()));
+        assert_eq!(listed["resources"][0]["exists"], json!(true));
+        assert!(engine.validate(LibraryRoots::default()).ok);
+        assert!(engine.export_lua().contains("frame_type = 'SPRITE'"));
+
+        let out = root.join("out");
+        let copied = engine
+            .copy_resources(out.to_string_lossy().to_string(), None, false, Some(lib.to_string_lossy().to_string()))
+            .await
+            .unwrap();
+        assert!(out.join("glow.mdx").is_file());
+        assert!(out.join("war3mapImported").join("glow.blp").is_file());
+        assert!(out.join("Textures").join("flat.blp").is_file());
+        assert!(!out.join("flat.blp").exists());
+        assert_eq!(copied["copied"].as_array().unwrap().len(), 3);
+        assert_eq!(copied["skipped"][0]["texture"], json!("Textures\\Black32.blp"));
+        assert_eq!(copied["errors"][0]["texture"], json!("..\\..\\evil.blp"));
+        assert!(!out.join("evil.blp").exists());
+        assert!(!root.join("evil.blp").exists());
+
+        let bad = engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": "glow", "payload": { "model": "D:/lib/glow.png" } })],
+            opts(),
+        );
+        assert!(bad.ok);
+        assert!(engine.validate(LibraryRoots::default()).diagnostics.iter().any(|d| d.contains("model must be a .mdx or .mdl file")));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn fonts_validate_list_and_copy() {
+        let root = std::env::temp_dir().join(format!("ui-designer-fonts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let lib = root.join("lib");
+        std::fs::create_dir_all(lib.join("fonts")).unwrap();
+        let utf16 = |s: &str| s.encode_utf16().flat_map(|u| u.to_be_bytes()).collect::<Vec<u8>>();
+        let (family, style) = (utf16("Test Sans"), utf16("Bold"));
+        let mut name = Vec::new();
+        for v in [0u16, 2, 30] {
+            name.extend(v.to_be_bytes());
+        }
+        for (id, len, off) in [(1u16, family.len(), 0usize), (2, style.len(), family.len())] {
+            for v in [3u16, 1, 0x409, id, len as u16, off as u16] {
+                name.extend(v.to_be_bytes());
+            }
+        }
+        name.extend(&family);
+        name.extend(&style);
+        let mut ttf = vec![0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        ttf.extend(b"name");
+        ttf.extend(0u32.to_be_bytes());
+        ttf.extend(28u32.to_be_bytes());
+        ttf.extend((name.len() as u32).to_be_bytes());
+        ttf.extend(name);
+        let font = lib.join("fonts").join("TestSans.ttf");
+        std::fs::write(&font, &ttf).unwrap();
+        let font_abs = font.to_string_lossy().to_string();
+
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "title", "font": font_abs } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "body", "font": "Fonts/fzxh1jw.ttf" } } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        let lib_root = Some(lib.to_string_lossy().to_string());
+        assert!(engine.validate(lib_root.clone()).ok, "{:?}", engine.validate(lib_root.clone()).diagnostics);
+        // 不传库根时库里的字体也认不出
+        assert!(engine.validate(LibraryRoots::default()).diagnostics.iter().any(|d| d.starts_with("title: unknown font")));
+
+        let fonts = engine.list_fonts(lib_root.clone());
+        assert_eq!(fonts["library"][0]["family"], json!("Test Sans"));
+        assert_eq!(fonts["library"][0]["style"], json!("Bold"));
+        assert!(fonts["used"].as_array().unwrap().iter().all(|u| u["known"] == json!(true)));
+        let listed = engine.list_resources(false, lib_root.clone());
+        let files: Vec<&serde_json::Value> = listed["resources"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|r| r["builtin"] == json!(false))
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0]["kind"], json!("font"));
+
+        let out = root.join("out");
+        engine.copy_resources(out.to_string_lossy().to_string(), None, false, lib_root.clone()).await.unwrap();
+        assert!(out.join("fonts").join("TestSans.ttf").is_file());
+        assert!(engine.export_lua().contains("font = [[Fonts/fzxh1jw.ttf]]"));
+
+        // 库里没有的相对字体路径：打开时不拼成库路径，但校验报未知字体
+        engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": "body", "payload": { "font": "Fonts\\Custom.ttf" } })],
+            opts(),
+        );
+        engine.hydrate_runtime_image_paths(lib_root.clone());
+        assert_eq!(engine.project.widgets[1]["font"], json!("Fonts\\Custom.ttf"));
+        let diags = engine.validate(lib_root.clone()).diagnostics;
+        assert_eq!(diags, vec!["body: unknown font Fonts\\Custom.ttf (not a builtin font or a font in the global library)".to_string()]);
+        let fonts = engine.list_fonts(lib_root.clone());
+        assert!(fonts["used"].as_array().unwrap().iter().any(|u| u["value"] == json!("Fonts\\Custom.ttf") && u["known"] == json!(false)));
+
+        // 库外真实存在的字体文件同样不认
+        let outside = root.join("outside.ttf");
+        std::fs::write(&outside, &ttf).unwrap();
+        let outside_abs = outside.to_string_lossy().to_string();
+        engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": "body", "payload": { "font": outside_abs } })],
+            opts(),
+        );
+        assert!(engine.validate(lib_root.clone()).diagnostics.iter().any(|d| d.starts_with("body: unknown font")));
+        let fonts = engine.list_fonts(lib_root.clone());
+        assert!(fonts["used"].as_array().unwrap().iter().any(|u| u["known"] == json!(false)));
+        engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": "body", "payload": { "font": "Fonts\\icon.png" } })],
+            opts(),
+        );
+        assert!(engine.validate(lib_root.clone()).diagnostics.iter().any(|d| d.starts_with("body: font must be a .ttf or .otf file")));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn resource_registry_covers_settings_presets_and_type_fields() {
+        let fields = resource_fields::widget_fields();
+        assert!(fields.iter().any(|f| f.path == "barTexture" && f.widget_type == Some("statusbar")));
+        assert!(resource_fields::fields_for("text").iter().all(|f| f.path != "barTexture"));
+
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "bg", "backgroundPreset": "DIALOG" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "statusbar", "overrides": { "name": "hp", "barTexture": "bars\\hp.blp" } } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        engine.project.settings["canvasBgImage"] = json!("bg\\menu.png");
+        assert_eq!(engine.hydrate_runtime_image_paths(Some("D:/lib".into())), 2);
+        assert_eq!(engine.project.settings["canvasBgImage"], json!("D:/lib/bg/menu.png"));
+        assert_eq!(engine.project.widgets[1]["barTexture"], json!("D:/lib/bars/hp.blp"));
+
+        let listed = engine.list_resources(false, None);
+        let by_value = |v: &str| {
+            listed["resources"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|r| r["value"] == json!(v))
+                .cloned()
+                .unwrap_or_else(|| panic!("{} not listed: {}", v, listed))
+        };
+        let preset = by_value("UI\\Widgets\\Glues\\GlueScreen-DialogBackground.blp");
+        assert_eq!(preset["builtin"], json!(true));
+        assert_eq!(preset["fields"], json!(["backgroundPreset"]));
+        assert_eq!(by_value("D:/lib/bg/menu.png")["fields"], json!(["settings.canvasBgImage"]));
+
+        engine.apply_actions(
+            &[json!({ "type": "updateWidgetProps", "targetId": "bg", "payload": { "backgroundPreset": "NOPE" } })],
+            opts(),
+        );
+        let diags = engine.validate(LibraryRoots::default()).diagnostics;
+        assert!(diags.iter().any(|d| d == "bg: unknown backgroundPreset NOPE"), "{:?}", diags);
+        assert!(diags.iter().any(|d| d.starts_with("missing resource files: 2")), "{:?}", diags);
+    }
+
+    #[tokio::test]
+    async fn save_dehydrates_library_paths_and_reports_outside() {
+        let dir = std::env::temp_dir().join(format!("ui-designer-save-{}", std::process::id()));
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "button", "overrides": { "name": "ok", "image": "D:/Lib/icons/ok.blp", "hoverImage": "C:\\Users\\me\\hover.png" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "text", "overrides": { "name": "title", "font": "Fonts\\FZXH1JW.TTF" } } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        engine.project.settings["canvasBgImage"] = json!("D:\\lib\\bg\\menu.png");
+
+        let path = dir.join("a.uiproj").to_string_lossy().to_string();
+        let saved = engine.save_project(Some(path.clone()), Some("D:\\lib\\".into())).await.unwrap();
+        assert_eq!(saved["dehydrated"], json!(2));
+        let diags = saved["diagnostics"].as_array().unwrap();
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].as_str().unwrap().contains("hoverImage: C:\\Users\\me\\hover.png"), "{:?}", diags);
+
+        let on_disk: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(on_disk["widgets"][0]["image"], json!("icons\\ok.blp"));
+        assert_eq!(on_disk["widgets"][0]["hoverImage"], json!("C:\\Users\\me\\hover.png"));
+        let title = on_disk["widgets"].as_array().unwrap().iter().find(|w| w["name"] == json!("title")).unwrap();
+        assert_eq!(title["font"], json!("Fonts\\FZXH1JW.TTF"));
+        assert_eq!(on_disk["settings"]["canvasBgImage"], json!("bg\\menu.png"));
+        // 内存里保持绝对路径
+        assert_eq!(engine.project.widgets[0]["image"], json!("D:/Lib/icons/ok.blp"));
+
+        engine.open_project(path).await.unwrap();
+        engine.hydrate_runtime_image_paths(Some("D:/lib".into()));
+        assert_eq!(engine.project.widgets[0]["image"], json!("D:/lib/icons/ok.blp"));
+        assert_eq!(engine.project.settings["canvasBgImage"], json!("D:/lib/bg/menu.png"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn library_roots_resolve_in_order_and_write_to_writable_root() {
+        let base = std::env::temp_dir().join(format!("ui-designer-roots-{}", std::process::id()));
+        let mine = base.join("mine");
+        let shared = base.join("shared");
+        for (dir, name) in [(&mine, "both.blp"), (&shared, "both.blp"), (&shared, "team.blp")] {
+            std::fs::create_dir_all(dir.join("icons")).unwrap();
+            std::fs::write(dir.join("icons").join(name), b"x").unwrap();
+        }
+        std::fs::write(base.join("loose.png"), b"x").unwrap();
+        let roots = || {
+            LibraryRoots::from_settings(
+                Some(mine.to_string_lossy().to_string()),
+                vec![shared.to_string_lossy().to_string()],
+            )
+        };
+
+        let mut engine = ProjectEngine::new();
+        let r = engine.apply_actions(
+            &[
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "a", "image": "icons\\both.blp" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "b", "image": "icons\\team.blp" } } }),
+                json!({ "type": "createWidget", "payload": { "widgetType": "panel", "overrides": { "name": "c", "image": base.join("loose.png").to_string_lossy() } } }),
+            ],
+            opts(),
+        );
+        assert!(r.ok, "{:?}", r.errors);
+        engine.hydrate_runtime_image_paths(roots());
+        let image = |e: &ProjectEngine, i: usize| e.project.widgets[i]["image"].as_str().unwrap().to_string();
+        assert!(same_path(&image(&engine, 0), &mine.join("icons").join("both.blp").to_string_lossy()));
+        assert!(same_path(&image(&engine, 1), &shared.join("icons").join("team.blp").to_string_lossy()));
+
+        // 共享根里的文件不拷贝；库外文件进可写根
+        let norm = engine.normalize_resource_paths(None, roots());
+        assert_eq!(norm["ok"], json!(true), "{}", norm);
+        assert_eq!(norm["copied"].as_array().unwrap().len(), 1, "{}", norm);
+        assert!(mine.join("loose.png").is_file());
+        assert!(!shared.join("loose.png").exists());
+
+        let saved = engine.save_project(Some(base.join("p.uiproj").to_string_lossy().to_string()), roots()).await.unwrap();
+        assert_eq!(saved["diagnostics"], json!([]));
+        let on_disk: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(base.join("p.uiproj")).unwrap()).unwrap();
+        let rels: Vec<&str> = on_disk["widgets"].as_array().unwrap().iter().filter_map(|w| w["image"].as_str()).collect();
+        assert_eq!(rels, ["icons\\both.blp", "icons\\team.blp", "loose.png"]);
+
+        let listed = engine.list_resources(false, roots());
+        assert!(listed["resources"].as_array().unwrap().iter().all(|r| r["exists"] == json!(true)), "{}", listed);
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn rewrite_library_refs_follows_renamed_files_and_folders() {
+        let mut project = json!({
+            "widgets": [
+                { "id": 1, "type": "button", "image": "ui\\Btn.blp", "clickImage": "war3mapImported\\ui\\btn_down.blp", "hoverImage": "ui2\\btn.blp" },
+                { "id": 2, "type": "panel", "image": "D:/Lib/UI/btn.blp" },
+                { "id": 3, "type": "panel", "image": "C:\\elsewhere\\ui\\btn.blp", "backgroundPreset": "ui" }
+            ],
+            "settings": { "canvasBgImage": "ui/bg/menu.png" }
+        });
+        let roots = LibraryRoots::default();
+        // 文件夹：其下的引用都跟着走，ui2 这类前缀相同的兄弟目录不受影响
+        let n = rewrite_library_refs(&mut project, &roots, "D:\\lib", "ui", "menus\\ui").len();
+        assert_eq!(n, 4);
+        assert_eq!(project["widgets"][0]["image"], json!("menus\\ui\\Btn.blp"));
+        assert_eq!(project["widgets"][0]["clickImage"], json!("war3mapImported\\menus\\ui\\btn_down.blp"));
+        assert_eq!(project["widgets"][0]["hoverImage"], json!("ui2\\btn.blp"));
+        assert_eq!(project["widgets"][1]["image"], json!("D:/lib/menus/ui/btn.blp"));
+        assert_eq!(project["widgets"][2]["image"], json!("C:\\elsewhere\\ui\\btn.blp"));
+        assert_eq!(project["widgets"][2]["backgroundPreset"], json!("ui"));
+        assert_eq!(project["settings"]["canvasBgImage"], json!("menus/ui/bg/menu.png"));
+
+        // 单个文件改名
+        let n = rewrite_library_refs(&mut project, &roots, "D:\\lib", "menus/ui/btn.blp", "menus\\ui\\ok.blp").len();
+        assert_eq!(n, 2);
+        assert_eq!(project["widgets"][0]["image"], json!("menus\\ui\\ok.blp"));
+        assert_eq!(project["widgets"][1]["image"], json!("D:/lib/menus/ui/ok.blp"));
+    }
+
+    #[test]
+    fn rewrite_library_refs_in_text_keeps_layout_and_skips_shadowed_refs() {
+        let base = std::env::temp_dir().join(format!("ui-designer-rewrite-refs-{}", std::process::id()));
+        let (first, second) = (base.join("first"), base.join("second"));
+        for (root, name) in [(&first, "a.blp"), (&second, "a.blp"), (&second, "b.blp")] {
+            std::fs::create_dir_all(root.join("ui")).unwrap();
+            std::fs::write(root.join("ui").join(name), b"BLP1").unwrap();
+        }
+        let second_str = second.to_string_lossy().to_string();
+        let roots = LibraryRoots::new(vec![
+            library_roots::LibraryRoot { path: first.to_string_lossy().to_string(), read_only: false },
+            library_roots::LibraryRoot { path: second_str.clone(), read_only: true },
+        ]);
+        // 键顺序不是字母序、缩进也不是 serde 的默认格式
+        let text = "{\n  \"widgets\": [\n    { \"type\": \"panel\", \"id\": 1, \"image\": \"ui\\\\b.blp\" },\n    { \"type\": \"panel\", \"id\": 2, \"image\": \"ui\\\\a.blp\" }\n  ],\n  \"settings\": {}\n}\n";
+        // ui\a.blp 解析到靠前的 first，second 里改名不应动它
+        let (out, n) = rewrite_library_refs_in_text(text, &roots, &second_str, "ui", "skins").unwrap();
+        assert_eq!(n, 1);
+        assert_eq!(out, text.replace("ui\\\\b.blp", "skins\\\\b.blp"));
+        let _ = std::fs::remove_dir_all(&base);
+    }
 }
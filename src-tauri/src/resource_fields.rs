@@ -0,0 +1,216 @@
+//! 资源字段登记表：哪些字段保存着资源引用、引用的是什么。
+//!
+//! - widget 字段来自 `widget_types` 注册表里标了 `resource` 的属性：通用属性对所有类型生效，
+//!   类型专属属性（如 statusbar 的 `barTexture`）只对该类型生效。给某个类型新增贴图属性时
+//!   只要在注册表里标上 `.resource(..)`，hydrate / list / copy / normalize / validate 都会自动覆盖。
+//! - 项目级字段用点分路径登记（如 `settings.canvasBgImage`）。
+//! - 预设字段（`backgroundPreset`）存的是预设键名，经预设表换算成游戏 / 模板仓内置贴图；
+//!   内置路径不是磁盘文件，只列出、不拷贝也不做路径换算。
+//!
+//! 引擎侧所有资源遍历都经 [`collect`] / [`visit_files_mut`]，不再各自维护字段列表。
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::OnceLock;
+
+use crate::font_files;
+use crate::widget_types;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ResourceKind {
+    Image,
+    Model,
+    Font,
+}
+
+/// 模板仓 `UIBackgrounds` 预设（与前端 `constants/templatePresets.ts` 一致）。
+pub const BACKGROUND_PRESETS: &[(&str, &str)] = &[
+    ("NONE", ""),
+    ("BLACK_TRANSPARENT", "UI\\Widgets\\EscMenu\\Human\\editbox-background.blp"),
+    ("DIALOG", "UI\\Widgets\\Glues\\GlueScreen-DialogBackground.blp"),
+    ("QUEST", "UI\\Widgets\\Quests\\QuestMainBackdrop.blp"),
+    ("ESC_MENU", "UI\\Widgets\\EscMenu\\Human\\human-options-menu-background.blp"),
+    ("TOOLTIP", "UI\\Widgets\\ToolTips\\Human\\human-tooltip-background.blp"),
+    ("HUMAN_BORDER", "UI\\Widgets\\print\\Human\\CommandButton\\human-multipleselection-border.blp"),
+    ("SHUIMO_PANEL", "Texture\\ui\\panel_background.tga"),
+    ("SHUIMO_PANEL_TITLE", "Texture\\ui\\panel_title_background.tga"),
+    ("SHUIMO_CLOSE_BTN", "Texture\\ui\\button_close_background.tga"),
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceField {
+    /// widget 上的字段名，或项目内的点分路径
+    pub path: &'static str,
+    pub kind: ResourceKind,
+    /// 只对该 widget 类型生效；`None` 为所有类型（项目级字段也为 `None`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub widget_type: Option<&'static str>,
+    /// 值是预设键名时的换算表
+    #[serde(skip)]
+    pub presets: Option<&'static [(&'static str, &'static str)]>,
+}
+
+/// 项目级（非 widget）资源字段。
+pub const PROJECT_FIELDS: &[ResourceField] = &[ResourceField {
+    path: "settings.canvasBgImage",
+    kind: ResourceKind::Image,
+    widget_type: None,
+    presets: None,
+}];
+
+/// 所有 widget 资源字段，由类型注册表派生。
+pub fn widget_fields() -> &'static [ResourceField] {
+    static FIELDS: OnceLock<Vec<ResourceField>> = OnceLock::new();
+    FIELDS.get_or_init(|| {
+        let reg = widget_types::registry();
+        let common = reg.common_props.iter().map(|p| (None, p));
+        let typed = reg
+            .types
+            .iter()
+            .flat_map(|t| t.props.iter().map(move |p| (Some(t.widget_type), p)));
+        common
+            .chain(typed)
+            .filter_map(|(widget_type, p)| {
+                Some(ResourceField {
+                    path: p.name,
+                    kind: p.resource?,
+                    widget_type,
+                    presets: p.resource_presets,
+                })
+            })
+            .collect()
+    })
+}
+
+/// 适用于某个 widget 类型的资源字段；同名字段只出现一次。
+pub fn fields_for(widget_type: &str) -> Vec<&'static ResourceField> {
+    let mut out: Vec<&'static ResourceField> = Vec::new();
+    for f in widget_fields() {
+        let applies = match f.widget_type {
+            Some(t) => t == widget_type,
+            None => true,
+        };
+        if applies && !out.iter().any(|o| o.path == f.path) {
+            out.push(f);
+        }
+    }
+    out
+}
+
+/// 值是否指向随项目流转的磁盘文件：非空、不是 URL、不是预设键、不是内置字体。
+pub fn is_file_value(field: &ResourceField, v: &str) -> bool {
+    if v.is_empty() || field.presets.is_some() {
+        return false;
+    }
+    if v.contains("://") || v.starts_with("data:") {
+        return false;
+    }
+    !(field.kind == ResourceKind::Font && font_files::is_builtin_font(v))
+}
+
+/// 一处资源引用。
+#[derive(Debug, Clone)]
+pub struct ResourceRef {
+    /// 所在 widget；项目级字段为 `None`
+    pub widget_id: Option<i64>,
+    pub widget_name: String,
+    pub field: &'static ResourceField,
+    /// 字段原值
+    pub value: String,
+    /// 换算后的引用路径：文件字段等于原值，预设字段为预设表里的贴图
+    pub resolved: String,
+    /// 是磁盘文件（需要换算 / 拷贝 / 检查存在）
+    pub is_file: bool,
+    /// 预设键在表里找不到
+    pub unknown_preset: bool,
+}
+
+fn get_path<'a>(v: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(v, |cur, seg| cur.get(seg))
+}
+
+fn get_path_mut<'a>(v: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(v, |cur, seg| cur.get_mut(seg))
+}
+
+fn make_ref(widget: Option<&Value>, field: &'static ResourceField, value: &str) -> ResourceRef {
+    let (resolved, unknown_preset) = match field.presets {
+        Some(table) => match table.iter().find(|(k, _)| *k == value) {
+            Some((_, path)) => (path.to_string(), false),
+            None => (String::new(), true),
+        },
+        None => (value.to_string(), false),
+    };
+    ResourceRef {
+        widget_id: widget.and_then(|w| w.get("id")).and_then(|x| x.as_i64()),
+        widget_name: widget
+            .and_then(|w| w.get("name"))
+            .and_then(|x| x.as_str())
+            .unwrap_or("")
+            .to_string(),
+        field,
+        value: value.to_string(),
+        resolved,
+        is_file: is_file_value(field, value),
+        unknown_preset,
+    }
+}
+
+/// 列出 widgets 与项目设置里所有非空的资源引用（widget 顺序在前，项目级在后）。
+pub fn collect(widgets: &[Value], settings: &Value) -> Vec<ResourceRef> {
+    let mut out = Vec::new();
+    for w in widgets {
+        let wtype = w.get("type").and_then(|x| x.as_str()).unwrap_or("");
+        for field in fields_for(wtype) {
+            if let Some(v) = get_path(w, field.path).and_then(|x| x.as_str()).filter(|s| !s.is_empty()) {
+                out.push(make_ref(Some(w), field, v));
+            }
+        }
+    }
+    for field in PROJECT_FIELDS {
+        let Some(rest) = field.path.strip_prefix("settings.") else {
+            continue;
+        };
+        if let Some(v) = get_path(settings, rest).and_then(|x| x.as_str()).filter(|s| !s.is_empty()) {
+            out.push(make_ref(None, field, v));
+        }
+    }
+    out
+}
+
+/// 逐个访问"磁盘文件"类的引用值，允许就地改写；回调收到 (widget id, 字段, 值)。
+/// 返回被改写的个数。
+pub fn visit_files_mut<F>(widgets: &mut [Value], settings: &mut Value, mut f: F) -> usize
+where
+    F: FnMut(Option<i64>, &'static ResourceField, &mut String),
+{
+    let mut changed = 0usize;
+    let mut apply = |slot: Option<&mut Value>, id: Option<i64>, field: &'static ResourceField| {
+        let Some(slot) = slot else { return };
+        let Some(cur) = slot.as_str().filter(|s| is_file_value(field, s)) else {
+            return;
+        };
+        let mut next = cur.to_string();
+        f(id, field, &mut next);
+        if next != cur {
+            *slot = Value::String(next);
+            changed += 1;
+        }
+    };
+    for w in widgets.iter_mut() {
+        let wtype = w.get("type").and_then(|x| x.as_str()).unwrap_or("").to_string();
+        let id = w.get("id").and_then(|x| x.as_i64());
+        for field in fields_for(&wtype) {
+            apply(get_path_mut(w, field.path), id, field);
+        }
+    }
+    for field in PROJECT_FIELDS {
+        let Some(rest) = field.path.strip_prefix("settings.") else {
+            continue;
+        };
+        apply(get_path_mut(settings, rest), None, field);
+    }
+    changed
+}
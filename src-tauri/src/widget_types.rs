@@ -0,0 +1,527 @@
+//! widget 类型注册表：每种类型允许的属性、取值类型 / 范围与默认值。
+//!
+//! - `project_engine::make_default_widget` 由这里的默认值生成新 widget；
+//! - `createWidget` / `updateWidgetProps` / `patchWidget` / `updateWhere` 写入字段前按这里校验；
+//! - MCP `ui_describe_widget_types` 原样发布，agent 据此知道每种类型能设置什么。
+//!
+//! 默认字段集与前端 `useWidgets.buildWidget` 保持一致：所有类型共享同一组通用属性，
+//! 类型专属属性只在对应类型上允许。未登记的类型（插件自定义类型）只校验通用属性的取值，
+//! 不拒绝未知字段。
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::font_files;
+use crate::model_files;
+use crate::resource_fields::{ResourceKind, BACKGROUND_PRESETS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PropKind {
+    Integer,
+    Number,
+    Boolean,
+    String,
+    /// 取值限定在 `values` 中的字符串
+    Enum,
+    /// 6 位十六进制 RGB（不带 `#`），空串表示未设置
+    Color,
+    Object,
+    /// 字符串数组（列表 / 下拉菜单的选项）
+    StringList,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropSpec {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub kind: PropKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub values: &'static [&'static str],
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub nullable: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    /// 新建 widget 时写入的默认值；没有默认值的属性是可选的，可以用 null 删除
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+    /// 保存资源引用的属性，见 `resource_fields`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resource: Option<ResourceKind>,
+    /// 取值是预设键名时的换算表
+    #[serde(skip)]
+    pub resource_presets: Option<&'static [(&'static str, &'static str)]>,
+    pub description: &'static str,
+}
+
+impl PropSpec {
+    fn new(name: &'static str, kind: PropKind, description: &'static str) -> Self {
+        Self {
+            name,
+            kind,
+            min: None,
+            max: None,
+            values: &[],
+            nullable: false,
+            read_only: false,
+            default: None,
+            resource: None,
+            resource_presets: None,
+            description,
+        }
+    }
+
+    fn default(mut self, v: Value) -> Self {
+        self.default = Some(v);
+        self
+    }
+
+    fn range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min = min;
+        self.max = max;
+        self
+    }
+
+    fn one_of(mut self, values: &'static [&'static str]) -> Self {
+        self.values = values;
+        self
+    }
+
+    fn resource(mut self, kind: ResourceKind) -> Self {
+        self.resource = Some(kind);
+        self
+    }
+
+    fn preset_resource(mut self, kind: ResourceKind, presets: &'static [(&'static str, &'static str)]) -> Self {
+        self.resource = Some(kind);
+        self.resource_presets = Some(presets);
+        self
+    }
+
+    fn nullable(mut self) -> Self {
+        self.nullable = true;
+        self
+    }
+
+    fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// 校验单个取值；`Value::Null` 只允许出现在可空或可选（无默认值）的属性上。
+    fn check(&self, v: &Value) -> Result<(), String> {
+        if v.is_null() {
+            return if self.nullable || self.default.is_none() {
+                Ok(())
+            } else {
+                Err(format!("{} cannot be null", self.name))
+            };
+        }
+        let ok = match self.kind {
+            PropKind::Integer => v.as_i64().is_some() || v.as_u64().is_some(),
+            PropKind::Number => v.is_number(),
+            PropKind::Boolean => v.is_boolean(),
+            PropKind::String => v.is_string(),
+            PropKind::Enum => v.as_str().is_some_and(|s| self.values.contains(&s)),
+            PropKind::Color => v.as_str().is_some_and(|s| {
+                s.is_empty() || (s.len() == 6 && s.chars().all(|c| c.is_ascii_hexdigit()))
+            }),
+            PropKind::Object => v.is_object(),
+            PropKind::StringList => v
+                .as_array()
+                .is_some_and(|a| a.iter().all(|x| x.is_string())),
+        };
+        if !ok {
+            return Err(format!("invalid {}: expected {}, got {}", self.name, self.expected(), v));
+        }
+        if let Some(n) = v.as_f64() {
+            if self.min.is_some_and(|m| n < m) || self.max.is_some_and(|m| n > m) {
+                return Err(format!("invalid {}: expected {}, got {}", self.name, self.expected(), v));
+            }
+        }
+        Ok(())
+    }
+
+    fn expected(&self) -> String {
+        let base = match self.kind {
+            PropKind::Integer => "integer".to_string(),
+            PropKind::Number => "number".to_string(),
+            PropKind::Boolean => "boolean".to_string(),
+            PropKind::String => "string".to_string(),
+            PropKind::Enum => format!("one of {}", self.values.join(" / ")),
+            PropKind::Color => "6-digit hex color like FFCC00".to_string(),
+            PropKind::Object => "object".to_string(),
+            PropKind::StringList => "array of strings".to_string(),
+        };
+        match (self.min, self.max) {
+            (Some(a), Some(b)) => format!("{} in {}..={}", base, a, b),
+            (Some(a), None) => format!("{} >= {}", base, a),
+            (None, Some(b)) => format!("{} <= {}", base, b),
+            (None, None) => base,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetTypeSpec {
+    #[serde(rename = "type")]
+    pub widget_type: &'static str,
+    pub label: &'static str,
+    /// 对应的 WC3 原生 frame 类型（BlzCreateFrameByType 的 typeName）；通用容器为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_type: Option<&'static str>,
+    pub description: &'static str,
+    /// 类型专属属性；同名时覆盖通用属性（例如 button 的 `text` 默认值为 "按钮"）
+    pub props: Vec<PropSpec>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetRegistry {
+    /// 所有类型共享的属性
+    pub common_props: Vec<PropSpec>,
+    pub types: Vec<WidgetTypeSpec>,
+}
+
+const TEXT_ALIGN_H: &[&str] = &["left", "center", "right"];
+const TEXT_ALIGN_V: &[&str] = &["top", "middle", "bottom"];
+const TEMPLATE_KINDS: &[&str] = &["Panel", "Button", "Dialog", "Text", "Tips", "MessageList", "FDFButton"];
+const RESIZE_RULES: &[&str] = &["keepOffset", "stretch", "center"];
+
+fn common_props() -> Vec<PropSpec> {
+    use PropKind::*;
+    vec![
+        PropSpec::new("id", Integer, "引擎分配的唯一 id").read_only(),
+        PropSpec::new("name", String, "同一 parent 下唯一，名字路径按它解析"),
+        PropSpec::new("type", String, "widget 类型，创建后不能改（换类型请新建控件）").read_only(),
+        PropSpec::new("parentId", Integer, "父节点 id，null 为顶层；改父子关系请用 setParent")
+            .nullable()
+            .default(Value::Null),
+        PropSpec::new("x", Number, "左上角 x（画布绝对坐标）").default(json!(960)),
+        PropSpec::new("y", Number, "左上角 y（画布绝对坐标）").default(json!(540)),
+        PropSpec::new("w", Number, "宽").range(Some(0.0), None).default(json!(100)),
+        PropSpec::new("h", Number, "高").range(Some(0.0), None).default(json!(100)),
+        PropSpec::new("enable", Boolean, "是否可交互").default(json!(true)),
+        PropSpec::new("visible", Boolean, "是否可见").default(json!(true)),
+        PropSpec::new("locked", Boolean, "锁定后引擎拒绝修改该子树；用 lock / unlock 切换").default(json!(false)),
+        PropSpec::new("font", String, "内置字体（如 Fonts\\FZXH1JW.TTF）或全局库里 .ttf / .otf 的绝对路径，空串为默认字体；可选值见 ui_list_fonts")
+            .resource(ResourceKind::Font)
+            .default(json!("")),
+        PropSpec::new("fontSize", Integer, "字号").range(Some(1.0), Some(200.0)).default(json!(14)),
+        PropSpec::new("outlineSize", Integer, "描边粗细").range(Some(0.0), Some(20.0)).default(json!(0)),
+        PropSpec::new("textAlignH", Enum, "水平对齐，对应模板 TextAlign").one_of(TEXT_ALIGN_H).default(json!("left")),
+        PropSpec::new("textAlignV", Enum, "垂直对齐，对应模板 VerticalAlign").one_of(TEXT_ALIGN_V).default(json!("top")),
+        PropSpec::new("textAlign", String, "旧版合并对齐字段，仅为兼容保留；新动作请写 textAlignH / textAlignV"),
+        PropSpec::new("textColor", Color, "文本颜色"),
+        PropSpec::new("text", String, "文本内容").default(json!("")),
+        PropSpec::new("image", String, "背景图，全局库下的绝对路径").resource(ResourceKind::Image).default(json!("")),
+        PropSpec::new("clickImage", String, "按下态图片").resource(ResourceKind::Image).default(json!("")),
+        PropSpec::new("hoverImage", String, "悬浮态图片").resource(ResourceKind::Image).default(json!("")),
+        PropSpec::new("draggable", Boolean, "运行时可拖动").default(json!(false)),
+        PropSpec::new("checked", Boolean, "复选框默认选中").default(json!(false)),
+        PropSpec::new("selectedIndex", Integer, "下拉框当前选项索引").range(Some(0.0), None).default(json!(0)),
+        PropSpec::new("alpha", Integer, "透明度，映射到模板 setAlpha").range(Some(0.0), Some(255.0)),
+        PropSpec::new("padding", Object, "内边距 { top, right, bottom, left }（像素）"),
+        PropSpec::new("backgroundPreset", String, "UIBackgrounds 键名，优先级高于 image")
+            .preset_resource(ResourceKind::Image, BACKGROUND_PRESETS),
+        PropSpec::new("tooltip", String, "悬浮提示"),
+        PropSpec::new("fdfTemplate", String, "ButtonTemplates 键名，button 导出时走 Button.createWithTemplate"),
+        PropSpec::new("templateKind", Enum, "显式指定模板组件类，缺省时按 type 推断").one_of(TEMPLATE_KINDS),
+        PropSpec::new("resizeRule", Enum, "父节点 resizeWidget 时本节点的跟随规则").one_of(RESIZE_RULES),
+        PropSpec::new("meta", Object, "自由元数据，供导出器使用"),
+    ]
+}
+
+fn panel_props() -> Vec<PropSpec> {
+    use PropKind::*;
+    vec![
+        PropSpec::new("showTitleBar", Boolean, "显示标题栏"),
+        PropSpec::new("titleBarHeight", Integer, "标题栏高度").range(Some(0.0), None),
+        PropSpec::new("title", String, "标题文字"),
+        PropSpec::new("titleColor", Color, "标题颜色"),
+        PropSpec::new("showCloseButton", Boolean, "显示关闭按钮"),
+    ]
+}
+
+/// 带数值范围的控件（slider / statusbar）共用的 min / max / value。
+fn range_props(value: f64) -> Vec<PropSpec> {
+    use PropKind::*;
+    vec![
+        PropSpec::new("minValue", Number, "最小值").default(json!(0)),
+        PropSpec::new("maxValue", Number, "最大值，必须大于 minValue").default(json!(100)),
+        PropSpec::new("value", Number, "当前值，落在 minValue..=maxValue 内").default(json!(value)),
+    ]
+}
+
+/// sprite / model 共用的模型引用。
+fn model_props() -> Vec<PropSpec> {
+    use PropKind::*;
+    vec![
+        PropSpec::new("model", String, "模型文件（.mdx / .mdl），全局库下的绝对路径")
+            .resource(ResourceKind::Model)
+            .default(json!("")),
+        PropSpec::new("cameraIndex", Integer, "使用模型里第几个镜头").range(Some(0.0), None).default(json!(0)),
+    ]
+}
+
+/// 类型专属的默认尺寸。
+fn size(w: i64, h: i64) -> Vec<PropSpec> {
+    use PropKind::*;
+    vec![
+        PropSpec::new("w", Number, "宽").range(Some(0.0), None).default(json!(w)),
+        PropSpec::new("h", Number, "高").range(Some(0.0), None).default(json!(h)),
+    ]
+}
+
+fn build_registry() -> WidgetRegistry {
+    use PropKind::*;
+    let text_default = |text: &str| vec![PropSpec::new("text", String, "文本内容").default(json!(text))];
+    let types = vec![
+        WidgetTypeSpec {
+            widget_type: "panel",
+            label: "面板",
+            frame_type: None,
+            description: "容器，可带标题栏 / 背景预设",
+            props: panel_props(),
+        },
+        WidgetTypeSpec {
+            widget_type: "dialog",
+            label: "对话框",
+            frame_type: None,
+            description: "组合控件：创建时生成 panel + 标题 text + 两个 button，属性同 panel",
+            props: panel_props(),
+        },
+        WidgetTypeSpec {
+            widget_type: "button",
+            label: "按钮",
+            frame_type: None,
+            description: "组合控件：创建时附带一个 text 子节点作为 label",
+            props: text_default("按钮")
+                .into_iter()
+                .chain([
+                    PropSpec::new("hoverAlpha", Integer, "悬浮透明度").range(Some(0.0), Some(255.0)),
+                    PropSpec::new("normalAlpha", Integer, "普通态透明度").range(Some(0.0), Some(255.0)),
+                ])
+                .collect(),
+        },
+        WidgetTypeSpec {
+            widget_type: "text",
+            label: "文本",
+            frame_type: None,
+            description: "纯文本",
+            props: text_default("文本"),
+        },
+        WidgetTypeSpec {
+            widget_type: "label",
+            label: "标签",
+            frame_type: None,
+            description: "旧版文本类型，导出时按 text 处理",
+            props: Vec::new(),
+        },
+        WidgetTypeSpec {
+            widget_type: "input",
+            label: "输入框",
+            frame_type: None,
+            description: "旧版输入框；新项目请用 editbox",
+            props: Vec::new(),
+        },
+        WidgetTypeSpec {
+            widget_type: "image",
+            label: "图片",
+            frame_type: None,
+            description: "只显示 image 的面板",
+            props: Vec::new(),
+        },
+        WidgetTypeSpec {
+            widget_type: "checkbox",
+            label: "复选框",
+            frame_type: Some("CHECKBOX"),
+            description: "组合控件：创建时附带一个 text 子节点作为右侧说明文字；checked 表示默认选中",
+            props: [size(24, 24), text_default("复选框")].concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "combobox",
+            label: "下拉框",
+            frame_type: None,
+            description: "旧版下拉框，用 selectedIndex 表示当前选项；新项目请用 popupmenu",
+            props: Vec::new(),
+        },
+        WidgetTypeSpec {
+            widget_type: "editbox",
+            label: "输入框",
+            frame_type: Some("EDITBOX"),
+            description: "可编辑文本框，text 为初始内容",
+            props: [
+                size(200, 32),
+                vec![PropSpec::new("maxLength", Integer, "最大字符数，0 为不限")
+                    .range(Some(0.0), None)
+                    .default(json!(0))],
+            ]
+            .concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "slider",
+            label: "滑动条",
+            frame_type: Some("SLIDER"),
+            description: "数值滑动条",
+            props: [
+                size(200, 24),
+                range_props(0.0),
+                vec![
+                    PropSpec::new("stepSize", Number, "步长，必须大于 0").default(json!(1)),
+                    PropSpec::new("vertical", Boolean, "竖直方向").default(json!(false)),
+                ],
+            ]
+            .concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "listbox",
+            label: "列表",
+            frame_type: Some("LISTBOX"),
+            description: "多行列表，selectedIndex 为当前选中项",
+            props: [
+                size(200, 160),
+                vec![PropSpec::new("items", StringList, "选项文字").default(json!([]))],
+            ]
+            .concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "popupmenu",
+            label: "下拉菜单",
+            frame_type: Some("POPUPMENU"),
+            description: "下拉菜单，selectedIndex 为当前选中项",
+            props: [
+                size(200, 32),
+                vec![PropSpec::new("items", StringList, "选项文字").default(json!([]))],
+            ]
+            .concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "statusbar",
+            label: "进度条",
+            frame_type: Some("SIMPLESTATUSBAR"),
+            description: "进度 / 冷却条，按 value 在 minValue..=maxValue 中的比例填充 barTexture",
+            props: [
+                size(200, 20),
+                range_props(100.0),
+                vec![
+                    PropSpec::new("barTexture", String, "填充条贴图，全局库下的绝对路径")
+                        .resource(ResourceKind::Image)
+                        .default(json!("")),
+                    PropSpec::new("barColor", Color, "填充条着色"),
+                ],
+            ]
+            .concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "sprite",
+            label: "特效",
+            frame_type: Some("SPRITE"),
+            description: "播放 .mdx / .mdl 模型的特效框，常用于按钮高亮、光圈",
+            props: [size(64, 64), model_props()].concat(),
+        },
+        WidgetTypeSpec {
+            widget_type: "model",
+            label: "模型",
+            frame_type: Some("MODEL"),
+            description: "模型展示框，按模型自带镜头渲染 .mdx / .mdl",
+            props: [size(200, 200), model_props()].concat(),
+        },
+    ];
+    WidgetRegistry {
+        common_props: common_props(),
+        types,
+    }
+}
+
+pub fn registry() -> &'static WidgetRegistry {
+    static REGISTRY: OnceLock<WidgetRegistry> = OnceLock::new();
+    REGISTRY.get_or_init(build_registry)
+}
+
+pub fn type_spec(widget_type: &str) -> Option<&'static WidgetTypeSpec> {
+    registry().types.iter().find(|t| t.widget_type == widget_type)
+}
+
+/// 查某类型上的属性定义：类型专属优先，其次通用属性。
+pub fn prop_spec(widget_type: &str, name: &str) -> Option<&'static PropSpec> {
+    type_spec(widget_type)
+        .and_then(|t| t.props.iter().find(|p| p.name == name))
+        .or_else(|| registry().common_props.iter().find(|p| p.name == name))
+}
+
+/// 新建 widget 的默认字段（不含 id / name / type，由调用方填写）。
+pub fn default_fields(widget_type: &str) -> serde_json::Map<String, Value> {
+    let mut out = serde_json::Map::new();
+    let type_props = type_spec(widget_type).map(|t| t.props.as_slice()).unwrap_or(&[]);
+    for p in registry().common_props.iter().chain(type_props) {
+        if let Some(d) = &p.default {
+            out.insert(p.name.to_string(), d.clone());
+        }
+    }
+    out
+}
+
+/// 字段之间的约束（取值范围、选中项下标等），单字段校验覆盖不到。返回诊断文本，供 `validate` 汇总。
+pub fn check_widget(w: &Value) -> Vec<String> {
+    let mut out = Vec::new();
+    let name = w.get("name").and_then(|x| x.as_str()).unwrap_or("");
+    let num = |k: &str| w.get(k).and_then(|x| x.as_f64());
+    if let (Some(lo), Some(hi)) = (num("minValue"), num("maxValue")) {
+        if lo >= hi {
+            out.push(format!("{}: minValue {} must be less than maxValue {}", name, lo, hi));
+        } else if let Some(v) = num("value").filter(|v| *v < lo || *v > hi) {
+            out.push(format!("{}: value {} out of range {}..={}", name, v, lo, hi));
+        }
+    }
+    if let Some(step) = num("stepSize").filter(|s| *s <= 0.0) {
+        out.push(format!("{}: stepSize {} must be positive", name, step));
+    }
+    if let Some(model) = w.get("model").and_then(|x| x.as_str()).filter(|m| !m.is_empty()) {
+        let ext = Path::new(model).extension().and_then(|s| s.to_str()).unwrap_or("");
+        if !model_files::is_model_ext(ext) {
+            out.push(format!("{}: model must be a .mdx or .mdl file, got {}", name, model));
+        }
+    }
+    if let Some(font) = w.get("font").and_then(|x| x.as_str()).filter(|f| !f.is_empty()) {
+        // 这里只看扩展名；是不是内置字体或库里的字体由 `ProjectEngine::validate` 按库根检查
+        let ext = Path::new(font).extension().and_then(|s| s.to_str()).unwrap_or("");
+        if !font_files::is_builtin_font(font) && !font_files::is_font_ext(ext) {
+            out.push(format!("{}: font must be a .ttf or .otf file, got {}", name, font));
+        }
+    }
+    if let Some(items) = w.get("items").and_then(|x| x.as_array()) {
+        let idx = w.get("selectedIndex").and_then(|x| x.as_u64()).unwrap_or(0) as usize;
+        if !items.is_empty() && idx >= items.len() {
+            out.push(format!("{}: selectedIndex {} out of range ({} items)", name, idx, items.len()));
+        }
+    }
+    out
+}
+
+/// 校验写入 `widget_type` 的一组字段。已登记类型拒绝未知属性；未登记类型只校验已知属性的取值。
+pub fn check_props(widget_type: &str, fields: &serde_json::Map<String, Value>) -> Result<(), String> {
+    let registered = type_spec(widget_type).is_some();
+    for (k, v) in fields {
+        match prop_spec(widget_type, k) {
+            Some(p) if p.read_only => return Err(format!("{} is read-only", k)),
+            Some(p) => p.check(v).map_err(|e| format!("{} ({})", e, widget_type))?,
+            None if registered => {
+                return Err(format!(
+                    "unknown property for {}: {} (see ui_describe_widget_types)",
+                    widget_type, k
+                ))
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
@@ -5,6 +5,7 @@ import { writeTextFile, readTextFile, mkdir } from '@tauri-apps/plugin-fs';
 import type { Widget, ImageResource, Animation, Settings } from '../types';
 import type { ExportPlugin, ExportContext } from '../types/plugin';
 import { usePluginSystem } from './usePluginSystem';
+import { WIDGET_RESOURCE_FIELDS, isFileResourceValue } from '../constants/resourceFields';
 
 type CurrentProjectPathRef = Ref<string | null> | (() => string | null) | null;
 
@@ -19,7 +20,6 @@ interface GlobalLibEntry {
 }
 
 const WAR3_PREFIX = 'war3mapImported\\';
-const IMAGE_FIELDS = ['image', 'clickImage', 'hoverImage'] as const;
 
 /** 判断是不是绝对磁盘路径。 */
 const isAbsolutePath = (s: string): boolean => {
@@ -274,9 +274,9 @@ export default plugin;
 
         const widgets = src.map((w) => {
             const next: any = { ...w };
-            for (const f of IMAGE_FIELDS) {
+            for (const f of WIDGET_RESOURCE_FIELDS) {
                 const v = (w as any)[f];
-                if (typeof v === 'string' && v.length > 0) {
+                if (isFileResourceValue(f, v)) {
                     next[f] = rewriteValue(v);
                 }
             }
@@ -628,9 +628,9 @@ export default plugin;
                         // 收集 widgets 引用到的所有绝对路径（三种图像字段都看）
                         const usedAbs = new Set<string>();
                         for (const w of widgetsList.value) {
-                            for (const f of IMAGE_FIELDS) {
+                            for (const f of WIDGET_RESOURCE_FIELDS) {
                                 const v = (w as any)[f];
-                                if (typeof v === 'string' && v.trim()) usedAbs.add(v);
+                                if (isFileResourceValue(f, v)) usedAbs.add(v);
                             }
                         }
 
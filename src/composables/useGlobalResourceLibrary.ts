@@ -14,6 +14,9 @@ import type { ImageResource } from '../types';
  *   - blp/tga → `invoke('*_decode_to_png_base64')`，由 Rust 统一转 PNG data URL
  *   - 其他    → `invoke('read_file_as_base64')` 生成 dataURL
  *
+ * 多个库根：`root` 是可写库（导入 / 删除的目标），`sharedRoots` 是按顺序排在其后的只读共享库。
+ * 列表由后端按顺序合并，相对路径相同时靠前的库遮蔽靠后的。
+ *
  * schema 2.0.0 起，项目不再维护 `imageResources` 登记表——widget 直接引用绝对路径。
  * 所以本 composable 也不再提供 `useInProject / hydrate / cascade` 这类登记辅助函数。
  */
@@ -25,6 +28,72 @@ export interface GlobalResourceEntry {
     size: number;
     mtimeMs: number;
     ext: string;
+    /** `image` / `model`（.mdx / .mdl，无预览）/ `font`（.ttf / .otf） */
+    kind: 'image' | 'model' | 'font';
+    /** 字体 family / style（仅 kind=font，读不出时缺省） */
+    fontFamily?: string;
+    fontStyle?: string;
+    /** 条目所在的库根 */
+    root: string;
+    /** 来自只读的共享库，不能删除 */
+    readOnly: boolean;
+    /** 被遮蔽的、靠后库里同一 relPath 的文件（绝对路径） */
+    shadowed?: string[];
+    /** 内容指纹（来自库根下的索引；导入结果里的条目没有） */
+    hash?: string;
+    /** 图片元数据（仅 kind=image 且文件头可识别时） */
+    image?: LibraryImageMeta;
+    /** 用户标签 / 描述（存在库根下的 `.ui-designer-meta.json`） */
+    tags?: string[];
+    description?: string;
+}
+
+export interface LibraryImageMeta {
+    width: number;
+    height: number;
+    /** 通道数（含 alpha）：灰度 1、灰度+alpha 2、RGB 3、RGBA 4 */
+    channels: number;
+    /** alpha 位深，0 表示没有 alpha 通道 */
+    alphaBits: number;
+    hasAlpha: boolean;
+    /** 仅 BLP */
+    blp?: {
+        version: number;
+        /** `jpeg` / `palette` / `dxt1` / `dxt3` / `dxt5` / `bgra` */
+        compression: string;
+        mipCount: number;
+    };
+}
+
+/** `global_resource_search` 的条件；各项之间是"且"。 */
+export interface LibrarySearchQuery {
+    /** 空白分隔的关键词，每个都要命中文件名 / relPath / 标签 / 描述之一 */
+    text?: string;
+    /** 必须全部带上的标签 */
+    tags?: string[];
+    /** 扩展名（不含点），命中其一即可 */
+    exts?: string[];
+    kind?: 'image' | 'model' | 'font';
+    minWidth?: number;
+    maxWidth?: number;
+    minHeight?: number;
+    maxHeight?: number;
+    hasAlpha?: boolean;
+    offset?: number;
+    /** 默认 50，最多 500 */
+    limit?: number;
+}
+
+export interface LibrarySearchPage {
+    total: number;
+    offset: number;
+    limit: number;
+    items: GlobalResourceEntry[];
+}
+
+export interface ResourceMeta {
+    tags?: string[];
+    description?: string;
 }
 
 export interface SetRootResult {
@@ -40,11 +109,30 @@ export interface ImportWarning {
     message: string;
 }
 
+/** 导入时遇到库里内容相同的文件：复用已有条目 / 跳过 / 照常导入。 */
+export type DuplicatePolicy = 'reuse' | 'skip' | 'import';
+
 export interface ImportResult {
     entries: GlobalResourceEntry[];
     warnings: ImportWarning[];
 }
 
+export interface ProjectRewrite {
+    path: string;
+    /** 改写的引用个数。 */
+    rewritten: number;
+}
+
+/** 后端 `global_resource_rename` / `global_resource_move` 的结果。 */
+export interface RelocateReport {
+    from: string;
+    to: string;
+    movedFiles: number;
+    /** 被改写的项目（没有引用的项目不列出）。 */
+    projects: ProjectRewrite[];
+    warnings: ImportWarning[];
+}
+
 export interface MigrateResult {
     movedCount: number;
     totalBytes: number;
@@ -140,9 +228,26 @@ export function buildLocalPathFromRel(root: string, rel: string): string {
     return `${normRoot}\\${normRel}`;
 }
 
+/** 可写库在前、共享只读库按顺序在后的库根列表（与后端 `LibraryRoot` 一致）。 */
+export function buildLibraryRoots(root: string, sharedRoots: string[] = []): { path: string; readOnly: boolean }[] {
+    const out: { path: string; readOnly: boolean }[] = [];
+    const seen = new Set<string>();
+    const push = (path: string, readOnly: boolean) => {
+        const p = (path || '').trim();
+        const key = p.replace(/\\/g, '/').replace(/\/+$/, '').toLowerCase();
+        if (!p || seen.has(key)) return;
+        seen.add(key);
+        out.push({ path: p, readOnly });
+    };
+    push(root, false);
+    for (const r of sharedRoots) push(r, true);
+    return out;
+}
+
 export function useGlobalResourceLibrary(
     root: Ref<string>,
     message: Ref<string>,
+    sharedRoots?: Ref<string[]>,
 ) {
     const entries = ref<GlobalResourceEntry[]>([]);
     const warnings = ref<ImportWarning[]>([]);
@@ -170,23 +275,29 @@ export function useGlobalResourceLibrary(
         relPath: e.relPath,
         localPath: e.absPath,
         previewUrl: previewMap.value[e.absPath] || '',
+        tags: e.tags,
+        width: e.image?.width,
+        height: e.image?.height,
+        hasAlpha: e.image?.hasAlpha,
     });
 
     const resources = computed<ImageResource[]>(() =>
         entries.value.map((e) => toImageResource(e)),
     );
 
-    const refresh = async () => {
-        if (!root.value) {
+    /** 当前配置的全部库根；写入类命令也带上，由后端据此拒绝写入只读根。 */
+    const currentRoots = () => buildLibraryRoots(root.value, sharedRoots?.value || []);
+
+    const loadEntries = async () => {
+        const roots = currentRoots();
+        if (!roots.length) {
             entries.value = [];
             previewMap.value = {};
             return;
         }
         loading.value = true;
         try {
-            const list = await invoke<GlobalResourceEntry[]>('global_resource_list', {
-                root: root.value,
-            });
+            const list = await invoke<GlobalResourceEntry[]>('global_resource_list_roots', { roots });
             entries.value = list || [];
             // 清掉那些文件已经不在列表里的旧预览（例如刚被删了的条目）。
             const alive = new Set((list || []).map((x) => x.absPath));
@@ -205,6 +316,21 @@ export function useGlobalResourceLibrary(
         }
     };
 
+    // 进行中的刷新；打开项目时要等它完成，relPath 才能按真实的库内容解析到具体库根。
+    let refreshing: Promise<void> | null = null;
+    const refresh = (): Promise<void> => {
+        const run: Promise<void> = loadEntries().finally(() => {
+            if (refreshing === run) refreshing = null;
+        });
+        refreshing = run;
+        return run;
+    };
+
+    /** 等进行中的列表刷新（包括期间又触发的）全部完成；没有刷新时立即返回。 */
+    const whenRefreshed = async () => {
+        while (refreshing) await refreshing;
+    };
+
     const fillPreviews = async () => {
         const snapshot = entries.value.slice();
         for (const entry of snapshot) {
@@ -243,6 +369,7 @@ export function useGlobalResourceLibrary(
         subDir?: string;
         convertToBlp?: boolean;
         overwrite?: boolean;
+        onDuplicate?: DuplicatePolicy;
     }): Promise<ImportResult> => {
         if (!root.value) {
             const msg = '请先在设置里配置全局资源库路径';
@@ -301,10 +428,12 @@ export function useGlobalResourceLibrary(
             const result = await invoke<ImportResult>('global_resource_import', {
                 req: {
                     root: root.value,
+                    roots: currentRoots(),
                     sources: opts.sources,
                     subDir: opts.subDir || '',
                     convertToBlp: !!opts.convertToBlp,
                     overwrite: !!opts.overwrite,
+                    onDuplicate: opts.onDuplicate || 'reuse',
                 },
             });
             warnings.value = result.warnings || [];
@@ -328,8 +457,13 @@ export function useGlobalResourceLibrary(
     /** 从全局库硬删除一条路径（文件或目录）。后端会直接 `fs::remove_*` 掉。 */
     const removeEntry = async (relPath: string): Promise<{ ok: boolean }> => {
         if (!root.value) return { ok: false };
+        const norm = relPath.toLowerCase();
+        if (entries.value.some((e) => e.readOnly && e.relPath.toLowerCase() === norm)) {
+            message.value = '该资源来自只读的共享库，不能删除';
+            return { ok: false };
+        }
         try {
-            await invoke('global_resource_delete', { root: root.value, relPath });
+            await invoke('global_resource_delete', { root: root.value, roots: currentRoots(), relPath });
             invalidatePreview();
             previewMap.value = {};
             await refresh();
@@ -341,6 +475,99 @@ export function useGlobalResourceLibrary(
         }
     };
 
+    /**
+     * 改名 / 移动库内文件或文件夹，并改写 `projects`（.uiproj 文件或目录）里指向它的引用。
+     * `mode` 为 rename 时 `target` 是新名称，为 move 时是目标文件夹（相对库根，空串为根）。
+     */
+    const relocateEntry = async (
+        relPath: string,
+        mode: 'rename' | 'move',
+        target: string,
+        projects: string[],
+    ): Promise<RelocateReport | null> => {
+        if (!root.value) return null;
+        const norm = relPath.toLowerCase();
+        if (entries.value.some((e) => e.readOnly && e.relPath.toLowerCase() === norm)) {
+            message.value = '该资源来自只读的共享库，不能改名或移动';
+            return null;
+        }
+        try {
+            const report = mode === 'rename'
+                ? await invoke<RelocateReport>('global_resource_rename', { root: root.value, roots: currentRoots(), relPath, newName: target, projects })
+                : await invoke<RelocateReport>('global_resource_move', { root: root.value, roots: currentRoots(), relPath, targetDir: target, projects });
+            invalidatePreview();
+            previewMap.value = {};
+            await refresh();
+            return report;
+        } catch (e: any) {
+            console.error('[globalLib] relocate 失败：', e);
+            message.value = (mode === 'rename' ? '改名失败：' : '移动失败：') + (e?.message || String(e));
+            return null;
+        }
+    };
+
+    /** 在全部库根里搜索（后端分页）；未配置库时返回空页。 */
+    const search = async (query: LibrarySearchQuery): Promise<LibrarySearchPage> => {
+        const roots = currentRoots();
+        const empty: LibrarySearchPage = { total: 0, offset: 0, limit: 0, items: [] };
+        if (!roots.length) return empty;
+        try {
+            return await invoke<LibrarySearchPage>('global_resource_search', { roots, query });
+        } catch (e: any) {
+            console.error('[globalLib] search 失败：', e);
+            message.value = '搜索全局资源库失败：' + (e?.message || String(e));
+            return empty;
+        }
+    };
+
+    /** 标签 / 描述只能写在可写库里；共享库条目返回 null。 */
+    const writableEntry = (relPath: string): GlobalResourceEntry | null => {
+        const norm = relPath.toLowerCase();
+        const entry = entries.value.find((e) => e.relPath.toLowerCase() === norm);
+        if (!root.value || !entry || entry.readOnly) {
+            message.value = entry?.readOnly ? '该资源来自只读的共享库，不能修改标签' : '全局资源库里没有该资源';
+            return null;
+        }
+        return entry;
+    };
+
+    /** 设置标签 / 描述：传入的字段整体替换，未传的保留。 */
+    const setMeta = async (
+        relPath: string,
+        meta: { tags?: string[]; description?: string },
+    ): Promise<{ ok: boolean }> => {
+        if (!writableEntry(relPath)) return { ok: false };
+        try {
+            await invoke<ResourceMeta>('global_resource_set_meta', {
+                root: root.value,
+                roots: currentRoots(),
+                relPath,
+                tags: meta.tags,
+                description: meta.description,
+            });
+            await refresh();
+            return { ok: true };
+        } catch (e: any) {
+            console.error('[globalLib] set meta 失败：', e);
+            message.value = '保存标签失败：' + (e?.message || String(e));
+            return { ok: false };
+        }
+    };
+
+    /** 去掉指定标签；不传 tags 时清掉全部标签与描述。 */
+    const removeMeta = async (relPath: string, tags?: string[]): Promise<{ ok: boolean }> => {
+        if (!writableEntry(relPath)) return { ok: false };
+        try {
+            await invoke<ResourceMeta>('global_resource_remove_meta', { root: root.value, roots: currentRoots(), relPath, tags });
+            await refresh();
+            return { ok: true };
+        } catch (e: any) {
+            console.error('[globalLib] remove meta 失败：', e);
+            message.value = '移除标签失败：' + (e?.message || String(e));
+            return { ok: false };
+        }
+    };
+
     const setRoot = async (newRoot: string): Promise<SetRootResult> => {
         try {
             const res = await invoke<SetRootResult>('global_resource_set_root', {
@@ -395,7 +622,7 @@ export function useGlobalResourceLibrary(
     };
 
     watch(
-        root,
+        () => [root.value, ...(sharedRoots?.value || [])].join('\n'),
         () => {
             invalidatePreview();
             previewMap.value = {};
@@ -412,8 +639,14 @@ export function useGlobalResourceLibrary(
         importProgress,
         resetImportProgress,
         refresh,
+        whenRefreshed,
         importSources,
         removeEntry,
+        relocateEntry,
+        search,
+        setMeta,
+        removeMeta,
+        toImageResource,
         setRoot,
         migrate,
         getDiskFree,
@@ -5,7 +5,13 @@ import { readTextFile, writeTextFile } from '@tauri-apps/plugin-fs';
 import type { Widget, Settings, Animation } from '../types';
 import { PROJECT_SCHEMA_VERSION } from '../types';
 import { clampAllWidgetsInPlace } from './widgetCanvasBounds';
+import {
+    WIDGET_RESOURCE_FIELDS,
+    SETTINGS_RESOURCE_FIELDS,
+    isFileResourceValue,
+} from '../constants/resourceFields';
 import type { ExportPlugin } from '../types/plugin';
+import type { GlobalResourceEntry } from './useGlobalResourceLibrary';
 
 interface ExportConfig {
     exportResourcesEnabled: boolean;
@@ -39,7 +45,6 @@ function versionGte(a: string | undefined, b: string): boolean {
 
 const MIN_SUPPORTED_SCHEMA = '2.0.0';
 
-const IMAGE_FIELDS = ['image', 'clickImage', 'hoverImage'] as const;
 
 /**
  * 读取项目文本：优先走后端 command（不受 fs:scope 限制），失败再回退 plugin-fs。
@@ -72,11 +77,21 @@ const isAbsolutePath = (s: string): boolean => {
     return false;
 };
 
+/**
+ * save/load 时 abs↔rel 换算用到的库信息。
+ * - `roots`：可写库在前、共享只读库按顺序在后。
+ * - `byRel`：合并后的库列表（小写 relPath → absPath），已按库根顺序处理遮蔽。
+ */
+interface LibraryPaths {
+    roots: string[];
+    byRel: Map<string, string>;
+}
+
 /** 把运行时的 widget.image（可能是绝对路径 / `war3mapImported\xxx` / 空）转换为落盘 relPath。
- *  - 绝对路径：若位于 globalRoot 下，相对化；否则保留原路径（兜底，跨机器可能失效，但不丢）。
+ *  - 绝对路径：若位于某个库根下，相对于该根；否则保留原路径（兜底，跨机器可能失效，但不丢）。
  *  - `war3mapImported\xxx`：剥前缀得到 relPath。
  *  - 其它：原样返回。 */
-function absToRel(abs: string, globalRoot: string): string {
+function absToRel(abs: string, lib: LibraryPaths): string {
     if (!abs) return '';
     // `war3mapImported\xxx` 兼容
     const stripped = abs.replace(/^war3mapImported[\\/]+/i, '');
@@ -85,13 +100,12 @@ function absToRel(abs: string, globalRoot: string): string {
     if (!isAbsolutePath(abs)) {
         return toBackslash(abs);
     }
-    if (!globalRoot) {
-        return toBackslash(abs);
-    }
-    const rootN = toForwardSlash(globalRoot).replace(/\/+$/, '').toLowerCase();
     const absN = toForwardSlash(abs);
-    if (absN.toLowerCase().startsWith(rootN + '/')) {
-        return toBackslash(absN.slice(rootN.length + 1));
+    for (const globalRoot of lib.roots) {
+        const rootN = toForwardSlash(globalRoot).replace(/\/+$/, '').toLowerCase();
+        if (absN.toLowerCase().startsWith(rootN + '/')) {
+            return toBackslash(absN.slice(rootN.length + 1));
+        }
     }
     // 不在全局库下——保留绝对路径；等下次打开时会被识别为"库外引用"。
     return abs;
@@ -99,35 +113,59 @@ function absToRel(abs: string, globalRoot: string): string {
 
 /** 把落盘的 relPath（或老格式的 `war3mapImported\xxx` / 绝对路径兜底）转换为运行时绝对路径。
  *  - 绝对路径：原样。
- *  - 其它：拼 globalRoot + rel。没有 globalRoot 时留原样（画布会显示"预览缺失"）。 */
-function relToAbs(rel: string, globalRoot: string): string {
+ *  - 其它：按库根顺序取第一个包含该文件的库；都没有时拼首个库根。
+ *    没有库根时留原样（画布会显示"预览缺失"）。
+ *  - 字体例外：库里找不到的相对路径是游戏内（MPQ）字体，原样保留。 */
+function relToAbs(rel: string, lib: LibraryPaths, field?: string): string {
     if (!rel) return '';
     const stripped = rel.replace(/^war3mapImported[\\/]+/i, '');
     if (isAbsolutePath(stripped)) return toForwardSlash(stripped);
+    const hit = lib.byRel.get(toBackslash(stripped).replace(/^\\+/, '').toLowerCase());
+    if (hit) return toForwardSlash(hit);
+    if (field === 'font') return rel;
+    // 带 `.` / `..` 段的相对路径会越出库根，与后端一致不拼成库路径
+    if (stripped.split(/[\\/]/).some((seg) => seg === '.' || seg === '..')) return rel;
+    const globalRoot = lib.roots[0];
     if (!globalRoot) return stripped; // 保留 rel，下次配好再开
     const root = toForwardSlash(globalRoot).replace(/\/+$/, '');
     return `${root}/${toForwardSlash(stripped)}`;
 }
 
-/** 就地把一组 widget 的图像字段做转换；返回新对象数组。 */
+/** 把一组 widget 的资源字段做转换；返回新对象数组。 */
 function transformWidgetImages(
     widgets: Widget[],
     mode: 'absToRel' | 'relToAbs',
-    globalRoot: string,
+    lib: LibraryPaths,
 ): Widget[] {
-    const fn = mode === 'absToRel' ? absToRel : relToAbs;
+    const fn: (v: string, lib: LibraryPaths, field?: string) => string = mode === 'absToRel' ? absToRel : relToAbs;
     return widgets.map((w) => {
         const next: any = { ...w };
-        for (const f of IMAGE_FIELDS) {
+        for (const f of WIDGET_RESOURCE_FIELDS) {
             const v = (w as any)[f];
-            if (typeof v === 'string' && v.length > 0) {
-                next[f] = fn(v, globalRoot);
+            if (isFileResourceValue(f, v)) {
+                next[f] = fn(v, lib, f);
             }
         }
         return next as Widget;
     });
 }
 
+/** 项目设置里的资源字段（如 canvasBgImage）做同样的转换。 */
+function transformSettingsImages<T extends Record<string, any>>(
+    settings: T,
+    mode: 'absToRel' | 'relToAbs',
+    lib: LibraryPaths,
+): T {
+    const fn: (v: string, lib: LibraryPaths, field?: string) => string = mode === 'absToRel' ? absToRel : relToAbs;
+    const next: Record<string, any> = { ...settings };
+    for (const f of SETTINGS_RESOURCE_FIELDS) {
+        if (isFileResourceValue(f, next[f])) {
+            next[f] = fn(next[f], lib, f);
+        }
+    }
+    return next as T;
+}
+
 export function useProjectFile(
     widgetsList: Ref<Widget[]>,
     selectedIds: Ref<number[]>,
@@ -147,28 +185,37 @@ export function useProjectFile(
     showWelcome: Ref<boolean>,
     /** 全局资源库根目录（来自 settings.globalResourceRootPath）。save/load 时用于 abs↔rel 转换。 */
     globalResourceRoot: Ref<string>,
+    /** 共享只读库根（settings.globalResourceSharedRoots），排在可写库之后。 */
+    sharedRoots?: Ref<string[]>,
+    /** 合并后的全局库列表，用来把 relPath 解析到具体是哪个库里的文件。 */
+    libraryEntries?: Ref<GlobalResourceEntry[]>,
+    /** 等全局库列表刷新完成；载入项目前调用，避免按还没列出的库把共享库文件解析到可写库下。 */
+    libraryReady?: () => Promise<void>,
 ) {
     const projectFileInput = ref<HTMLInputElement | null>(null);
     const currentProjectPath = ref<string | null>(null);
 
+    const libraryPaths = (): LibraryPaths => ({
+        roots: [globalResourceRoot.value, ...(sharedRoots?.value || [])].filter((r) => !!r && !!r.trim()),
+        byRel: new Map((libraryEntries?.value || []).map((e) => [e.relPath.toLowerCase(), e.absPath])),
+    });
+
     const buildProjectJson = (): string => {
         // 用户级字段从项目设置里摘掉（跟随本机，不跨机器）
         const {
             globalResourceRootPath: _grlPath,
+            globalResourceSharedRoots: _sharedRoots,
             defaultConvertToBlp: _convert,
             ...projectScopedSettings
         } = settings.value as any;
 
-        const widgetsForDisk = transformWidgetImages(
-            widgetsList.value,
-            'absToRel',
-            globalResourceRoot.value || '',
-        );
+        const lib = libraryPaths();
+        const widgetsForDisk = transformWidgetImages(widgetsList.value, 'absToRel', lib);
 
         const data = {
             schemaVersion: PROJECT_SCHEMA_VERSION,
             widgets: widgetsForDisk,
-            settings: projectScopedSettings,
+            settings: transformSettingsImages(projectScopedSettings, 'absToRel', lib),
             // 动画
             animations: animations.value,
             nextAnimId: nextAnimIdAnim.value,
@@ -184,20 +231,17 @@ export function useProjectFile(
         return JSON.stringify(data, null, 2);
     };
 
-    const applyLoadedData = (data: any): boolean => {
+    const applyLoadedData = async (data: any): Promise<boolean> => {
         if (!versionGte(data.schemaVersion, MIN_SUPPORTED_SCHEMA)) {
             message.value =
                 `项目格式已不兼容（需要 schema ${MIN_SUPPORTED_SCHEMA}+，当前=${data.schemaVersion || '<missing>'}）。` +
                 '请在新版 ui-designer 中重建项目。';
             return false;
         }
+        await libraryReady?.();
         if (Array.isArray(data.widgets)) {
             pushHistory();
-            const hydrated = transformWidgetImages(
-                data.widgets as Widget[],
-                'relToAbs',
-                globalResourceRoot.value || '',
-            );
+            const hydrated = transformWidgetImages(data.widgets as Widget[], 'relToAbs', libraryPaths());
             widgetsList.value = hydrated;
             selectedIds.value = [];
             nextId.value =
@@ -205,8 +249,9 @@ export function useProjectFile(
         }
         if (data.settings) {
             // 把用户级（跨机器）字段剥掉
-            const incoming = { ...data.settings };
+            const incoming = transformSettingsImages({ ...data.settings }, 'relToAbs', libraryPaths());
             delete incoming.globalResourceRootPath;
+            delete incoming.globalResourceSharedRoots;
             delete incoming.defaultConvertToBlp;
             settings.value = {
                 ...settings.value,
@@ -289,7 +334,7 @@ export function useProjectFile(
         try {
             const json = await readProjectText(filePath);
             const data = JSON.parse(json) as any;
-            if (!applyLoadedData(data)) return;
+            if (!(await applyLoadedData(data))) return;
             currentProjectPath.value = filePath;
             addRecentProject(filePath);
             showWelcome.value = false;
@@ -323,11 +368,11 @@ export function useProjectFile(
         if (!file) return;
 
         const reader = new FileReader();
-        reader.onload = () => {
+        reader.onload = async () => {
             try {
                 const json = reader.result as string;
                 const data = JSON.parse(json) as any;
-                if (!applyLoadedData(data)) {
+                if (!(await applyLoadedData(data))) {
                     input.value = '';
                     return;
                 }
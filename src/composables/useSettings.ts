@@ -23,6 +23,7 @@ export function useSettings() {
         canvasBgImage: '',
         // 全局资源库（跨项目共享）：空串 = 未配置
         globalResourceRootPath: '',
+        globalResourceSharedRoots: [],
         defaultConvertToBlp: true,
     });
 
@@ -47,6 +48,7 @@ export function useSettings() {
             canvasBgColor: '#1a1a1a',
             canvasBgImage: '',
             globalResourceRootPath: '',
+            globalResourceSharedRoots: [],
             defaultConvertToBlp: true,
         };
         applyCanvasClamp(settings.value);
@@ -0,0 +1,14 @@
+/**
+ * 对应 WC3 原生 frame 类型的控件（与引擎 widget 类型注册表的 frameType 一致）。
+ * Lua 导出写 `frame_type`，结构化 JSON 导出在控件树节点上写 `frameType`。
+ */
+export const NATIVE_FRAME_TYPES: Record<string, string> = {
+    editbox: 'EDITBOX',
+    slider: 'SLIDER',
+    checkbox: 'CHECKBOX',
+    listbox: 'LISTBOX',
+    popupmenu: 'POPUPMENU',
+    statusbar: 'SIMPLESTATUSBAR',
+    sprite: 'SPRITE',
+    model: 'MODEL',
+};
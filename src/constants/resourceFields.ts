@@ -0,0 +1,25 @@
+/**
+ * 资源字段登记表（与引擎 `resource_fields` / 类型注册表里标了 `resource` 的属性一致）。
+ *
+ * 保存 / 打开时的 abs↔rel 换算、导出时的资源收集与 war3 路径改写都只遍历这里登记的字段；
+ * 新增贴图属性时在此补一项即可。
+ */
+import { isBuiltinFont } from './templatePresets';
+
+/** widget 上保存资源路径的字段（各类型专属字段也列在这里，不存在时自然跳过）。 */
+export const WIDGET_RESOURCE_FIELDS = ['image', 'clickImage', 'hoverImage', 'barTexture', 'model', 'font'] as const;
+
+/** 项目设置里保存资源路径的字段。 */
+export const SETTINGS_RESOURCE_FIELDS = ['canvasBgImage'] as const;
+
+export type WidgetResourceField = (typeof WIDGET_RESOURCE_FIELDS)[number];
+
+/**
+ * 字段值是否指向随项目流转的磁盘文件：非空、不是 URL、不是内置字体。
+ * `backgroundPreset` 存的是预设键名，不在此列。
+ */
+export function isFileResourceValue(field: string, value: unknown): value is string {
+    if (typeof value !== 'string' || !value.trim()) return false;
+    if (value.includes('://') || value.startsWith('data:')) return false;
+    return !(field === 'font' && isBuiltinFont(value));
+}
@@ -89,6 +89,22 @@ export const TextFonts = {
 
 export type TextFontKey = keyof typeof TextFonts;
 
+/**
+ * 游戏客户端 / 模板仓自带的字体（与引擎 `font_files::BUILTIN_FONTS` 一致）。
+ * widget.font 为这些值时是 MPQ 内路径而非磁盘文件，不参与全局库路径换算与资源拷贝。
+ */
+export const BuiltinFonts = [
+    'Fonts\\FRIZQT__.TTF',
+    'Fonts\\FZXH1JW.TTF',
+    'Fonts\\MORPHEUS.TTF',
+    'UI\\hpbar\\ZiTi.ttf',
+] as const;
+
+export function isBuiltinFont(value: string): boolean {
+    const norm = (value || '').trim().replace(/\//g, '\\').toLowerCase();
+    return BuiltinFonts.some((f) => f.toLowerCase() === norm);
+}
+
 /**
  * 从值反查 key（便于在 UI 显示"预设名 + 路径"）。
  */
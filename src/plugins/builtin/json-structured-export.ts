@@ -4,8 +4,12 @@ import {
     WC3_FRAME_UI_NORM_HEIGHT,
     WC3_FRAME_UI_NORM_WIDTH,
 } from '../../constants/wc3CanvasLimits';
+import { NATIVE_FRAME_TYPES } from '../../constants/nativeFrameTypes';
+import { WIDGET_RESOURCE_FIELDS } from '../../constants/resourceFields';
 
 type WidgetTreeNode = Widget & {
+    /** 原生 frame 类型的控件才有，与引擎导出一致 */
+    frameType?: string;
     children: WidgetTreeNode[];
 };
 
@@ -16,8 +20,10 @@ const buildWidgetTree = (widgets: Widget[]): WidgetTreeNode[] => {
     const roots: WidgetTreeNode[] = [];
 
     widgets.forEach((widget) => {
+        const frameType = NATIVE_FRAME_TYPES[widget.type];
         nodeMap.set(widget.id, {
             ...cloneWidget(widget),
+            ...(frameType ? { frameType } : {}),
             children: [],
         });
     });
@@ -49,9 +55,10 @@ function exportFunction(context: ExportContext): string {
 
     const usedResourceValues = new Set<string>();
     widgets.forEach((widget) => {
-        if (widget.image) usedResourceValues.add(widget.image);
-        if (widget.clickImage) usedResourceValues.add(widget.clickImage);
-        if (widget.hoverImage) usedResourceValues.add(widget.hoverImage);
+        for (const field of WIDGET_RESOURCE_FIELDS) {
+            const value = widget[field];
+            if (value) usedResourceValues.add(value);
+        }
     });
 
     const payload = {
@@ -1,5 +1,6 @@
 import type { ExportPluginModule, ExportContext } from '../../types/plugin';
 import type { Widget } from '../../types';
+import { NATIVE_FRAME_TYPES } from '../../constants/nativeFrameTypes';
 
 /**
  * Lua 输出插件
@@ -83,6 +84,11 @@ function convertImagePath(imagePath: string, resourcePath: string, codePath: str
     return relativePath;
 }
 
+// Lua 长括号字符串；内容里的 `]]` 会提前结束字符串，与引擎 escape_lua_text 一样拆开
+function longString(text: unknown): string {
+    return `[[${String(text ?? '').replace(/\]\]/g, '] ]')}]]`;
+}
+
 // 导出函数
 function exportFunction(context: ExportContext): string {
     const { widgets, options, animations, settings } = context;
@@ -114,7 +120,8 @@ function exportFunction(context: ExportContext): string {
     lua += '        image = Const.Texture.blank,\n';
     lua += '        -- 以下子控件由设计器生成\n';
 
-    const emitWidget = (w: Widget, indent: number) => {
+    // level：同级多于一个时按数组顺序（即绘制顺序，0 在最下）给出层级，与引擎导出一致
+    const emitWidget = (w: Widget, indent: number, level?: number) => {
         const pad = ' '.repeat(indent);
         let luaType = 'Panel';
         if (w.type === 'label' || w.type === 'input') luaType = 'Text';
@@ -127,6 +134,9 @@ function exportFunction(context: ExportContext): string {
         lua += `${pad}    y = ${Math.round(w.y)},\n`;
         lua += `${pad}    w = ${Math.round(w.w)},\n`;
         lua += `${pad}    h = ${Math.round(w.h)},\n`;
+        if (level !== undefined) {
+            lua += `${pad}    level = ${level},\n`;
+        }
 
         // 文本字段：
         // - Panel 类型完全不导出 text
@@ -134,7 +144,7 @@ function exportFunction(context: ExportContext): string {
         if (luaType !== 'Panel') {
             const text = w.text ?? '';
             if (String(text).length > 0) {
-                lua += `${pad}    text = [[${text}]],\n`;
+                lua += `${pad}    text = ${longString(text)},\n`;
             }
         }
 
@@ -144,7 +154,17 @@ function exportFunction(context: ExportContext): string {
             if (options.resourcePath && options.codePath) {
                 imagePath = convertImagePath(w.image, options.resourcePath, options.codePath);
             }
-            lua += `${pad}    image = [[${imagePath}]],\n`;
+            lua += `${pad}    image = ${longString(imagePath)},\n`;
+        }
+
+        if (w.font) {
+            let fontPath = w.font;
+            // 只有库里的字体是磁盘绝对路径；内置 / 游戏内字体是 MPQ 路径，原样写出
+            const onDisk = /^([a-zA-Z]:[\\/]|\\\\|\/)/.test(w.font);
+            if (options.resourcePath && options.codePath && onDisk) {
+                fontPath = convertImagePath(w.font, options.resourcePath, options.codePath);
+            }
+            lua += `${pad}    font = ${longString(fontPath)},\n`;
         }
 
         if (w.type === 'checkbox') {
@@ -153,12 +173,51 @@ function exportFunction(context: ExportContext): string {
         if (w.type === 'combobox') {
             lua += `${pad}    selected_index = ${w.selectedIndex || 0},\n`;
         }
+        const frameType = NATIVE_FRAME_TYPES[w.type];
+        if (frameType) {
+            lua += `${pad}    frame_type = '${frameType}',\n`;
+        }
+        if (w.type === 'editbox') {
+            lua += `${pad}    max_length = ${w.maxLength || 0},\n`;
+        }
+        if (w.type === 'slider' || w.type === 'statusbar') {
+            lua += `${pad}    min_value = ${w.minValue ?? 0},\n`;
+            lua += `${pad}    max_value = ${w.maxValue ?? 100},\n`;
+            lua += `${pad}    value = ${w.value ?? 0},\n`;
+        }
+        if (w.type === 'slider') {
+            lua += `${pad}    step_size = ${w.stepSize ?? 1},\n`;
+            lua += `${pad}    vertical = ${w.vertical ? 'true' : 'false'},\n`;
+        }
+        if (w.type === 'statusbar' && w.barTexture) {
+            let barPath = w.barTexture;
+            if (options.resourcePath && options.codePath) {
+                barPath = convertImagePath(w.barTexture, options.resourcePath, options.codePath);
+            }
+            lua += `${pad}    bar_texture = ${longString(barPath)},\n`;
+        }
+        if (w.type === 'statusbar' && w.barColor) {
+            lua += `${pad}    bar_color = ${longString(w.barColor)},\n`;
+        }
+        if ((w.type === 'sprite' || w.type === 'model') && w.model) {
+            let modelPath = w.model;
+            if (options.resourcePath && options.codePath) {
+                modelPath = convertImagePath(w.model, options.resourcePath, options.codePath);
+            }
+            lua += `${pad}    model = ${longString(modelPath)},\n`;
+            lua += `${pad}    camera_index = ${w.cameraIndex || 0},\n`;
+        }
+        if (w.type === 'listbox' || w.type === 'popupmenu') {
+            const items = (w.items || []).map(longString).join(', ');
+            lua += `${pad}    items = { ${items} },\n`;
+            lua += `${pad}    selected_index = ${w.selectedIndex || 0},\n`;
+        }
 
         const children = widgets.filter((child) => child.parentId === w.id);
         if (children.length) {
             lua += `${pad}    -- children\n`;
-            children.forEach((child) => {
-                emitWidget(child, indent + 4);
+            children.forEach((child, i) => {
+                emitWidget(child, indent + 4, children.length > 1 ? i : undefined);
             });
         }
 
@@ -166,9 +225,8 @@ function exportFunction(context: ExportContext): string {
     };
 
     // 只导出根节点（parentId 为 null）
-    widgets
-        .filter((w) => w.parentId == null)
-        .forEach((w) => emitWidget(w, 8));
+    const roots = widgets.filter((w) => w.parentId == null);
+    roots.forEach((w, i) => emitWidget(w, 8, roots.length > 1 ? i : undefined));
 
     lua += '    })\n';
     lua += 'end\n\n';
@@ -184,8 +242,8 @@ function exportFunction(context: ExportContext): string {
             lua += `    [${wid}] = {\n`;
             list.forEach((a) => {
                 lua += '        {\n';
-                lua += `            name = [[${a.name || ''}]],\n`;
-                lua += `            type = [[${a.type || ''}]],\n`;
+                lua += `            name = ${longString(a.name)},\n`;
+                lua += `            type = ${longString(a.type)},\n`;
                 lua += `            duration = ${a.duration || 0},\n`;
                 lua += `            delay = ${a.delay || 0},\n`;
                 lua += `            loop = ${a.loop ? 'true' : 'false'},\n`;
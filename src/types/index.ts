@@ -65,6 +65,28 @@ export interface Widget {
     checked?: boolean;
     selectedIndex?: number;
 
+    // 原生 frame 类型（editbox / slider / listbox / popupmenu / statusbar / sprite / model）
+    /** editbox 最大字符数，0 为不限 */
+    maxLength?: number;
+    /** slider / statusbar 取值范围与当前值 */
+    minValue?: number;
+    maxValue?: number;
+    value?: number;
+    /** slider 步长 */
+    stepSize?: number;
+    /** slider 竖直方向 */
+    vertical?: boolean;
+    /** listbox / popupmenu 选项文字 */
+    items?: string[];
+    /** statusbar 填充条贴图（全局库绝对路径） */
+    barTexture?: string;
+    /** statusbar 填充条着色（十六进制 RGB） */
+    barColor?: string;
+    /** sprite / model 的模型文件（.mdx / .mdl，全局库绝对路径） */
+    model?: string;
+    /** sprite / model 使用的镜头序号 */
+    cameraIndex?: number;
+
     // 通用外观
     /** 0-255 透明度；映射到模板 setAlpha */
     alpha?: number;
@@ -117,6 +139,11 @@ export interface Settings {
      * **不会写入 .uiproj**。为空时"导入资源…"会弹首次引导对话框。
      */
     globalResourceRootPath?: string;
+    /**
+     * 共享只读库根（如团队网络盘），按顺序排在 `globalResourceRootPath` 之后：
+     * 相对路径先在可写库里找，再依次在这些库里找；导入只写可写库。用户级设置，不写入 .uiproj。
+     */
+    globalResourceSharedRoots?: string[];
     /**
      * 导入时是否默认把非 BLP 转成 BLP。用户级设置，不写入 .uiproj。
      */
@@ -139,6 +166,12 @@ export interface ImageResource {
     localPath?: string;
     relPath?: string;
     previewUrl?: string;
+    /** 全局库条目的用户标签 */
+    tags?: string[];
+    /** 图片尺寸与是否带 alpha（来自全局库索引，读不出时缺省） */
+    width?: number;
+    height?: number;
+    hasAlpha?: boolean;
 }
 
 export interface Animation {